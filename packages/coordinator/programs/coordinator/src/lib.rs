@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 
 pub mod errors;
 pub mod state;
@@ -28,6 +29,7 @@ pub mod coordinator {
         state.max_batch_size = max_batch_size;
         state.batch_counter = 0;
         state.bump = ctx.bumps.coordinator_state;
+        state.encryption_key_fingerprint = [0u8; 32];
 
         msg!("Coordinator initialized with k={}", min_batch_size);
         Ok(())
@@ -126,6 +128,59 @@ pub mod coordinator {
         msg!("Batch {} executed", batch.id);
         Ok(())
     }
+
+    /// Publish the fingerprint of the proxy's active encryption keys
+    ///
+    /// Grows coordinator state accounts created before the fingerprint field
+    /// existed, so this must run once after upgrading the program before the
+    /// other instructions can load the state again.
+    pub fn set_encryption_key(
+        ctx: Context<SetEncryptionKey>,
+        fingerprint: [u8; 32],
+    ) -> Result<()> {
+        let state = ctx.accounts.coordinator_state.to_account_info();
+
+        {
+            let data = state.try_borrow_data()?;
+            require!(
+                data.len() >= CoordinatorState::LEGACY_SIZE
+                    && &data[..8] == CoordinatorState::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            let authority = Pubkey::try_from(&data[8..40])
+                .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+            require_keys_eq!(
+                authority,
+                ctx.accounts.authority.key(),
+                CoordinatorError::Unauthorized
+            );
+        }
+
+        if state.data_len() < CoordinatorState::SIZE {
+            let rent = Rent::get()?.minimum_balance(CoordinatorState::SIZE);
+            let shortfall = rent.saturating_sub(state.lamports());
+            if shortfall > 0 {
+                transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: state.clone(),
+                        },
+                    ),
+                    shortfall,
+                )?;
+            }
+            state.resize(CoordinatorState::SIZE)?;
+        }
+
+        let mut data = state.try_borrow_mut_data()?;
+        data[CoordinatorState::FINGERPRINT_OFFSET..CoordinatorState::SIZE]
+            .copy_from_slice(&fingerprint);
+
+        msg!("Encryption key fingerprint updated");
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -216,3 +271,20 @@ pub struct CompleteBatch<'info> {
     )]
     pub executor: Signer<'info>,
 }
+
+#[derive(Accounts)]
+pub struct SetEncryptionKey<'info> {
+    /// CHECK: deserialized by hand because accounts created before the
+    /// fingerprint field are too short for `Account<CoordinatorState>`;
+    /// the instruction checks the discriminator and authority
+    #[account(
+        mut,
+        seeds = [CoordinatorState::SEED],
+        bump,
+        owner = crate::ID
+    )]
+    pub coordinator_state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
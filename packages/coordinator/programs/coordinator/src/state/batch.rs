@@ -16,7 +16,7 @@ pub struct Batch {
     pub status: BatchStatus,
     /// Number of queries in this batch
     pub query_count: u8,
-    /// Query hashes (SHA-256, 32 bytes each)
+    /// Canonical query hashes (SHA-256, see `test-vectors/README.md`)
     pub query_hashes: Vec<[u8; 32]>,
     /// Submitters for each query (for reward distribution)
     pub submitters: Vec<Pubkey>,
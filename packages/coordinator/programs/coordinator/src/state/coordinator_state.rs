@@ -13,9 +13,15 @@ pub struct CoordinatorState {
     pub batch_counter: u64,
     /// Bump seed for PDA
     pub bump: u8,
+    /// SHA-256 fingerprint of the proxy's active encryption keys (zero if unset)
+    pub encryption_key_fingerprint: [u8; 32],
 }
 
 impl CoordinatorState {
     pub const SEED: &'static [u8] = b"coordinator";
-    pub const SIZE: usize = 8 + 32 + 1 + 1 + 8 + 1;
+    pub const SIZE: usize = Self::LEGACY_SIZE + 32;
+    /// Size of accounts created before `encryption_key_fingerprint` was added
+    pub const LEGACY_SIZE: usize = 8 + 32 + 1 + 1 + 8 + 1;
+    /// Byte offset of `encryption_key_fingerprint` in the account data
+    pub const FINGERPRINT_OFFSET: usize = Self::LEGACY_SIZE;
 }
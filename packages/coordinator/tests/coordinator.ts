@@ -149,4 +149,46 @@ describe("coordinator", () => {
             expect(batch.resultsHash).to.not.be.null;
         });
     });
+
+    describe("set_encryption_key", () => {
+        it("publishes the encryption key fingerprint", async () => {
+            const fingerprint = hashQuery("encryption-keys:v1");
+
+            await program.methods
+                .setEncryptionKey(fingerprint)
+                .accounts({
+                    coordinatorState: coordinatorStatePda,
+                    authority: provider.wallet.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                })
+                .rpc();
+
+            const state = await program.account.coordinatorState.fetch(coordinatorStatePda);
+            expect(Array.from(state.encryptionKeyFingerprint)).to.deep.equal(fingerprint);
+        });
+
+        it("rejects a non-authority signer", async () => {
+            const intruder = anchor.web3.Keypair.generate();
+            const signature = await provider.connection.requestAirdrop(
+                intruder.publicKey,
+                anchor.web3.LAMPORTS_PER_SOL
+            );
+            await provider.connection.confirmTransaction(signature);
+
+            try {
+                await program.methods
+                    .setEncryptionKey(hashQuery("encryption-keys:forged"))
+                    .accounts({
+                        coordinatorState: coordinatorStatePda,
+                        authority: intruder.publicKey,
+                        systemProgram: anchor.web3.SystemProgram.programId,
+                    })
+                    .signers([intruder])
+                    .rpc();
+                expect.fail("set_encryption_key should reject a non-authority signer");
+            } catch (error) {
+                expect(String(error)).to.include("Unauthorized");
+            }
+        });
+    });
 });
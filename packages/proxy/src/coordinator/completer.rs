@@ -0,0 +1,463 @@
+//! Submission of `complete_batch` after a coordinated batch executes
+//!
+//! Once the proxy has executed a verified batch it records the results hash
+//! on-chain, moving the batch from `Finalized` to `Executed`. The executor
+//! keypair must be the coordinator authority.
+//!
+//! Retry policy: transport failures (timeouts, dropped connections, expired
+//! blockhashes) are retried with exponential backoff. Errors returned by the
+//! program or the runtime are not retried. Before every retry, and after any
+//! failure, the batch is re-read on-chain: if it is already `Executed` an
+//! earlier attempt landed and completion is considered confirmed.
+//!
+//! Final statuses are kept for a retention window, then dropped.
+
+use super::key_publisher::KeyPublisher;
+use super::reader::{CoordinatorReader, OnChainBatchStatus};
+use crate::error::{ProxyError, ProxyResult};
+use crate::upstream::CallSlots;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Anchor discriminator of the `complete_batch` instruction
+const COMPLETE_BATCH_DISCRIMINATOR: [u8; 8] = [87, 141, 249, 230, 112, 147, 8, 139];
+
+/// Default time a final completion status stays readable
+pub const DEFAULT_STATUS_RETENTION_SECS: u64 = 3600;
+
+/// On-chain completion state of a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionStatus {
+    /// Transaction is being sent (attempt number, starting at 1)
+    Submitting { attempt: u32 },
+    /// Transaction confirmed with the given signature
+    Confirmed { signature: String },
+    /// Batch was found already executed on-chain
+    AlreadyExecuted,
+    /// Completion gave up
+    Failed { reason: String },
+}
+
+impl CompletionStatus {
+    /// Whether completion is over, whatever its outcome
+    pub fn is_final(&self) -> bool {
+        !matches!(self, Self::Submitting { .. })
+    }
+}
+
+/// Retry policy for `complete_batch` submission
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of send attempts
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// Build the `complete_batch` instruction
+pub fn complete_batch_instruction(
+    program_id: &Pubkey,
+    coordinator_state: &Pubkey,
+    batch: &Pubkey,
+    executor: &Pubkey,
+    batch_id: u64,
+    results_hash: [u8; 32],
+) -> Instruction {
+    let mut data = Vec::with_capacity(8 + 8 + 32);
+    data.extend_from_slice(&COMPLETE_BATCH_DISCRIMINATOR);
+    data.extend_from_slice(&batch_id.to_le_bytes());
+    data.extend_from_slice(&results_hash);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*coordinator_state, false),
+            AccountMeta::new(*batch, false),
+            AccountMeta::new_readonly(*executor, true),
+        ],
+        data,
+    }
+}
+
+/// Signs and submits `complete_batch` transactions in the background
+#[derive(Clone)]
+pub struct BatchCompleter {
+    rpc_client: Arc<RpcClient>,
+    reader: Arc<CoordinatorReader>,
+    executor: Arc<Keypair>,
+    retry_policy: RetryPolicy,
+    status_retention: Duration,
+    /// Status of every batch, with the time it was last set
+    statuses: Arc<RwLock<HashMap<u64, (CompletionStatus, Instant)>>>,
+}
+
+impl BatchCompleter {
+    pub fn new(rpc_url: &str, executor: Keypair) -> Self {
+        Self {
+            rpc_client: Arc::new(RpcClient::new(rpc_url.to_string())),
+            reader: Arc::new(CoordinatorReader::new(rpc_url)),
+            executor: Arc::new(executor),
+            retry_policy: RetryPolicy::default(),
+            status_retention: Duration::from_secs(DEFAULT_STATUS_RETENTION_SECS),
+            statuses: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Create a completer with the executor keypair loaded from a JSON file
+    pub fn from_keypair_file(rpc_url: &str, path: &str) -> ProxyResult<Self> {
+        let executor = read_keypair_file(path)
+            .map_err(|e| ProxyError::Internal(format!("Failed to read executor keypair: {}", e)))?;
+        Ok(Self::new(rpc_url, executor))
+    }
+
+    /// Send every request through one of the shared call `slots`
+    pub fn with_call_slots(mut self, slots: &Arc<CallSlots>) -> Self {
+        self.rpc_client = Arc::new(slots.client(&self.rpc_client.url()));
+        self.reader =
+            Arc::new(CoordinatorReader::new(&self.rpc_client.url()).with_call_slots(slots));
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set how long a final status stays readable before it is dropped
+    pub fn with_status_retention(mut self, retention: Duration) -> Self {
+        self.status_retention = retention;
+        self
+    }
+
+    /// Public key the completer signs with
+    pub fn executor_pubkey(&self) -> Pubkey {
+        self.executor.pubkey()
+    }
+
+    /// Publisher of the encryption key fingerprint, signing with the same
+    /// authority keypair
+    pub fn key_publisher(&self) -> KeyPublisher {
+        KeyPublisher::new(
+            Arc::clone(&self.rpc_client),
+            Arc::clone(&self.reader),
+            Arc::clone(&self.executor),
+        )
+        .with_retry_policy(self.retry_policy.clone())
+    }
+
+    /// Current completion status of a batch, if completion was requested
+    ///
+    /// Final statuses are forgotten once the retention window has passed.
+    pub async fn status(&self, batch_id: u64) -> Option<CompletionStatus> {
+        let statuses = self.statuses.read().await;
+        let (status, updated_at) = statuses.get(&batch_id)?;
+        let expired = status.is_final() && updated_at.elapsed() >= self.status_retention;
+        (!expired).then(|| status.clone())
+    }
+
+    /// Number of statuses held (including expired, not yet purged)
+    pub async fn status_count(&self) -> usize {
+        self.statuses.read().await.len()
+    }
+
+    /// Submit `complete_batch` for a batch in a background task
+    ///
+    /// Does nothing if completion was already requested for this batch.
+    pub async fn spawn_completion(
+        &self,
+        batch_id: u64,
+        results_hash: [u8; 32],
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        {
+            let now = Instant::now();
+            let mut statuses = self.statuses.write().await;
+            statuses.retain(|_, (status, updated_at)| {
+                !status.is_final() || now.duration_since(*updated_at) < self.status_retention
+            });
+            if statuses.contains_key(&batch_id) {
+                return None;
+            }
+            statuses.insert(batch_id, (CompletionStatus::Submitting { attempt: 1 }, now));
+        }
+
+        let completer = self.clone();
+        Some(tokio::spawn(async move {
+            let status = completer
+                .complete(batch_id, results_hash)
+                .await
+                .unwrap_or_else(|error| CompletionStatus::Failed {
+                    reason: error.to_string(),
+                });
+            match &status {
+                CompletionStatus::Confirmed { signature } => {
+                    info!(batch_id = batch_id, signature = %signature, "Batch completed on-chain");
+                }
+                CompletionStatus::AlreadyExecuted => {
+                    info!(batch_id = batch_id, "Batch already executed on-chain");
+                }
+                CompletionStatus::Failed { reason } => {
+                    warn!(batch_id = batch_id, reason = %reason, "Batch completion failed");
+                }
+                CompletionStatus::Submitting { .. } => {}
+            }
+            completer.set_status(batch_id, status).await;
+        }))
+    }
+
+    /// Send `complete_batch` until it lands or the retry policy gives up
+    ///
+    /// Returns the error of the last attempt once no retry is left.
+    async fn complete(
+        &self,
+        batch_id: u64,
+        results_hash: [u8; 32],
+    ) -> ProxyResult<CompletionStatus> {
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            self.set_status(batch_id, CompletionStatus::Submitting { attempt })
+                .await;
+
+            let error = match self.send(batch_id, results_hash).await {
+                Ok(signature) => return Ok(CompletionStatus::Confirmed { signature }),
+                Err(error) => error,
+            };
+
+            // A failed send may still have landed, or another executor may
+            // have completed the batch first
+            if self.is_executed(batch_id).await {
+                return Ok(CompletionStatus::AlreadyExecuted);
+            }
+
+            if !is_retryable(&error) {
+                return Err(ProxyError::SolanaRpc(error));
+            }
+            if attempt >= max_attempts {
+                return Err(ProxyError::Internal(format!(
+                    "gave up after {} attempts: {}",
+                    attempt, error
+                )));
+            }
+
+            let delay = self.retry_policy.backoff(attempt);
+            warn!(
+                batch_id = batch_id,
+                attempt = attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %error,
+                "Retrying batch completion"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send(
+        &self,
+        batch_id: u64,
+        results_hash: [u8; 32],
+    ) -> Result<String, Box<ClientError>> {
+        let instruction = complete_batch_instruction(
+            &self.reader.program_id(),
+            &self.reader.get_coordinator_state_pda(),
+            &self.reader.get_batch_pda(batch_id),
+            &self.executor.pubkey(),
+            batch_id,
+            results_hash,
+        );
+
+        send_instruction(&self.rpc_client, &self.executor, instruction).await
+    }
+
+    async fn is_executed(&self, batch_id: u64) -> bool {
+        let pda = self.reader.get_batch_pda(batch_id);
+        let Ok(account) = self.rpc_client.get_account(&pda).await else {
+            return false;
+        };
+        self.reader
+            .parse_batch_data(&account.data)
+            .is_some_and(|batch| batch.status == OnChainBatchStatus::Executed)
+    }
+
+    async fn set_status(&self, batch_id: u64, status: CompletionStatus) {
+        self.statuses
+            .write()
+            .await
+            .insert(batch_id, (status, Instant::now()));
+    }
+}
+
+/// Sign a single-instruction transaction with `signer` as fee payer and send it
+pub(super) async fn send_instruction(
+    rpc_client: &RpcClient,
+    signer: &Keypair,
+    instruction: Instruction,
+) -> Result<String, Box<ClientError>> {
+    // Fetch a fresh blockhash on every attempt so retries never reuse an
+    // expired one
+    let blockhash = rpc_client.get_latest_blockhash().await.map_err(Box::new)?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer.pubkey()),
+        &[signer],
+        blockhash,
+    );
+    rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .map(|signature| signature.to_string())
+        .map_err(Box::new)
+}
+
+/// Whether a failed send is worth retrying
+///
+/// Errors carrying a `TransactionError` were produced by the runtime or the
+/// program (e.g. `BatchNotFinalized`, `Unauthorized`) and will fail again.
+pub(super) fn is_retryable(error: &ClientError) -> bool {
+    error.get_transaction_error().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use solana_client::client_error::ClientErrorKind;
+    use solana_sdk::transaction::TransactionError;
+
+    #[test]
+    fn test_discriminator_matches_anchor() {
+        let hash = Sha256::digest(b"global:complete_batch");
+        assert_eq!(COMPLETE_BATCH_DISCRIMINATOR, hash[..8]);
+    }
+
+    #[test]
+    fn test_complete_batch_instruction_layout() {
+        let program_id = Pubkey::new_unique();
+        let state = Pubkey::new_unique();
+        let batch = Pubkey::new_unique();
+        let executor = Pubkey::new_unique();
+
+        let ix = complete_batch_instruction(&program_id, &state, &batch, &executor, 42, [7u8; 32]);
+
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(&ix.data[..8], &COMPLETE_BATCH_DISCRIMINATOR);
+        assert_eq!(&ix.data[8..16], &42u64.to_le_bytes());
+        assert_eq!(&ix.data[16..], &[7u8; 32]);
+
+        assert_eq!(ix.accounts[0], AccountMeta::new_readonly(state, false));
+        assert_eq!(ix.accounts[1], AccountMeta::new(batch, false));
+        assert_eq!(ix.accounts[2], AccountMeta::new_readonly(executor, true));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_program_errors_are_not_retried() {
+        let transport = ClientError::from(std::io::Error::other("connection reset"));
+        assert!(is_retryable(&transport));
+
+        let program = ClientError::from(ClientErrorKind::TransactionError(
+            TransactionError::AccountNotFound,
+        ));
+        assert!(!is_retryable(&program));
+    }
+
+    #[tokio::test]
+    async fn test_completion_requested_once_per_batch() {
+        let completer = BatchCompleter::new("http://127.0.0.1:1", Keypair::new())
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::ZERO,
+                max_backoff: Duration::ZERO,
+            });
+
+        let first = completer.spawn_completion(3, [0u8; 32]).await;
+        assert!(first.is_some());
+        assert!(completer.spawn_completion(3, [0u8; 32]).await.is_none());
+
+        first.unwrap().await.unwrap();
+        match completer.status(3).await {
+            Some(CompletionStatus::Failed { reason }) => {
+                assert!(reason.contains("gave up after 2 attempts"), "{}", reason)
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_final_statuses_are_dropped_after_retention() {
+        let completer = BatchCompleter::new("http://127.0.0.1:1", Keypair::new())
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 1,
+                initial_backoff: Duration::ZERO,
+                max_backoff: Duration::ZERO,
+            })
+            .with_status_retention(Duration::ZERO);
+
+        completer
+            .spawn_completion(3, [0u8; 32])
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(completer.status(3).await.is_none());
+
+        // The next completion purges the expired status
+        completer
+            .spawn_completion(4, [0u8; 32])
+            .await
+            .unwrap()
+            .abort();
+        assert_eq!(completer.status_count().await, 1);
+        assert!(matches!(
+            completer.status(4).await,
+            Some(CompletionStatus::Submitting { .. })
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+//! Publication of the proxy's encryption key fingerprint
+//!
+//! Whenever the active key set changes, the proxy records its fingerprint in
+//! the coordinator state with `set_encryption_key`, signed by the coordinator
+//! authority. Nothing is sent if the chain already holds the fingerprint.
+//! Failures are retried with the same policy as `complete_batch`.
+
+use super::completer::{is_retryable, send_instruction, RetryPolicy};
+use super::reader::{parse_encryption_key_fingerprint, CoordinatorReader};
+use crate::error::{ProxyError, ProxyResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Anchor discriminator of the `set_encryption_key` instruction
+const SET_ENCRYPTION_KEY_DISCRIMINATOR: [u8; 8] = [60, 95, 22, 80, 124, 130, 247, 92];
+
+/// Build the `set_encryption_key` instruction
+pub fn set_encryption_key_instruction(
+    program_id: &Pubkey,
+    coordinator_state: &Pubkey,
+    authority: &Pubkey,
+    fingerprint: [u8; 32],
+) -> Instruction {
+    let mut data = Vec::with_capacity(8 + 32);
+    data.extend_from_slice(&SET_ENCRYPTION_KEY_DISCRIMINATOR);
+    data.extend_from_slice(&fingerprint);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*coordinator_state, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Publishes the encryption key fingerprint on-chain
+#[derive(Clone)]
+pub struct KeyPublisher {
+    rpc_client: Arc<RpcClient>,
+    reader: Arc<CoordinatorReader>,
+    authority: Arc<Keypair>,
+    retry_policy: RetryPolicy,
+}
+
+impl KeyPublisher {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        reader: Arc<CoordinatorReader>,
+        authority: Arc<Keypair>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            reader,
+            authority,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Set the retry policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Publish a fingerprint unless the chain already holds it
+    ///
+    /// Returns the transaction signature, or `None` if nothing was sent.
+    pub async fn publish(&self, fingerprint: [u8; 32]) -> ProxyResult<Option<String>> {
+        if self.published().await == Some(fingerprint) {
+            return Ok(None);
+        }
+
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let instruction = set_encryption_key_instruction(
+                &self.reader.program_id(),
+                &self.reader.get_coordinator_state_pda(),
+                &self.authority.pubkey(),
+                fingerprint,
+            );
+
+            let error = match send_instruction(&self.rpc_client, &self.authority, instruction).await
+            {
+                Ok(signature) => return Ok(Some(signature)),
+                Err(error) => error,
+            };
+
+            // A failed send may still have landed
+            if self.published().await == Some(fingerprint) {
+                return Ok(None);
+            }
+
+            if !is_retryable(&error) || attempt == max_attempts {
+                return Err(ProxyError::SolanaRpc(error));
+            }
+
+            let delay = self.retry_policy.backoff(attempt);
+            warn!(
+                attempt = attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %error,
+                "Retrying encryption key publication"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn published(&self) -> Option<[u8; 32]> {
+        let pda = self.reader.get_coordinator_state_pda();
+        let account = self.rpc_client.get_account(&pda).await.ok()?;
+        parse_encryption_key_fingerprint(&account.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_discriminator_matches_anchor() {
+        let hash = Sha256::digest(b"global:set_encryption_key");
+        assert_eq!(SET_ENCRYPTION_KEY_DISCRIMINATOR, hash[..8]);
+    }
+
+    #[test]
+    fn test_set_encryption_key_instruction_layout() {
+        let program_id = Pubkey::new_unique();
+        let state = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let ix = set_encryption_key_instruction(&program_id, &state, &authority, [3u8; 32]);
+
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(&ix.data[..8], &SET_ENCRYPTION_KEY_DISCRIMINATOR);
+        assert_eq!(&ix.data[8..], &[3u8; 32]);
+
+        assert_eq!(ix.accounts[0], AccountMeta::new(state, false));
+        assert_eq!(ix.accounts[1], AccountMeta::new(authority, true));
+        assert_eq!(
+            ix.accounts[2],
+            AccountMeta::new_readonly(system_program::id(), false)
+        );
+    }
+}
@@ -1,9 +1,16 @@
 //! On-chain coordinator client
 //!
-//! Reads batch data from the Solana coordinator program.
+//! Reads batch data from the Solana coordinator program and records executed
+//! batches and the proxy's encryption key fingerprint back on-chain.
 
+mod completer;
+mod key_publisher;
 mod poller;
 mod reader;
+mod verifier;
 
+pub use completer::{complete_batch_instruction, BatchCompleter, CompletionStatus, RetryPolicy};
+pub use key_publisher::{set_encryption_key_instruction, KeyPublisher};
 pub use poller::BatchPoller;
 pub use reader::{CoordinatorReader, OnChainBatch, OnChainBatchStatus};
+pub use verifier::verify_query_hashes;
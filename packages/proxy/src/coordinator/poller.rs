@@ -1,11 +1,12 @@
 //! Batch poller for monitoring finalized on-chain batches
 
 use super::reader::{CoordinatorReader, OnChainBatch, OnChainBatchStatus};
+use crate::upstream::CallSlots;
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 /// Tracks processed batches to avoid duplicate processing
 pub struct BatchPoller {
@@ -23,6 +24,12 @@ impl BatchPoller {
         }
     }
 
+    /// Send every request through one of the shared call `slots`
+    pub fn with_call_slots(mut self, slots: &Arc<CallSlots>) -> Self {
+        self.reader = self.reader.with_call_slots(slots);
+        self
+    }
+
     /// Start the polling loop in a background task
     pub fn start(self) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
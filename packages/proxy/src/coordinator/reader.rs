@@ -1,14 +1,20 @@
 //! Coordinator account reader
 
+use crate::upstream::CallSlots;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
+use std::sync::Arc;
 use tracing::{debug, warn};
 
 const COORDINATOR_PROGRAM_ID: &str = "3LsgXZDcRaC3vGq3392WGuEa4AST76m8NPNQCaqDd3n6";
 const COORDINATOR_SEED: &[u8] = b"coordinator";
 const BATCH_SEED: &[u8] = b"batch";
 
+/// Offset of `encryption_key_fingerprint` in the coordinator state: after the
+/// discriminator, authority, min/max batch size, batch counter and bump
+const FINGERPRINT_OFFSET: usize = 8 + 32 + 1 + 1 + 8 + 1;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OnChainBatchStatus {
     Pending,
@@ -42,12 +48,23 @@ impl CoordinatorReader {
         }
     }
 
-    fn get_coordinator_state_pda(&self) -> Pubkey {
+    /// Send every request through one of the shared call `slots`
+    pub fn with_call_slots(mut self, slots: &Arc<CallSlots>) -> Self {
+        self.rpc_client = slots.blocking_client(&self.rpc_client.url());
+        self
+    }
+
+    /// Coordinator program this reader targets
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub(crate) fn get_coordinator_state_pda(&self) -> Pubkey {
         let (pda, _) = Pubkey::find_program_address(&[COORDINATOR_SEED], &self.program_id);
         pda
     }
 
-    fn get_batch_pda(&self, batch_id: u64) -> Pubkey {
+    pub(crate) fn get_batch_pda(&self, batch_id: u64) -> Pubkey {
         let (pda, _) = Pubkey::find_program_address(
             &[BATCH_SEED, &batch_id.to_le_bytes()],
             &self.program_id,
@@ -69,6 +86,16 @@ impl CoordinatorReader {
         Some(counter)
     }
 
+    /// Encryption key fingerprint published in the coordinator state
+    ///
+    /// `None` if the state cannot be read, predates the field or holds no
+    /// fingerprint yet.
+    pub fn get_encryption_key_fingerprint(&self) -> Option<[u8; 32]> {
+        let pda = self.get_coordinator_state_pda();
+        let account = self.rpc_client.get_account(&pda).ok()?;
+        parse_encryption_key_fingerprint(&account.data)
+    }
+
     pub fn get_batch(&self, batch_id: u64) -> Option<OnChainBatch> {
         let pda = self.get_batch_pda(batch_id);
         let account = self.rpc_client.get_account(&pda).ok()?;
@@ -79,7 +106,7 @@ impl CoordinatorReader {
         self.parse_batch_data(&data)
     }
 
-    fn parse_batch_data(&self, data: &[u8]) -> Option<OnChainBatch> {
+    pub(super) fn parse_batch_data(&self, data: &[u8]) -> Option<OnChainBatch> {
         if data.len() < 20 {
             return None;
         }
@@ -180,10 +207,31 @@ impl CoordinatorReader {
     }
 }
 
+pub(super) fn parse_encryption_key_fingerprint(data: &[u8]) -> Option<[u8; 32]> {
+    let fingerprint: [u8; 32] = data
+        .get(FINGERPRINT_OFFSET..FINGERPRINT_OFFSET + 32)?
+        .try_into()
+        .ok()?;
+    (fingerprint != [0u8; 32]).then_some(fingerprint)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encryption_key_fingerprint_parsing() {
+        let mut data = vec![0u8; FINGERPRINT_OFFSET + 32];
+        assert_eq!(parse_encryption_key_fingerprint(&data), None);
+        assert_eq!(
+            parse_encryption_key_fingerprint(&data[..FINGERPRINT_OFFSET]),
+            None
+        );
+
+        data[FINGERPRINT_OFFSET..].copy_from_slice(&[9u8; 32]);
+        assert_eq!(parse_encryption_key_fingerprint(&data), Some([9u8; 32]));
+    }
+
     #[test]
     fn test_batch_status_parsing() {
         assert_eq!(
@@ -0,0 +1,162 @@
+//! Verification of submitted queries against an on-chain batch
+
+use super::reader::OnChainBatch;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::Query;
+
+/// Verify that the submitted queries are exactly the ones committed on-chain
+///
+/// Every query is hashed with the canonical encoding the SDK uses before
+/// calling `submit_query`, salted with the revealed salt, which every query
+/// must carry, and bound to the query's encryption key when it has one. The
+/// resulting hashes must match the batch's `query_hashes` one-to-one: same
+/// count and same membership. Otherwise a caller who knows a finalized
+/// `batch_id` could run arbitrary queries under that batch's anonymity set,
+/// or have their results encrypted to a key of their own.
+pub fn verify_query_hashes(queries: &[Query], batch: &OnChainBatch) -> ProxyResult<()> {
+    if queries.len() != batch.query_hashes.len() {
+        return Err(ProxyError::QueryHashMismatch {
+            batch_id: batch.id,
+            reason: format!(
+                "expected {} queries, got {}",
+                batch.query_hashes.len(),
+                queries.len()
+            ),
+        });
+    }
+
+    let mut submitted = queries
+        .iter()
+        .map(Query::commitment_hash)
+        .collect::<ProxyResult<Vec<[u8; 32]>>>()?;
+    let mut committed = batch.query_hashes.clone();
+    submitted.sort_unstable();
+    committed.sort_unstable();
+
+    // Never report which query differs, only that the sets disagree
+    if submitted != committed {
+        return Err(ProxyError::QueryHashMismatch {
+            batch_id: batch.id,
+            reason: "submitted queries do not match committed hashes".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::OnChainBatchStatus;
+    use crate::enums::RpcMethod;
+
+    const KEY_A: &str = "So11111111111111111111111111111111111111112";
+    const KEY_B: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const KEY_C: &str = "11111111111111111111111111111111";
+
+    /// Query salted with its id, which is a hex digit in every test
+    fn query(id: &str, pubkey: &str) -> Query {
+        Query::new(id.to_string(), RpcMethod::GetBalance, pubkey.to_string())
+            .with_salt(id.repeat(64))
+    }
+
+    fn hash(query: &Query) -> [u8; 32] {
+        query.commitment_hash().unwrap()
+    }
+
+    fn batch_with(hashes: Vec<[u8; 32]>) -> OnChainBatch {
+        OnChainBatch {
+            id: 7,
+            status: OnChainBatchStatus::Finalized,
+            query_count: hashes.len() as u8,
+            query_hashes: hashes,
+            submitters: vec![],
+            created_at: 0,
+            finalized_at: Some(0),
+            results_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_queries_in_any_order() {
+        let queries = vec![query("1", KEY_A), query("2", KEY_B)];
+        let batch = batch_with(vec![hash(&queries[1]), hash(&queries[0])]);
+
+        assert!(verify_query_hashes(&queries, &batch).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_count_mismatch() {
+        let queries = vec![query("1", KEY_A), query("2", KEY_B)];
+        let batch = batch_with(vec![hash(&queries[0])]);
+
+        let result = verify_query_hashes(&queries, &batch);
+        assert!(matches!(
+            result,
+            Err(ProxyError::QueryHashMismatch { batch_id: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_substituted_query() {
+        let committed = [query("1", KEY_A), query("2", KEY_B)];
+        let batch = batch_with(committed.iter().map(hash).collect());
+
+        let submitted = vec![query("1", KEY_A), query("2", KEY_C)];
+        let result = verify_query_hashes(&submitted, &batch);
+        assert!(matches!(result, Err(ProxyError::QueryHashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_salted_queries_verify_with_revealed_salt() {
+        let queries = vec![
+            query("1", KEY_A).with_salt(hex::encode([1u8; 32])),
+            query("2", KEY_B).with_salt(hex::encode([2u8; 32])),
+        ];
+        let batch = batch_with(queries.iter().map(hash).collect());
+
+        assert!(verify_query_hashes(&queries, &batch).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_wrong_salt() {
+        let committed = query("1", KEY_A).with_salt(hex::encode([1u8; 32]));
+        let batch = batch_with(vec![hash(&committed)]);
+
+        let revealed = vec![query("1", KEY_A).with_salt(hex::encode([9u8; 32]))];
+        let result = verify_query_hashes(&revealed, &batch);
+        assert!(matches!(result, Err(ProxyError::QueryHashMismatch { .. })));
+
+        let mut unsalted = query("1", KEY_A);
+        unsalted.salt = None;
+        let result = verify_query_hashes(&[unsalted], &batch);
+        assert!(matches!(result, Err(ProxyError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_rejects_swapped_encryption_key() {
+        let client_key = hex::encode([9u8; 32]);
+        let committed = query("1", KEY_A).with_encryption_key(client_key);
+        let batch = batch_with(vec![hash(&committed)]);
+        assert!(verify_query_hashes(std::slice::from_ref(&committed), &batch).is_ok());
+
+        // Whoever posts the batch substitutes their own key, or strips it
+        let swapped = vec![query("1", KEY_A).with_encryption_key(hex::encode([10u8; 32]))];
+        let result = verify_query_hashes(&swapped, &batch);
+        assert!(matches!(result, Err(ProxyError::QueryHashMismatch { .. })));
+
+        let stripped = vec![query("1", KEY_A)];
+        let result = verify_query_hashes(&stripped, &batch);
+        assert!(matches!(result, Err(ProxyError::QueryHashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_rejects_duplicated_query() {
+        let a = query("1", KEY_A);
+        let batch = batch_with(vec![hash(&a), hash(&query("2", KEY_B))]);
+
+        let submitted = vec![a.clone(), query("3", KEY_A)];
+        let result = verify_query_hashes(&submitted, &batch);
+        assert!(matches!(result, Err(ProxyError::QueryHashMismatch { .. })));
+    }
+}
@@ -0,0 +1,440 @@
+//! Constant-rate emitter of upstream calls
+//!
+//! The emitter owns a fixed clock of `calls_per_sec` slots. Every slot sends
+//! exactly one upstream call: the oldest queued real query if there is one,
+//! otherwise a decoy. The upstream thus sees the same rate whether or not
+//! clients are active.
+//!
+//! Each slot makes a single attempt on one upstream, with no retry or
+//! failover, so a failing upstream never adds calls between slots. A slot
+//! never waits for one of the pool's call slots either: when none is free it
+//! is skipped and its real call, if any, stays queued for the next slot.
+//! Calls whose caller gave up on them while queued are dropped unsent.
+//!
+//! Decoys draw on an optional bandwidth budget, a token bucket over the
+//! approximate JSON size of each call and its result. When it runs dry, empty
+//! slots stay unused; real calls are always sent and still count against it.
+
+use crate::decoy::DecoyGenerator;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{CoverTrafficConfig, CoverTrafficMetrics, Query, QueryResult};
+use crate::upstream::{CallSlots, Departure, UpstreamPool};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+/// Seconds of budget the bucket can accumulate while idle
+const BUDGET_BURST_SECS: f64 = 1.0;
+
+/// A real call waiting for a slot
+struct QueuedCall {
+    query: Query,
+    enqueued_at: Instant,
+    departure: Departure,
+    responder: oneshot::Sender<QueryResult>,
+}
+
+/// Token bucket over approximate upstream bytes
+#[derive(Debug)]
+struct Budget {
+    bytes_per_sec: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Budget {
+    fn new(bytes_per_sec: u64, now: Instant) -> Self {
+        let bytes_per_sec = bytes_per_sec as f64;
+        Self {
+            bytes_per_sec,
+            tokens: bytes_per_sec * BUDGET_BURST_SECS,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec)
+            .min(self.bytes_per_sec * BUDGET_BURST_SECS);
+        self.refilled_at = now;
+    }
+
+    /// Check whether a decoy may be sent
+    fn has_room(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens > 0.0
+    }
+
+    /// Charge a completed call; the balance may go negative
+    fn spend(&mut self, bytes: u64, now: Instant) {
+        self.refill(now);
+        self.tokens -= bytes as f64;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    slots: AtomicU64,
+    real_calls: AtomicU64,
+    decoy_calls: AtomicU64,
+    skipped_slots: AtomicU64,
+    bytes: AtomicU64,
+    max_queue_wait_ms: AtomicU64,
+}
+
+/// Keeps a constant rate of upstream calls, filling idle slots with decoys
+pub struct CoverTraffic {
+    upstreams: Arc<UpstreamPool>,
+    decoys: Arc<DecoyGenerator>,
+    config: CoverTrafficConfig,
+    queue: Mutex<VecDeque<QueuedCall>>,
+    budget: Option<Mutex<Budget>>,
+    counters: Counters,
+}
+
+impl CoverTraffic {
+    /// Create an emitter against `upstreams`, drawing decoys from `decoys`
+    pub fn new(
+        upstreams: Arc<UpstreamPool>,
+        decoys: Arc<DecoyGenerator>,
+        config: CoverTrafficConfig,
+    ) -> Self {
+        let budget = config
+            .max_bytes_per_sec
+            .map(|bytes_per_sec| Mutex::new(Budget::new(bytes_per_sec, Instant::now())));
+        Self {
+            upstreams,
+            decoys,
+            config,
+            queue: Mutex::new(VecDeque::new()),
+            budget,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Start the slot clock in a background task
+    pub fn start(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let emitter = Arc::clone(self);
+        tokio::spawn(async move {
+            info!(
+                calls_per_sec = emitter.config.calls_per_sec,
+                max_bytes_per_sec = ?emitter.config.max_bytes_per_sec,
+                "Starting cover traffic"
+            );
+            let mut clock = tokio::time::interval(emitter.config.slot_interval());
+            clock.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                clock.tick().await;
+                emitter.fill_slot();
+            }
+        })
+    }
+
+    /// Queue real calls for the next free slots, in the given order
+    ///
+    /// Either every call is queued or, if the queue cannot hold them all,
+    /// none is. Each receiver yields the result of its call; a call's
+    /// departure is marked when its slot sends it.
+    pub fn submit(
+        &self,
+        calls: Vec<(Query, Departure)>,
+    ) -> ProxyResult<Vec<oneshot::Receiver<QueryResult>>> {
+        let mut queue = self.lock_queue();
+        if queue.len() + calls.len() > self.config.max_queue {
+            return Err(ProxyError::CoverQueueFull(queue.len()));
+        }
+
+        let enqueued_at = Instant::now();
+        Ok(calls
+            .into_iter()
+            .map(|(query, departure)| {
+                let (responder, receiver) = oneshot::channel();
+                queue.push_back(QueuedCall {
+                    query,
+                    enqueued_at,
+                    departure,
+                    responder,
+                });
+                receiver
+            })
+            .collect())
+    }
+
+    /// Snapshot of the emitter's counters
+    pub fn metrics(&self) -> CoverTrafficMetrics {
+        CoverTrafficMetrics {
+            calls_per_sec: self.config.calls_per_sec,
+            slots: self.counters.slots.load(Ordering::Relaxed),
+            real_calls: self.counters.real_calls.load(Ordering::Relaxed),
+            decoy_calls: self.counters.decoy_calls.load(Ordering::Relaxed),
+            skipped_slots: self.counters.skipped_slots.load(Ordering::Relaxed),
+            bytes: self.counters.bytes.load(Ordering::Relaxed),
+            queue_depth: self.lock_queue().len(),
+            max_queue_wait_ms: self.counters.max_queue_wait_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Send the call of one slot
+    fn fill_slot(self: &Arc<Self>) {
+        self.counters.slots.fetch_add(1, Ordering::Relaxed);
+
+        // Waiting for a call slot would send the call whenever another one
+        // completes, tying the slot's timing to that call's latency
+        let Some(call_slot) = self.upstreams.try_acquire_slot() else {
+            self.counters.skipped_slots.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        let next = {
+            let mut queue = self.lock_queue();
+            queue.retain(|call| !call.responder.is_closed());
+            queue.pop_front()
+        };
+        let (query, responder) = match next {
+            Some(call) => {
+                let waited_ms = call.enqueued_at.elapsed().as_millis() as u64;
+                self.counters
+                    .max_queue_wait_ms
+                    .fetch_max(waited_ms, Ordering::Relaxed);
+                self.counters.real_calls.fetch_add(1, Ordering::Relaxed);
+                call.departure.mark();
+                (call.query, Some(call.responder))
+            }
+            None => {
+                if !self.budget_has_room() {
+                    self.counters.skipped_slots.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                let Some(decoy) = self.decoys.generate_like(&[], 1).pop() else {
+                    warn!("No decoy available for cover traffic slot");
+                    return;
+                };
+                self.counters.decoy_calls.fetch_add(1, Ordering::Relaxed);
+                (decoy, None)
+            }
+        };
+
+        let emitter = Arc::clone(self);
+        tokio::spawn(async move {
+            let request_bytes = serde_json::to_vec(&query).map_or(0, |bytes| bytes.len());
+            let call = emitter.upstreams.execute_once(query, None);
+            let result = CallSlots::with_slot(call_slot, call).await;
+            let result_bytes = serde_json::to_vec(&result).map_or(0, |bytes| bytes.len());
+            emitter.record_bytes((request_bytes + result_bytes) as u64);
+
+            if let Some(responder) = responder {
+                if responder.send(result).is_err() {
+                    debug!("Cover traffic caller went away before its result");
+                }
+            }
+        });
+    }
+
+    fn budget_has_room(&self) -> bool {
+        self.budget.as_ref().is_none_or(|budget| {
+            budget
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .has_room(Instant::now())
+        })
+    }
+
+    fn record_bytes(&self, bytes: u64) {
+        self.counters.bytes.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(budget) = &self.budget {
+            budget
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .spend(bytes, Instant::now());
+        }
+    }
+
+    fn lock_queue(&self) -> std::sync::MutexGuard<'_, VecDeque<QueuedCall>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoy::{CorpusEntry, DecoyCorpus};
+    use crate::enums::RpcMethod;
+    use crate::types::{DecoyConfig, FailoverConfig};
+    use crate::upstream::MockUpstream;
+    use std::time::Duration;
+
+    fn emitter(upstreams: UpstreamPool, config: CoverTrafficConfig) -> Arc<CoverTraffic> {
+        let corpus = DecoyCorpus {
+            accounts: vec![CorpusEntry {
+                pubkey: "So11111111111111111111111111111111111111112".to_string(),
+                weight: 1.0,
+            }],
+            token_accounts: Vec::new(),
+        };
+        let decoys = DecoyGenerator::new(corpus, DecoyConfig::new(String::new(), 0)).unwrap();
+        Arc::new(CoverTraffic::new(
+            Arc::new(upstreams),
+            Arc::new(decoys),
+            config,
+        ))
+    }
+
+    fn call(id: &str) -> (Query, Departure) {
+        let query = Query::new(
+            id.to_string(),
+            RpcMethod::GetBalance,
+            "11111111111111111111111111111111".to_string(),
+        );
+        (query, Departure::channel().0)
+    }
+
+    #[tokio::test]
+    async fn test_slots_prefer_real_calls_then_decoys() {
+        let upstream = MockUpstream::start().await;
+        let emitter = emitter(
+            UpstreamPool::single(&upstream.url()),
+            CoverTrafficConfig::new(10),
+        );
+        let receivers = emitter.submit(vec![call("a"), call("b")]).unwrap();
+        assert_eq!(emitter.metrics().queue_depth, 2);
+
+        for _ in 0..3 {
+            emitter.fill_slot();
+        }
+
+        let mut ids = Vec::new();
+        for receiver in receivers {
+            ids.push(receiver.await.unwrap().id);
+        }
+        assert_eq!(ids, ["a", "b"]);
+
+        let metrics = emitter.metrics();
+        assert_eq!(metrics.slots, 3);
+        assert_eq!(metrics.real_calls, 2);
+        assert_eq!(metrics.decoy_calls, 1);
+        assert_eq!(metrics.queue_depth, 0);
+    }
+
+    #[tokio::test]
+    async fn test_slot_makes_a_single_attempt() {
+        let (first, second) = (MockUpstream::start().await, MockUpstream::start().await);
+        first.fail_with(503);
+        second.fail_with(503);
+        let upstreams = UpstreamPool::new(
+            &[first.url(), second.url()],
+            FailoverConfig::default().with_backoff(1, 1),
+        )
+        .unwrap();
+        let emitter = emitter(upstreams, CoverTrafficConfig::new(10));
+
+        let receivers = emitter.submit(vec![call("a")]).unwrap();
+        emitter.fill_slot();
+        let result = receivers.into_iter().next().unwrap().await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.attempts, Some(1));
+        assert_eq!(first.requests() + second.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slot_is_skipped_while_call_slots_are_taken() {
+        let upstream = MockUpstream::start().await;
+        let emitter = emitter(
+            UpstreamPool::single(&upstream.url()).with_max_in_flight(1),
+            CoverTrafficConfig::new(10),
+        );
+        let receivers = emitter.submit(vec![call("a")]).unwrap();
+
+        let taken = emitter.upstreams.try_acquire_slot().unwrap();
+        emitter.fill_slot();
+        let metrics = emitter.metrics();
+        assert_eq!(metrics.skipped_slots, 1);
+        assert_eq!(metrics.queue_depth, 1);
+
+        drop(taken);
+        emitter.fill_slot();
+        for receiver in receivers {
+            assert!(receiver.await.unwrap().success);
+        }
+        assert_eq!(upstream.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_calls_are_dropped_unsent() {
+        let upstream = MockUpstream::start().await;
+        let emitter = emitter(
+            UpstreamPool::single(&upstream.url()),
+            CoverTrafficConfig::new(10),
+        );
+        let (departure, mut departed) = Departure::channel();
+        let mut receivers = emitter
+            .submit(vec![call("a"), (call("b").0, departure)])
+            .unwrap();
+
+        // The caller gave up on "a" before its slot came
+        drop(receivers.remove(0));
+        emitter.fill_slot();
+        assert_eq!(receivers.remove(0).await.unwrap().id, "b");
+        assert!(departed.try_recv().is_ok());
+        assert_eq!(upstream.targets().len(), 1);
+        assert_eq!(emitter.metrics().real_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_submit_rejects_batches_beyond_queue_capacity() {
+        let upstream = MockUpstream::start().await;
+        let emitter = emitter(
+            UpstreamPool::single(&upstream.url()),
+            CoverTrafficConfig::new(10).with_max_queue(2),
+        );
+        emitter.submit(vec![call("a")]).unwrap();
+
+        assert!(matches!(
+            emitter.submit(vec![call("b"), call("c")]),
+            Err(ProxyError::CoverQueueFull(1))
+        ));
+        assert_eq!(emitter.metrics().queue_depth, 1);
+    }
+
+    #[test]
+    fn test_budget_refills_up_to_one_second() {
+        let start = Instant::now();
+        let mut budget = Budget::new(100, start);
+        assert!(budget.has_room(start));
+
+        budget.spend(250, start);
+        assert!(!budget.has_room(start + Duration::from_millis(500)));
+        assert!(budget.has_room(start + Duration::from_millis(1600)));
+
+        budget.refill(start + Duration::from_secs(60));
+        assert_eq!(budget.tokens, 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_budget_skips_empty_slots_only() {
+        let upstream = MockUpstream::start().await;
+        let emitter = emitter(
+            UpstreamPool::single(&upstream.url()),
+            CoverTrafficConfig::new(10).with_max_bytes_per_sec(1),
+        );
+        emitter.record_bytes(10);
+
+        emitter.fill_slot();
+        assert_eq!(emitter.metrics().skipped_slots, 1);
+
+        let receivers = emitter.submit(vec![call("a")]).unwrap();
+        emitter.fill_slot();
+        for receiver in receivers {
+            assert_eq!(receiver.await.unwrap().id, "a");
+        }
+        assert_eq!(emitter.metrics().real_calls, 1);
+    }
+}
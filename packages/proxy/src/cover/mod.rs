@@ -0,0 +1,8 @@
+//! Cover traffic toward the upstream RPC
+//!
+//! Hides when clients are active by sending upstream calls at a constant
+//! rate, real when queued and decoys otherwise.
+
+mod cover_traffic;
+
+pub use cover_traffic::CoverTraffic;
@@ -0,0 +1,500 @@
+//! Proxy encryption key management
+//!
+//! The proxy holds two kinds of X25519 keys, each stored as a JSON file in a
+//! key directory:
+//!
+//! - a long-term key (`long-term.json`), generated on first start, that never
+//!   expires
+//! - rotating keys (`rotating-<not_before>.json`), one generated every
+//!   rotation interval; each stays valid for two intervals, so a client that
+//!   fetched the previous key keeps a grace period
+//!
+//! The active key set is served at `GET /keys`, and its fingerprint is
+//! published in the coordinator state so clients can check the keys they
+//! encrypt to against the chain:
+//!
+//! ```text
+//! fingerprint = SHA-256("privacy-rpc/encryption-keys" || 0x01 || count (u32 LE)
+//!                       || for each key: public_key (32)
+//!                                        || not_before (i64 LE)
+//!                                        || 0x00 | 0x01 || not_after (i64 LE))
+//! ```
+//!
+//! Keys are fingerprinted in the order they are served: the long-term key
+//! first, then rotating keys oldest first. The set (and fingerprint) changes
+//! only when [`KeyManager::rotate`] adds or expires a key.
+
+use crate::enums::KeyKind;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::EncryptionKeyInfo;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Domain separator of the key set fingerprint
+pub const KEY_FINGERPRINT_DOMAIN: &[u8] = b"privacy-rpc/encryption-keys";
+
+/// Version byte of the key set fingerprint encoding
+pub const KEY_FINGERPRINT_VERSION: u8 = 1;
+
+/// Default rotation interval (one day)
+pub const DEFAULT_KEY_ROTATION_SECS: u64 = 86_400;
+
+const LONG_TERM_FILE: &str = "long-term.json";
+const ROTATING_PREFIX: &str = "rotating-";
+
+/// Current Unix time in seconds
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Compute the fingerprint of a key set, in the order given
+pub fn fingerprint_keys(keys: &[EncryptionKeyInfo]) -> ProxyResult<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_FINGERPRINT_DOMAIN);
+    hasher.update([KEY_FINGERPRINT_VERSION]);
+    hasher.update((keys.len() as u32).to_le_bytes());
+
+    for key in keys {
+        let public_key: [u8; 32] = hex::decode(&key.public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| ProxyError::Crypto("Malformed public key".to_string()))?;
+        hasher.update(public_key);
+        hasher.update(key.not_before.to_le_bytes());
+        match key.not_after {
+            Some(not_after) => {
+                hasher.update([1u8]);
+                hasher.update(not_after.to_le_bytes());
+            }
+            None => hasher.update([0u8]),
+        }
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// On-disk form of a key
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyFile {
+    kind: KeyKind,
+    secret_key: String,
+    not_before: i64,
+    not_after: Option<i64>,
+}
+
+/// A key pair with its validity window
+#[derive(Clone)]
+struct ProxyKey {
+    info: EncryptionKeyInfo,
+    secret: StaticSecret,
+}
+
+impl ProxyKey {
+    fn generate(kind: KeyKind, not_before: i64, not_after: Option<i64>) -> Self {
+        let mut secret_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret_bytes);
+        Self::from_secret(
+            StaticSecret::from(secret_bytes),
+            kind,
+            not_before,
+            not_after,
+        )
+    }
+
+    fn from_secret(
+        secret: StaticSecret,
+        kind: KeyKind,
+        not_before: i64,
+        not_after: Option<i64>,
+    ) -> Self {
+        let public = PublicKey::from(&secret);
+        Self {
+            info: EncryptionKeyInfo {
+                kind,
+                public_key: hex::encode(public.as_bytes()),
+                not_before,
+                not_after,
+            },
+            secret,
+        }
+    }
+
+    fn load(path: &Path) -> ProxyResult<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            ProxyError::Internal(format!("Failed to read key {}: {}", path.display(), e))
+        })?;
+        let file: KeyFile = serde_json::from_str(&contents).map_err(|e| {
+            ProxyError::Crypto(format!("Malformed key file {}: {}", path.display(), e))
+        })?;
+        let secret: [u8; 32] = hex::decode(&file.secret_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ProxyError::Crypto(format!("Malformed secret key in {}", path.display()))
+            })?;
+
+        Ok(Self::from_secret(
+            StaticSecret::from(secret),
+            file.kind,
+            file.not_before,
+            file.not_after,
+        ))
+    }
+
+    fn file_name(&self) -> String {
+        match self.info.kind {
+            KeyKind::LongTerm => LONG_TERM_FILE.to_string(),
+            KeyKind::Rotating => format!("{}{}.json", ROTATING_PREFIX, self.info.not_before),
+        }
+    }
+
+    fn save(&self, dir: &Path) -> ProxyResult<()> {
+        let file = KeyFile {
+            kind: self.info.kind,
+            secret_key: hex::encode(self.secret.to_bytes()),
+            not_before: self.info.not_before,
+            not_after: self.info.not_after,
+        };
+        let contents = serde_json::to_string_pretty(&file)
+            .map_err(|e| ProxyError::Internal(format!("Failed to encode key: {}", e)))?;
+        write_private(&dir.join(self.file_name()), contents.as_bytes())
+    }
+}
+
+impl std::fmt::Debug for ProxyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyKey")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Write a file readable only by its owner
+pub fn write_private(path: &Path, contents: &[u8]) -> ProxyResult<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, contents))
+        .map_err(|e| ProxyError::Internal(format!("Failed to write key {}: {}", path.display(), e)))
+}
+
+/// Loads, generates and rotates the proxy's encryption keys
+#[derive(Debug)]
+pub struct KeyManager {
+    dir: PathBuf,
+    rotation_secs: Option<u64>,
+    keys: RwLock<Vec<ProxyKey>>,
+}
+
+impl KeyManager {
+    /// Load the keys in `dir`, generating any that are missing or expired
+    ///
+    /// With `rotation_secs` of `None` only the long-term key is used.
+    pub fn load_or_generate(
+        dir: impl Into<PathBuf>,
+        rotation_secs: Option<u64>,
+    ) -> ProxyResult<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| {
+            ProxyError::Internal(format!(
+                "Failed to create key directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+
+        let mut keys = Vec::new();
+        let entries = fs::read_dir(&dir).map_err(|e| {
+            ProxyError::Internal(format!(
+                "Failed to read key directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == LONG_TERM_FILE
+                || (name.starts_with(ROTATING_PREFIX) && name.ends_with(".json"))
+            {
+                keys.push(ProxyKey::load(&entry.path())?);
+            }
+        }
+
+        if !keys.iter().any(|key| key.info.kind == KeyKind::LongTerm) {
+            let long_term = ProxyKey::generate(KeyKind::LongTerm, unix_now(), None);
+            long_term.save(&dir)?;
+            keys.push(long_term);
+        }
+
+        let manager = Self {
+            dir,
+            rotation_secs: rotation_secs.filter(|secs| *secs > 0),
+            keys: RwLock::new(Vec::new()),
+        };
+        manager.replace_keys(keys);
+        manager.rotate(unix_now())?;
+        Ok(manager)
+    }
+
+    /// Expire old rotating keys and generate a new one when due
+    ///
+    /// Returns whether the active key set changed, in which case the new
+    /// fingerprint should be published.
+    pub fn rotate(&self, now: i64) -> ProxyResult<bool> {
+        let Some(interval) = self.rotation_secs.map(|secs| secs as i64) else {
+            return Ok(false);
+        };
+
+        let mut keys = self.read_keys();
+        let mut changed = false;
+
+        for expired in keys
+            .iter()
+            .filter(|key| key.info.not_after.is_some_and(|not_after| not_after <= now))
+        {
+            let path = self.dir.join(expired.file_name());
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to remove expired key");
+            }
+            changed = true;
+        }
+        keys.retain(|key| key.info.not_after.is_none_or(|not_after| not_after > now));
+
+        let newest = keys
+            .iter()
+            .filter(|key| key.info.kind == KeyKind::Rotating)
+            .map(|key| key.info.not_before)
+            .max();
+        if newest.is_none_or(|not_before| not_before + interval <= now) {
+            let key = ProxyKey::generate(KeyKind::Rotating, now, Some(now + 2 * interval));
+            key.save(&self.dir)?;
+            keys.push(key);
+            changed = true;
+        }
+
+        if changed {
+            self.replace_keys(keys);
+        }
+        Ok(changed)
+    }
+
+    /// Active keys: long-term first, then rotating keys oldest first
+    pub fn keys(&self) -> Vec<EncryptionKeyInfo> {
+        self.read_keys().into_iter().map(|key| key.info).collect()
+    }
+
+    /// Key clients should encrypt to: the newest valid rotating key, or the
+    /// long-term key
+    pub fn current_key(&self, now: i64) -> EncryptionKeyInfo {
+        let keys = self.keys();
+        keys.iter()
+            .rev()
+            .find(|key| key.kind == KeyKind::Rotating && key.is_valid_at(now))
+            .or_else(|| keys.iter().find(|key| key.kind == KeyKind::LongTerm))
+            .cloned()
+            .expect("key manager always holds a long-term key")
+    }
+
+    /// Fingerprint of the active key set, as published on-chain
+    pub fn fingerprint(&self) -> [u8; 32] {
+        fingerprint_keys(&self.keys()).expect("managed keys are well-formed")
+    }
+
+    /// Secret of a currently valid key, for decrypting data sent to it
+    pub fn secret_for(&self, public_key: &PublicKey, now: i64) -> Option<StaticSecret> {
+        let public_key = hex::encode(public_key.as_bytes());
+        self.read_keys()
+            .into_iter()
+            .find(|key| key.info.public_key == public_key && key.info.is_valid_at(now))
+            .map(|key| key.secret)
+    }
+
+    fn read_keys(&self) -> Vec<ProxyKey> {
+        self.keys
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn replace_keys(&self, mut keys: Vec<ProxyKey>) {
+        keys.sort_by_key(|key| (key.info.kind == KeyKind::Rotating, key.info.not_before));
+        *self
+            .keys
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = keys;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+
+    fn key_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "privacy-rpc-keys-{}-{}-{}",
+            name,
+            std::process::id(),
+            rand::thread_rng().next_u64()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_generates_long_term_and_rotating_keys() {
+        let dir = key_dir("generate");
+        let manager = KeyManager::load_or_generate(&dir, Some(HOUR)).unwrap();
+
+        let keys = manager.keys();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].kind, KeyKind::LongTerm);
+        assert_eq!(keys[0].not_after, None);
+        assert_eq!(keys[1].kind, KeyKind::Rotating);
+        assert_eq!(
+            keys[1].not_after,
+            Some(keys[1].not_before + 2 * HOUR as i64)
+        );
+        assert_eq!(manager.current_key(unix_now()), keys[1]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keys_persist_across_restarts() {
+        let dir = key_dir("persist");
+        let first = KeyManager::load_or_generate(&dir, Some(HOUR)).unwrap();
+        let second = KeyManager::load_or_generate(&dir, Some(HOUR)).unwrap();
+
+        assert_eq!(first.keys(), second.keys());
+        assert_eq!(first.fingerprint(), second.fingerprint());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_key_for_one_interval() {
+        let dir = key_dir("rotate");
+        let manager = KeyManager::load_or_generate(&dir, Some(HOUR)).unwrap();
+        let start = manager.keys()[1].not_before;
+        let interval = HOUR as i64;
+
+        assert!(!manager.rotate(start + interval - 1).unwrap());
+
+        assert!(manager.rotate(start + interval).unwrap());
+        let keys = manager.keys();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[1].not_before, start);
+        assert_eq!(keys[2].not_before, start + interval);
+
+        // The first rotating key expires when the third is generated
+        assert!(manager.rotate(start + 2 * interval).unwrap());
+        let keys = manager.keys();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[1].not_before, start + interval);
+        assert!(!dir
+            .join(format!("{}{}.json", ROTATING_PREFIX, start))
+            .exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_without_rotation_only_long_term_key() {
+        let dir = key_dir("static");
+        let manager = KeyManager::load_or_generate(&dir, None).unwrap();
+
+        assert_eq!(manager.keys().len(), 1);
+        assert!(!manager.rotate(i64::MAX / 2).unwrap());
+        assert_eq!(manager.current_key(unix_now()).kind, KeyKind::LongTerm);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_secret_for_valid_keys_only() {
+        let dir = key_dir("secret");
+        let manager = KeyManager::load_or_generate(&dir, Some(HOUR)).unwrap();
+        let rotating = manager.keys()[1].clone();
+        let public: [u8; 32] = hex::decode(&rotating.public_key)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let public = PublicKey::from(public);
+
+        let secret = manager.secret_for(&public, rotating.not_before).unwrap();
+        assert_eq!(PublicKey::from(&secret).as_bytes(), public.as_bytes());
+        assert!(manager
+            .secret_for(&public, rotating.not_after.unwrap())
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_vector() {
+        let keys = vec![
+            EncryptionKeyInfo {
+                kind: KeyKind::LongTerm,
+                public_key: "11".repeat(32),
+                not_before: 1_700_000_000,
+                not_after: None,
+            },
+            EncryptionKeyInfo {
+                kind: KeyKind::Rotating,
+                public_key: "22".repeat(32),
+                not_before: 1_700_003_600,
+                not_after: Some(1_700_010_800),
+            },
+        ];
+
+        assert_eq!(
+            hex::encode(fingerprint_keys(&keys).unwrap()),
+            "ba96c80379c8f6e6d760c72b8682854f633ce732d740d9e85592be65a97aafdd"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_depends_on_validity_window() {
+        let key = EncryptionKeyInfo {
+            kind: KeyKind::Rotating,
+            public_key: "22".repeat(32),
+            not_before: 10,
+            not_after: Some(20),
+        };
+        let extended = EncryptionKeyInfo {
+            not_after: Some(30),
+            ..key.clone()
+        };
+
+        assert_ne!(
+            fingerprint_keys(&[key]).unwrap(),
+            fingerprint_keys(&[extended]).unwrap()
+        );
+        assert!(fingerprint_keys(&[EncryptionKeyInfo {
+            kind: KeyKind::LongTerm,
+            public_key: "zz".to_string(),
+            not_before: 0,
+            not_after: None,
+        }])
+        .is_err());
+    }
+}
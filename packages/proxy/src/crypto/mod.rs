@@ -0,0 +1,15 @@
+//! End-to-end encryption of query results and proxy key management
+//!
+//! Each file implements one scheme for modularity.
+
+mod key_manager;
+mod result_encryption;
+
+pub use key_manager::{
+    fingerprint_keys, unix_now, write_private, KeyManager, DEFAULT_KEY_ROTATION_SECS,
+    KEY_FINGERPRINT_DOMAIN, KEY_FINGERPRINT_VERSION,
+};
+pub use result_encryption::{
+    decrypt_result, encrypt_result, parse_public_key, seal_result, EncryptedPayload,
+    RESULT_ENCRYPTION_ALG,
+};
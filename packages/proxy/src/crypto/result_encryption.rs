@@ -0,0 +1,374 @@
+//! Result encryption to a client-supplied X25519 key
+//!
+//! A query may carry an ephemeral X25519 public key. Its result data is then
+//! sealed so that only the client, and not the party posting the batch, can
+//! read it:
+//!
+//! ```text
+//! shared = X25519(proxy_ephemeral_secret, client_public)
+//! key    = HKDF-SHA256(salt = proxy_ephemeral_public || client_public,
+//!                      ikm  = shared,
+//!                      info = "privacy-rpc/result-encryption/v1")
+//! data   = AES-256-GCM(key, nonce, plaintext = JSON(data), aad = query id)
+//! ```
+//!
+//! The proxy uses a fresh ephemeral key per result. The query id is bound as
+//! associated data, so a ciphertext cannot be passed off as another query's
+//! result. In a coordinated batch the client key is part of the query's
+//! on-chain commitment (see [`crate::hashing`]), so the party posting the
+//! batch cannot swap it for its own.
+//!
+//! Error messages often repeat the queried pubkey or signature, so a failed
+//! result is sealed too: its message is encrypted as `{"error": message}` and
+//! only the error code is left in plaintext.
+
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::QueryResult;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Algorithm identifier carried in every envelope
+pub const RESULT_ENCRYPTION_ALG: &str = "X25519-HKDF-SHA256-AES256GCM";
+
+const HKDF_INFO: &[u8] = b"privacy-rpc/result-encryption/v1";
+const NONCE_LEN: usize = 12;
+
+/// Encrypted result data, serialized in place of the plaintext `data`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedPayload {
+    /// Envelope algorithm, always [`RESULT_ENCRYPTION_ALG`]
+    pub alg: String,
+
+    /// Hex-encoded X25519 public key of the proxy's ephemeral key
+    pub ephemeral_public_key: String,
+
+    /// Base64-encoded 12-byte AES-GCM nonce
+    pub nonce: String,
+
+    /// Base64-encoded ciphertext with the 16-byte GCM tag appended
+    pub ciphertext: String,
+}
+
+impl EncryptedPayload {
+    /// Interpret a result's `data` as an encrypted envelope
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        serde_json::from_value(value.clone())
+            .ok()
+            .filter(|payload: &Self| payload.alg == RESULT_ENCRYPTION_ALG)
+    }
+
+    /// Canonical bytes of the envelope, for hashing
+    ///
+    /// Hashes the decoded key, nonce and ciphertext rather than their JSON
+    /// form, so the digest does not depend on field order or encoding.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.alg.as_bytes());
+        for field in [
+            hex::decode(&self.ephemeral_public_key).unwrap_or_default(),
+            BASE64.decode(&self.nonce).unwrap_or_default(),
+            BASE64.decode(&self.ciphertext).unwrap_or_default(),
+        ] {
+            bytes.extend_from_slice(&(field.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&field);
+        }
+        bytes
+    }
+}
+
+/// Parse a hex-encoded X25519 public key
+pub fn parse_public_key(key: &str) -> ProxyResult<PublicKey> {
+    let bytes: [u8; 32] = hex::decode(key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ProxyError::InvalidQuery("Invalid encryption key".to_string()))?;
+    let key = PublicKey::from(bytes);
+
+    // Clamped scalars are multiples of 8, so any low-order point maps to zero
+    let probe = StaticSecret::from([1u8; 32]).diffie_hellman(&key);
+    if probe.as_bytes().iter().all(|byte| *byte == 0) {
+        return Err(ProxyError::InvalidQuery(
+            "Invalid encryption key: low-order point".to_string(),
+        ));
+    }
+
+    Ok(key)
+}
+
+/// Replace a result's data, or its error message, with an encrypted envelope
+///
+/// A failure's message is sealed in `data` and its `error` is replaced with
+/// the error code, or `"failed"` when it has none. If encryption fails the
+/// result is dropped rather than returned in plaintext.
+pub fn seal_result(result: QueryResult, client_public: &PublicKey) -> QueryResult {
+    let plaintext = match (&result.data, &result.error) {
+        (Some(data), _) => data.clone(),
+        (None, Some(error)) => serde_json::json!({ "error": error }),
+        (None, None) => return result,
+    };
+
+    match encrypt_result(client_public, &result.id, &plaintext).and_then(|payload| {
+        serde_json::to_value(payload).map_err(|e| ProxyError::Crypto(e.to_string()))
+    }) {
+        Ok(sealed) => QueryResult {
+            data: Some(sealed),
+            error: result.error.as_ref().map(|_| {
+                result
+                    .error_code
+                    .map_or("failed", |code| code.as_str())
+                    .to_string()
+            }),
+            ..result
+        },
+        Err(e) => {
+            tracing::warn!(error = %e, "Result encryption failed");
+            QueryResult::failure(result.id, "Result encryption failed".to_string())
+        }
+    }
+}
+
+/// Encrypt result data to a client's public key
+pub fn encrypt_result(
+    client_public: &PublicKey,
+    query_id: &str,
+    data: &serde_json::Value,
+) -> ProxyResult<EncryptedPayload> {
+    let mut secret_bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret_bytes);
+    let ephemeral_secret = StaticSecret::from(secret_bytes);
+    let ephemeral_public = PublicKey::from(&ephemeral_secret);
+
+    let shared = ephemeral_secret.diffie_hellman(client_public);
+    let cipher = derive_cipher(shared.as_bytes(), &ephemeral_public, client_public)?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let plaintext = serde_json::to_vec(data)
+        .map_err(|e| ProxyError::Crypto(format!("Failed to serialize result: {}", e)))?;
+    let ciphertext = cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &plaintext,
+                aad: query_id.as_bytes(),
+            },
+        )
+        .map_err(|_| ProxyError::Crypto("Encryption failed".to_string()))?;
+
+    Ok(EncryptedPayload {
+        alg: RESULT_ENCRYPTION_ALG.to_string(),
+        ephemeral_public_key: hex::encode(ephemeral_public.as_bytes()),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+/// Decrypt result data with the client's secret key
+///
+/// Reference decryptor for the envelope produced by [`encrypt_result`].
+pub fn decrypt_result(
+    client_secret: &StaticSecret,
+    query_id: &str,
+    payload: &EncryptedPayload,
+) -> ProxyResult<serde_json::Value> {
+    if payload.alg != RESULT_ENCRYPTION_ALG {
+        return Err(ProxyError::Crypto(format!(
+            "Unsupported algorithm: {}",
+            payload.alg
+        )));
+    }
+
+    let ephemeral_public = parse_public_key(&payload.ephemeral_public_key)?;
+    let client_public = PublicKey::from(client_secret);
+    let shared = client_secret.diffie_hellman(&ephemeral_public);
+    let cipher = derive_cipher(shared.as_bytes(), &ephemeral_public, &client_public)?;
+
+    let nonce: [u8; NONCE_LEN] = BASE64
+        .decode(&payload.nonce)
+        .ok()
+        .and_then(|nonce| nonce.try_into().ok())
+        .ok_or_else(|| ProxyError::Crypto("Invalid nonce".to_string()))?;
+    let ciphertext = BASE64
+        .decode(&payload.ciphertext)
+        .map_err(|_| ProxyError::Crypto("Invalid ciphertext encoding".to_string()))?;
+
+    let plaintext = cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: query_id.as_bytes(),
+            },
+        )
+        .map_err(|_| ProxyError::Crypto("Decryption failed".to_string()))?;
+
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| ProxyError::Crypto(format!("Invalid result plaintext: {}", e)))
+}
+
+/// Derive the AES-256-GCM cipher from an X25519 shared secret
+fn derive_cipher(
+    shared: &[u8; 32],
+    proxy_public: &PublicKey,
+    client_public: &PublicKey,
+) -> ProxyResult<Aes256Gcm> {
+    // A low-order client key yields an all-zero secret an observer could derive
+    if shared.iter().all(|byte| *byte == 0) {
+        return Err(ProxyError::InvalidQuery(
+            "Invalid encryption key: low-order point".to_string(),
+        ));
+    }
+
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(proxy_public.as_bytes());
+    salt[32..].copy_from_slice(client_public.as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, &mut key)
+        .map_err(|_| ProxyError::Crypto("Key derivation failed".to_string()))?;
+
+    Ok(Aes256Gcm::new(&Key::from(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::QueryErrorCode;
+
+    fn client_keypair(seed: u8) -> (StaticSecret, PublicKey) {
+        let secret = StaticSecret::from([seed; 32]);
+        let public = PublicKey::from(&secret);
+        (secret, public)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (secret, public) = client_keypair(1);
+        let data = serde_json::json!({ "lamports": 1_000_000_000u64 });
+
+        let payload = encrypt_result(&public, "query-1", &data).unwrap();
+        assert_eq!(payload.alg, RESULT_ENCRYPTION_ALG);
+        assert!(!payload.ciphertext.contains("lamports"));
+
+        let decrypted = decrypt_result(&secret, "query-1", &payload).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let (_, public) = client_keypair(1);
+        let (other_secret, _) = client_keypair(2);
+
+        let payload = encrypt_result(&public, "query-1", &serde_json::json!(42)).unwrap();
+        assert!(matches!(
+            decrypt_result(&other_secret, "query-1", &payload),
+            Err(ProxyError::Crypto(_))
+        ));
+    }
+
+    #[test]
+    fn test_query_id_is_authenticated() {
+        let (secret, public) = client_keypair(1);
+
+        let payload = encrypt_result(&public, "query-1", &serde_json::json!(42)).unwrap();
+        assert!(decrypt_result(&secret, "query-2", &payload).is_err());
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails() {
+        let (secret, public) = client_keypair(1);
+
+        let mut payload = encrypt_result(&public, "query-1", &serde_json::json!(42)).unwrap();
+        let mut ciphertext = BASE64.decode(&payload.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        payload.ciphertext = BASE64.encode(ciphertext);
+
+        assert!(decrypt_result(&secret, "query-1", &payload).is_err());
+    }
+
+    #[test]
+    fn test_fresh_ephemeral_key_per_result() {
+        let (_, public) = client_keypair(1);
+        let data = serde_json::json!(42);
+
+        let a = encrypt_result(&public, "query-1", &data).unwrap();
+        let b = encrypt_result(&public, "query-1", &data).unwrap();
+        assert_ne!(a.ephemeral_public_key, b.ephemeral_public_key);
+        assert_ne!(a.ciphertext, b.ciphertext);
+    }
+
+    #[test]
+    fn test_rejects_low_order_key() {
+        let identity = PublicKey::from([0u8; 32]);
+        assert!(matches!(
+            encrypt_result(&identity, "query-1", &serde_json::json!(42)),
+            Err(ProxyError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_seal_result_encrypts_data_and_errors() {
+        let (secret, public) = client_keypair(1);
+
+        let sealed = seal_result(
+            QueryResult::success("query-1".to_string(), serde_json::json!(42)),
+            &public,
+        );
+        assert!(sealed.success);
+        let payload = EncryptedPayload::from_value(sealed.data.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            decrypt_result(&secret, "query-1", &payload).unwrap(),
+            serde_json::json!(42)
+        );
+
+        let message = "Invalid pubkey 'So11111111111111111111111111111111111111112'";
+        let failure = QueryResult::failure("query-2".to_string(), message.to_string());
+        let sealed = seal_result(failure, &public);
+        assert!(!sealed.success);
+        assert_eq!(sealed.error.as_deref(), Some("failed"));
+        let payload = EncryptedPayload::from_value(sealed.data.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            decrypt_result(&secret, "query-2", &payload).unwrap(),
+            serde_json::json!({ "error": message })
+        );
+
+        let timeout = seal_result(QueryResult::timeout("query-3".to_string(), 100), &public);
+        assert_eq!(timeout.error.as_deref(), Some("timeout"));
+        assert_eq!(timeout.error_code, Some(QueryErrorCode::Timeout));
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        let (_, public) = client_keypair(1);
+        let parsed = parse_public_key(&hex::encode(public.as_bytes())).unwrap();
+        assert_eq!(parsed.as_bytes(), public.as_bytes());
+
+        assert!(parse_public_key("zz").is_err());
+        assert!(parse_public_key(&hex::encode([0u8; 32])).is_err());
+        assert!(parse_public_key(&hex::encode([1u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_envelope_round_trips_through_json_value() {
+        let (_, public) = client_keypair(1);
+        let payload = encrypt_result(&public, "query-1", &serde_json::json!(42)).unwrap();
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(EncryptedPayload::from_value(&value), Some(payload.clone()));
+        assert_eq!(
+            EncryptedPayload::from_value(&serde_json::json!({ "lamports": 1 })),
+            None
+        );
+        assert_eq!(payload.canonical_bytes(), payload.clone().canonical_bytes());
+    }
+}
@@ -0,0 +1,133 @@
+//! Decoy corpus loading
+//!
+//! The corpus is a JSON file listing plausible query targets:
+//!
+//! ```json
+//! {
+//!   "accounts": [{ "pubkey": "So11111111111111111111111111111111111111112", "weight": 5 }],
+//!   "tokenAccounts": [{ "pubkey": "..." }]
+//! }
+//! ```
+//!
+//! Entries are ordered by popularity (most queried first), which is the rank
+//! used by [`SamplingDistribution::Zipf`](crate::enums::SamplingDistribution).
+//! Weights default to 1.
+
+use crate::error::{ProxyError, ProxyResult};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// A single decoy target
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CorpusEntry {
+    /// Base58-encoded public key
+    pub pubkey: String,
+
+    /// Relative popularity used by weighted sampling
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+/// Plausible query targets decoys are drawn from
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecoyCorpus {
+    /// System or program-owned accounts (getBalance, getAccountInfo)
+    #[serde(default)]
+    pub accounts: Vec<CorpusEntry>,
+
+    /// SPL token accounts (getTokenAccountBalance)
+    #[serde(default)]
+    pub token_accounts: Vec<CorpusEntry>,
+}
+
+impl DecoyCorpus {
+    /// Load and validate a corpus file
+    pub fn load(path: impl AsRef<Path>) -> ProxyResult<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| {
+            ProxyError::Internal(format!(
+                "Failed to read decoy corpus {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&contents)
+    }
+
+    /// Parse and validate a corpus from JSON
+    ///
+    /// Every pubkey must parse: a decoy the proxy rejects locally would never
+    /// reach the upstream and shrink the batch it sees.
+    pub fn from_json(json: &str) -> ProxyResult<Self> {
+        let corpus: Self = serde_json::from_str(json)
+            .map_err(|e| ProxyError::Internal(format!("Malformed decoy corpus: {}", e)))?;
+
+        for entry in corpus.accounts.iter().chain(&corpus.token_accounts) {
+            Pubkey::from_str(&entry.pubkey)
+                .map_err(|e| ProxyError::InvalidPubkey(format!("{}: {}", entry.pubkey, e)))?;
+            if !entry.weight.is_finite() || entry.weight < 0.0 {
+                return Err(ProxyError::Internal(format!(
+                    "Invalid decoy weight {} for {}",
+                    entry.weight, entry.pubkey
+                )));
+            }
+        }
+
+        Ok(corpus)
+    }
+
+    /// Check whether the corpus has no entries at all
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.token_accounts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_from_json() {
+        let corpus = DecoyCorpus::from_json(
+            r#"{
+                "accounts": [
+                    { "pubkey": "So11111111111111111111111111111111111111112", "weight": 5 },
+                    { "pubkey": "11111111111111111111111111111111" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(corpus.accounts.len(), 2);
+        assert_eq!(corpus.accounts[0].weight, 5.0);
+        assert_eq!(corpus.accounts[1].weight, 1.0);
+        assert!(corpus.token_accounts.is_empty());
+        assert!(!corpus.is_empty());
+    }
+
+    #[test]
+    fn test_corpus_rejects_invalid_entries() {
+        let invalid_pubkey = r#"{ "accounts": [{ "pubkey": "not-a-pubkey" }] }"#;
+        assert!(matches!(
+            DecoyCorpus::from_json(invalid_pubkey),
+            Err(ProxyError::InvalidPubkey(_))
+        ));
+
+        let negative_weight = r#"{ "tokenAccounts": [{ "pubkey": "11111111111111111111111111111111", "weight": -1 }] }"#;
+        assert!(DecoyCorpus::from_json(negative_weight).is_err());
+    }
+
+    #[test]
+    fn test_example_corpus_is_valid() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/decoy-corpus.example.json");
+        assert!(!DecoyCorpus::load(path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,496 @@
+//! Decoy query generation
+
+use super::{CorpusEntry, DecoyCorpus};
+use crate::enums::{RpcMethod, SamplingDistribution};
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{DecoyConfig, Query};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use tracing::warn;
+
+/// Draws tried per cohort member before giving up on distinct targets
+const COHORT_DRAWS_PER_MEMBER: usize = 8;
+
+/// Build a sampler over corpus entries, or `None` if nothing can be drawn
+fn entry_sampler(entries: &[CorpusEntry], config: &DecoyConfig) -> Option<WeightedIndex<f64>> {
+    let weights = entries
+        .iter()
+        .enumerate()
+        .map(|(rank, entry)| match config.distribution {
+            SamplingDistribution::Uniform => 1.0,
+            SamplingDistribution::Weighted => entry.weight,
+            SamplingDistribution::Zipf => 1.0 / ((rank + 1) as f64).powf(config.zipf_exponent),
+        });
+    WeightedIndex::new(weights).ok()
+}
+
+/// Generates decoy queries that pad batches before they reach the upstream
+///
+/// Decoys are ordinary queries for the methods of the configured mix on
+/// targets sampled from the corpus, so the upstream executes them exactly like
+/// real queries.
+#[derive(Debug)]
+pub struct DecoyGenerator {
+    config: DecoyConfig,
+    corpus: DecoyCorpus,
+    account_sampler: Option<WeightedIndex<f64>>,
+    token_account_sampler: Option<WeightedIndex<f64>>,
+    methods: Vec<RpcMethod>,
+    method_sampler: WeightedIndex<f64>,
+}
+
+impl DecoyGenerator {
+    /// Load the corpus named by the configuration and build a generator
+    pub fn from_config(config: DecoyConfig) -> ProxyResult<Self> {
+        let corpus = DecoyCorpus::load(&config.corpus_path)?;
+        Self::new(corpus, config)
+    }
+
+    /// Build a generator over an already loaded corpus
+    ///
+    /// Methods of the mix whose corpus list is empty are skipped; the mix must
+    /// leave at least one method with a positive share.
+    pub fn new(corpus: DecoyCorpus, config: DecoyConfig) -> ProxyResult<Self> {
+        if !config.zipf_exponent.is_finite() || config.zipf_exponent < 0.0 {
+            return Err(ProxyError::Internal(format!(
+                "Invalid decoy Zipf exponent {}",
+                config.zipf_exponent
+            )));
+        }
+
+        let account_sampler = entry_sampler(&corpus.accounts, &config);
+        let token_account_sampler = entry_sampler(&corpus.token_accounts, &config);
+
+        let mut methods = Vec::new();
+        let mut weights = Vec::new();
+        for &(method, weight) in &config.method_mix {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(ProxyError::Internal(format!(
+                    "Invalid decoy weight {} for {}",
+                    weight, method
+                )));
+            }
+            let sampler = match method {
+                RpcMethod::GetBalance | RpcMethod::GetAccountInfo => &account_sampler,
+                RpcMethod::GetTokenAccountBalance => &token_account_sampler,
+                _ => {
+                    return Err(ProxyError::Internal(format!(
+                        "{} cannot be used for decoy queries",
+                        method
+                    )))
+                }
+            };
+            if sampler.is_none() {
+                warn!(method = %method, "No decoy corpus entries for method, skipping it");
+                continue;
+            }
+            methods.push(method);
+            weights.push(weight);
+        }
+
+        let method_sampler = WeightedIndex::new(&weights).map_err(|_| {
+            ProxyError::Internal("Decoy method mix has no usable method".to_string())
+        })?;
+
+        Ok(Self {
+            config,
+            corpus,
+            account_sampler,
+            token_account_sampler,
+            methods,
+            method_sampler,
+        })
+    }
+
+    /// Number of decoys added to a batch of `real_count` queries
+    pub fn decoy_count(&self, real_count: usize) -> usize {
+        self.config.decoy_count(real_count)
+    }
+
+    /// Generate the decoys for a batch of real queries
+    ///
+    /// Each decoy copies the commitment of a randomly chosen real query so the
+    /// commitment mix of the batch is preserved.
+    pub fn generate(&self, real: &[Query]) -> Vec<Query> {
+        let mut rng = rand::thread_rng();
+        (0..self.decoy_count(real.len()))
+            .filter_map(|i| {
+                let method = self.methods[self.method_sampler.sample(&mut rng)];
+                let commitment = real.choose(&mut rng).and_then(|q| q.commitment.clone());
+                self.decoy(format!("decoy-{}", i), method, commitment, &mut rng)
+            })
+            .collect()
+    }
+
+    /// Generate `count` decoys mirroring the methods of the real queries
+    ///
+    /// Each decoy copies the method and commitment of a randomly chosen real
+    /// query (getMultipleAccounts decoys also its number of accounts), so the
+    /// padding follows the method mix of the batch. Real queries no decoy can
+    /// mirror, such as getTransaction, are skipped; if none is left the
+    /// configured mix is used instead.
+    pub fn generate_like(&self, real: &[Query], count: usize) -> Vec<Query> {
+        let mut rng = rand::thread_rng();
+        let templates: Vec<&Query> = real.iter().filter(|q| self.can_mirror(q)).collect();
+        (0..count)
+            .filter_map(|i| {
+                let id = format!("padding-{}", i);
+                match templates.choose(&mut rng) {
+                    Some(template) => self.mirror(id, template, &mut rng),
+                    None => {
+                        let method = self.methods[self.method_sampler.sample(&mut rng)];
+                        let commitment = real.choose(&mut rng).and_then(|q| q.commitment.clone());
+                        self.decoy(id, method, commitment, &mut rng)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Generate the cohort of a query: up to `size` decoys with its method on
+    /// distinct targets other than its own
+    ///
+    /// Targets are drawn with an RNG seeded from `key`, the method and the
+    /// target of `template`, so a query always gets the same cohort as long as
+    /// the key and the corpus stay the same. Fewer decoys come back when the
+    /// corpus holds too few distinct targets, none for queries without a
+    /// target or that cannot be mirrored.
+    pub fn cohort(&self, key: &[u8; 32], template: &Query, size: usize) -> Vec<Query> {
+        let Some(target) = template.target() else {
+            return Vec::new();
+        };
+        if template.method == RpcMethod::GetBlockHeight || !self.can_mirror(template) {
+            return Vec::new();
+        }
+
+        let seed: [u8; 32] = Sha256::new()
+            .chain_update(key)
+            .chain_update(template.method.to_string().as_bytes())
+            .chain_update(target.as_bytes())
+            .finalize()
+            .into();
+        let mut rng = StdRng::from_seed(seed);
+
+        let mut targets = HashSet::from([target]);
+        let mut cohort = Vec::with_capacity(size);
+        for _ in 0..size * COHORT_DRAWS_PER_MEMBER {
+            if cohort.len() == size {
+                break;
+            }
+            let id = format!("cohort-{}", cohort.len());
+            let Some(decoy) = self.mirror(id, template, &mut rng) else {
+                break;
+            };
+            if decoy.target().is_some_and(|target| targets.insert(target)) {
+                cohort.push(decoy);
+            }
+        }
+        cohort
+    }
+
+    /// Check whether a decoy can be built with the same method as `query`
+    fn can_mirror(&self, query: &Query) -> bool {
+        match query.method {
+            RpcMethod::GetBalance | RpcMethod::GetAccountInfo | RpcMethod::GetMultipleAccounts => {
+                self.account_sampler.is_some()
+            }
+            RpcMethod::GetTokenAccountBalance => self.token_account_sampler.is_some(),
+            RpcMethod::GetBlockHeight => true,
+            RpcMethod::GetTransaction => false,
+        }
+    }
+
+    /// Build a decoy shaped like `template`
+    fn mirror<R: Rng>(&self, id: String, template: &Query, rng: &mut R) -> Option<Query> {
+        let commitment = template.commitment.clone();
+        match template.method {
+            // No target to hide: only the shape is copied
+            RpcMethod::GetBlockHeight => Some(Query {
+                id,
+                method: template.method,
+                pubkey: None,
+                params: template.params.clone(),
+                commitment,
+                salt: None,
+                encryption_key: None,
+            }),
+            RpcMethod::GetMultipleAccounts => {
+                let sampler = self.account_sampler.as_ref()?;
+                let count = template
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.as_array())
+                    .map_or(1, Vec::len)
+                    .max(1);
+                let pubkeys: Vec<&str> = (0..count)
+                    .map(|_| self.corpus.accounts[sampler.sample(rng)].pubkey.as_str())
+                    .collect();
+                let query = Query::with_params(id, template.method, serde_json::json!(pubkeys));
+                Some(match commitment {
+                    Some(commitment) => query.with_commitment(commitment),
+                    None => query,
+                })
+            }
+            method => self.decoy(id, method, commitment, rng),
+        }
+    }
+
+    /// Build a decoy for `method`, or `None` if the corpus has no target for it
+    fn decoy<R: Rng>(
+        &self,
+        id: String,
+        method: RpcMethod,
+        commitment: Option<String>,
+        rng: &mut R,
+    ) -> Option<Query> {
+        let (entries, sampler) = match method {
+            RpcMethod::GetBalance | RpcMethod::GetAccountInfo => {
+                (&self.corpus.accounts, self.account_sampler.as_ref()?)
+            }
+            RpcMethod::GetTokenAccountBalance => (
+                &self.corpus.token_accounts,
+                self.token_account_sampler.as_ref()?,
+            ),
+            _ => return None,
+        };
+
+        let pubkey = entries[sampler.sample(rng)].pubkey.clone();
+        let query = Query::new(id, method, pubkey);
+        Some(match commitment {
+            Some(commitment) => query.with_commitment(commitment),
+            None => query,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POPULAR: &str = "So11111111111111111111111111111111111111112";
+    const RARE: &str = "11111111111111111111111111111111";
+    const TOKEN_ACCOUNT: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn corpus(token_accounts: bool) -> DecoyCorpus {
+        let entry = |pubkey: &str, weight: f64| CorpusEntry {
+            pubkey: pubkey.to_string(),
+            weight,
+        };
+        DecoyCorpus {
+            accounts: vec![entry(POPULAR, 1.0), entry(RARE, 0.0)],
+            token_accounts: if token_accounts {
+                vec![entry(TOKEN_ACCOUNT, 1.0)]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn config(target_size: usize) -> DecoyConfig {
+        DecoyConfig::new("corpus.json".to_string(), target_size)
+    }
+
+    fn real_queries(count: usize) -> Vec<Query> {
+        (0..count)
+            .map(|i| {
+                Query::new(
+                    format!("query-{}", i),
+                    RpcMethod::GetBalance,
+                    RARE.to_string(),
+                )
+                .with_commitment("finalized".to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generates_decoys_up_to_target() {
+        let generator = DecoyGenerator::new(corpus(true), config(10)).unwrap();
+        let decoys = generator.generate(&real_queries(4));
+
+        assert_eq!(decoys.len(), 6);
+        for decoy in &decoys {
+            assert_eq!(decoy.commitment.as_deref(), Some("finalized"));
+            match decoy.method {
+                RpcMethod::GetBalance | RpcMethod::GetAccountInfo => {
+                    assert_eq!(decoy.pubkey.as_deref(), Some(POPULAR))
+                }
+                RpcMethod::GetTokenAccountBalance => {
+                    assert_eq!(decoy.pubkey.as_deref(), Some(TOKEN_ACCOUNT))
+                }
+                other => panic!("unexpected decoy method {}", other),
+            }
+        }
+        assert!(generator.generate(&real_queries(10)).is_empty());
+    }
+
+    #[test]
+    fn test_method_mix_is_respected() {
+        let config = config(50).with_method_mix(vec![
+            (RpcMethod::GetAccountInfo, 1.0),
+            (RpcMethod::GetBalance, 0.0),
+        ]);
+        let generator = DecoyGenerator::new(corpus(true), config).unwrap();
+
+        assert!(generator
+            .generate(&real_queries(1))
+            .iter()
+            .all(|decoy| decoy.method == RpcMethod::GetAccountInfo));
+    }
+
+    #[test]
+    fn test_methods_without_targets_are_skipped() {
+        let generator = DecoyGenerator::new(corpus(false), config(50)).unwrap();
+        assert!(generator
+            .generate(&real_queries(1))
+            .iter()
+            .all(|decoy| decoy.method != RpcMethod::GetTokenAccountBalance));
+
+        let token_only = config(50).with_method_mix(vec![(RpcMethod::GetTokenAccountBalance, 1.0)]);
+        assert!(DecoyGenerator::new(corpus(false), token_only).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unsupported_methods() {
+        let config = config(10).with_method_mix(vec![(RpcMethod::GetTransaction, 1.0)]);
+        assert!(DecoyGenerator::new(corpus(true), config).is_err());
+    }
+
+    #[test]
+    fn test_padding_mirrors_real_methods() {
+        let generator = DecoyGenerator::new(corpus(true), config(0)).unwrap();
+        let real = vec![
+            Query::with_params(
+                "accounts".to_string(),
+                RpcMethod::GetMultipleAccounts,
+                serde_json::json!([RARE, RARE, RARE]),
+            ),
+            Query::with_params(
+                "height".to_string(),
+                RpcMethod::GetBlockHeight,
+                serde_json::Value::Null,
+            )
+            .with_commitment("processed".to_string()),
+            Query::with_params(
+                "tx".to_string(),
+                RpcMethod::GetTransaction,
+                serde_json::json!("signature"),
+            ),
+        ];
+
+        let padding = generator.generate_like(&real, 40);
+        assert_eq!(padding.len(), 40);
+        for decoy in &padding {
+            match decoy.method {
+                RpcMethod::GetMultipleAccounts => {
+                    assert_eq!(
+                        decoy.params,
+                        Some(serde_json::json!([POPULAR, POPULAR, POPULAR]))
+                    )
+                }
+                RpcMethod::GetBlockHeight => {
+                    assert_eq!(decoy.commitment.as_deref(), Some("processed"))
+                }
+                other => panic!("unexpected padding method {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_padding_falls_back_to_method_mix() {
+        let generator = DecoyGenerator::new(corpus(false), config(0)).unwrap();
+        let real = vec![Query::with_params(
+            "tx".to_string(),
+            RpcMethod::GetTransaction,
+            serde_json::json!("signature"),
+        )];
+
+        let padding = generator.generate_like(&real, 10);
+        assert_eq!(padding.len(), 10);
+        assert!(padding.iter().all(|decoy| matches!(
+            decoy.method,
+            RpcMethod::GetBalance | RpcMethod::GetAccountInfo
+        )));
+    }
+
+    #[test]
+    fn test_cohort_is_stable_and_distinct() {
+        let corpus = DecoyCorpus {
+            accounts: (1..=20u8)
+                .map(|i| CorpusEntry {
+                    pubkey: solana_sdk::pubkey::Pubkey::new_from_array([i; 32]).to_string(),
+                    weight: 1.0,
+                })
+                .collect(),
+            token_accounts: Vec::new(),
+        };
+        let generator = DecoyGenerator::new(corpus.clone(), config(0)).unwrap();
+        let template = &real_queries(1)[0];
+        let targets = |cohort: Vec<Query>| -> Vec<Option<String>> {
+            cohort.into_iter().map(|decoy| decoy.pubkey).collect()
+        };
+
+        let cohort = generator.cohort(&[1; 32], template, 5);
+        assert_eq!(cohort.len(), 5);
+        assert!(cohort
+            .iter()
+            .all(|decoy| decoy.method == RpcMethod::GetBalance
+                && decoy.commitment.as_deref() == Some("finalized")));
+        let members: HashSet<_> = cohort.iter().map(|decoy| decoy.pubkey.clone()).collect();
+        assert_eq!(members.len(), 5);
+        assert!(!members.contains(&Some(RARE.to_string())));
+
+        // Same key, same cohort, even from another generator over the corpus
+        let again = DecoyGenerator::new(corpus, config(0)).unwrap();
+        assert_eq!(
+            targets(again.cohort(&[1; 32], template, 5)),
+            targets(cohort.clone())
+        );
+        assert_ne!(
+            targets(generator.cohort(&[2; 32], template, 5)),
+            targets(cohort)
+        );
+    }
+
+    #[test]
+    fn test_cohort_is_limited_by_corpus() {
+        let generator = DecoyGenerator::new(corpus(true), config(0)).unwrap();
+
+        // RARE has weight 0, so POPULAR is the only other target
+        assert_eq!(generator.cohort(&[1; 32], &real_queries(1)[0], 4).len(), 1);
+
+        let transaction = Query::with_params(
+            "tx".to_string(),
+            RpcMethod::GetTransaction,
+            serde_json::json!("signature"),
+        );
+        assert!(generator.cohort(&[1; 32], &transaction, 4).is_empty());
+    }
+
+    #[test]
+    fn test_sampling_distribution() {
+        let mix = vec![(RpcMethod::GetBalance, 1.0)];
+        let draws = |distribution| {
+            let config = config(100)
+                .with_method_mix(mix.clone())
+                .with_distribution(distribution)
+                .with_zipf_exponent(2.0);
+            let generator = DecoyGenerator::new(corpus(false), config).unwrap();
+            generator
+                .generate(&[])
+                .iter()
+                .filter(|decoy| decoy.pubkey.as_deref() == Some(RARE))
+                .count()
+        };
+
+        // The rare entry has weight 0, rank 2 under Zipf (1/4 of the first)
+        assert_eq!(draws(SamplingDistribution::Weighted), 0);
+        assert!(draws(SamplingDistribution::Uniform) > 0);
+        let zipf = draws(SamplingDistribution::Zipf);
+        assert!(zipf > 0 && zipf < 50);
+    }
+}
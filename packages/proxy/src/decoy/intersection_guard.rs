@@ -0,0 +1,279 @@
+//! Intersection attack mitigation
+//!
+//! An upstream that sees the same target in batch after batch can intersect
+//! those batches: fresh random decoys drop out and the recurring target is
+//! left alone. The guard counts target recurrence in a salted sketch and, once
+//! a target crossed the threshold, attaches its stable cohort of decoys to
+//! every batch carrying it, so the intersection always keeps the cohort.
+//!
+//! Protection degrades when a recurring target cannot get its full cohort
+//! (too small a corpus, a method no decoy can mirror, or the per-batch bound).
+//! Such batches are counted and logged, without their targets.
+
+use super::{DecoyGenerator, RecurrenceSketch};
+use crate::crypto::write_private;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{CohortConfig, CohortMetrics, Query};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Load the cohort secret at `path`, generating it on first use
+fn load_or_generate_key(path: &Path) -> ProxyResult<[u8; 32]> {
+    match fs::read_to_string(path) {
+        Ok(contents) => hex::decode(contents.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ProxyError::Crypto(format!("Malformed cohort key in {}", path.display()))
+            }),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            write_private(path, hex::encode(key).as_bytes())?;
+            info!(path = %path.display(), "Generated cohort key");
+            Ok(key)
+        }
+        Err(e) => Err(ProxyError::Internal(format!(
+            "Failed to read cohort key {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    batches: AtomicU64,
+    recurring_targets: AtomicU64,
+    cohort_decoys: AtomicU64,
+    unprotected_targets: AtomicU64,
+    degraded_batches: AtomicU64,
+    degraded: AtomicBool,
+}
+
+/// Gives recurring query targets persistent decoy cohorts
+pub struct IntersectionGuard {
+    decoys: Arc<DecoyGenerator>,
+    config: CohortConfig,
+    key: [u8; 32],
+    sketch: Mutex<RecurrenceSketch>,
+    counters: Counters,
+}
+
+impl IntersectionGuard {
+    /// Build a guard, loading the cohort secret named by the configuration
+    pub fn from_config(decoys: Arc<DecoyGenerator>, config: CohortConfig) -> ProxyResult<Self> {
+        let key = match &config.key_path {
+            Some(path) => load_or_generate_key(Path::new(path))?,
+            None => {
+                let mut key = [0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                key
+            }
+        };
+        Ok(Self::new(decoys, config, key))
+    }
+
+    /// Build a guard deriving cohorts from `key`
+    pub fn new(decoys: Arc<DecoyGenerator>, config: CohortConfig, key: [u8; 32]) -> Self {
+        let sketch = RecurrenceSketch::new(config.sketch_width, config.sketch_depth);
+        Self {
+            decoys,
+            config,
+            key,
+            sketch: Mutex::new(sketch),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Record the targets of a batch and return the cohort decoys it needs
+    ///
+    /// Each distinct target counts once per batch. Targets at or above the
+    /// threshold contribute their cohort, up to the per-batch bound.
+    pub fn protect(&self, real: &[Query]) -> Vec<Query> {
+        let mut recurring = Vec::new();
+        {
+            let mut sketch = self
+                .sketch
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut seen = HashSet::new();
+            for query in real {
+                let Some(target) = query.target() else {
+                    continue;
+                };
+                if seen.insert(target.clone()) && sketch.record(&target) >= self.config.threshold {
+                    recurring.push(query);
+                }
+            }
+
+            let batches = self.counters.batches.fetch_add(1, Ordering::Relaxed) + 1;
+            if batches.is_multiple_of(self.config.decay_batches) {
+                sketch.decay();
+            }
+        }
+
+        let mut cohorts = Vec::new();
+        let mut unprotected = 0;
+        for query in &recurring {
+            let cohort = self
+                .decoys
+                .cohort(&self.key, query, self.config.cohort_size);
+            let room = self.config.max_cohort_decoys.saturating_sub(cohorts.len());
+            if cohort.len() < self.config.cohort_size || cohort.len() > room {
+                unprotected += 1;
+            }
+            cohorts.extend(cohort.into_iter().take(room));
+        }
+
+        self.counters
+            .recurring_targets
+            .fetch_add(recurring.len() as u64, Ordering::Relaxed);
+        self.counters
+            .cohort_decoys
+            .fetch_add(cohorts.len() as u64, Ordering::Relaxed);
+        self.counters
+            .degraded
+            .store(unprotected > 0, Ordering::Relaxed);
+        if unprotected > 0 {
+            self.counters
+                .unprotected_targets
+                .fetch_add(unprotected, Ordering::Relaxed);
+            self.counters
+                .degraded_batches
+                .fetch_add(1, Ordering::Relaxed);
+            warn!(
+                recurring = recurring.len(),
+                unprotected = unprotected,
+                "Intersection protection degraded: recurring targets without a full cohort"
+            );
+        }
+
+        cohorts
+    }
+
+    /// Snapshot of the guard's counters
+    pub fn metrics(&self) -> CohortMetrics {
+        CohortMetrics {
+            threshold: self.config.threshold,
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            recurring_targets: self.counters.recurring_targets.load(Ordering::Relaxed),
+            cohort_decoys: self.counters.cohort_decoys.load(Ordering::Relaxed),
+            unprotected_targets: self.counters.unprotected_targets.load(Ordering::Relaxed),
+            degraded_batches: self.counters.degraded_batches.load(Ordering::Relaxed),
+            degraded: self.counters.degraded.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoy::{CorpusEntry, DecoyCorpus};
+    use crate::enums::RpcMethod;
+    use crate::types::DecoyConfig;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn pubkey(i: u8) -> String {
+        Pubkey::new_from_array([i; 32]).to_string()
+    }
+
+    fn guard(corpus_size: u8, config: CohortConfig) -> IntersectionGuard {
+        let corpus = DecoyCorpus {
+            accounts: (1..=corpus_size)
+                .map(|i| CorpusEntry {
+                    pubkey: pubkey(i),
+                    weight: 1.0,
+                })
+                .collect(),
+            token_accounts: Vec::new(),
+        };
+        let decoys = DecoyGenerator::new(corpus, DecoyConfig::new(String::new(), 0)).unwrap();
+        IntersectionGuard::new(Arc::new(decoys), config, [7; 32])
+    }
+
+    fn batch(targets: &[u8]) -> Vec<Query> {
+        targets
+            .iter()
+            .map(|i| Query::new(format!("query-{}", i), RpcMethod::GetBalance, pubkey(*i)))
+            .collect()
+    }
+
+    fn targets(decoys: &[Query]) -> Vec<String> {
+        decoys.iter().filter_map(Query::target).collect()
+    }
+
+    #[test]
+    fn test_recurring_targets_keep_their_cohort() {
+        let guard = guard(50, CohortConfig::new(3, 4));
+
+        // Repeated targets count once per batch
+        assert!(guard.protect(&batch(&[100, 100, 101])).is_empty());
+        assert!(guard.protect(&batch(&[100, 102])).is_empty());
+
+        let first = guard.protect(&batch(&[100, 103]));
+        assert_eq!(first.len(), 4);
+        assert!(!targets(&first).contains(&pubkey(100)));
+
+        let second = guard.protect(&batch(&[104, 100, 105]));
+        assert_eq!(targets(&second), targets(&first));
+
+        let metrics = guard.metrics();
+        assert_eq!(metrics.batches, 4);
+        assert_eq!(metrics.recurring_targets, 2);
+        assert_eq!(metrics.cohort_decoys, 8);
+        assert_eq!(metrics.degraded_batches, 0);
+        assert!(!metrics.degraded);
+    }
+
+    #[test]
+    fn test_small_corpus_degrades_protection() {
+        let guard = guard(2, CohortConfig::new(1, 4));
+
+        assert_eq!(guard.protect(&batch(&[100])).len(), 2);
+        let metrics = guard.metrics();
+        assert_eq!(metrics.unprotected_targets, 1);
+        assert_eq!(metrics.degraded_batches, 1);
+        assert!(metrics.degraded);
+    }
+
+    #[test]
+    fn test_cohort_decoys_are_bounded_per_batch() {
+        let guard = guard(50, CohortConfig::new(1, 4).with_max_cohort_decoys(6));
+
+        assert_eq!(guard.protect(&batch(&[100, 101])).len(), 6);
+        assert_eq!(guard.metrics().unprotected_targets, 1);
+
+        assert_eq!(guard.protect(&batch(&[100])).len(), 4);
+        assert!(!guard.metrics().degraded);
+    }
+
+    #[test]
+    fn test_recurrence_decays() {
+        // Halving after every batch keeps a target from ever counting twice
+        let guard = guard(50, CohortConfig::new(2, 4).with_decay_batches(1));
+        for _ in 0..3 {
+            assert!(guard.protect(&batch(&[100])).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_cohort_key_persists() {
+        let path = std::env::temp_dir().join(format!("cohort-key-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let key = load_or_generate_key(&path).unwrap();
+        assert_eq!(load_or_generate_key(&path).unwrap(), key);
+
+        fs::write(&path, "not hex").unwrap();
+        assert!(load_or_generate_key(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
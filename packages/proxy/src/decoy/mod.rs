@@ -0,0 +1,16 @@
+//! Decoy query injection
+//!
+//! Pads batches with queries on plausible targets from a configurable corpus
+//! so the upstream RPC sees more calls than the batch really holds. Decoy
+//! results are discarded by the executor. Targets that recur across batches get
+//! a persistent cohort of decoys against intersection attacks.
+
+mod decoy_corpus;
+mod decoy_generator;
+mod intersection_guard;
+mod recurrence_sketch;
+
+pub use decoy_corpus::{CorpusEntry, DecoyCorpus};
+pub use decoy_generator::DecoyGenerator;
+pub use intersection_guard::IntersectionGuard;
+pub use recurrence_sketch::RecurrenceSketch;
@@ -0,0 +1,135 @@
+//! Salted count-min sketch of query target recurrence
+//!
+//! Counts how many batches each target appeared in without storing targets:
+//! a target only selects one counter per row through a salted SHA-256, and
+//! the salt is drawn at startup and never leaves memory. Estimates may be
+//! too high (hash collisions) but never too low.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Count-min sketch over query targets
+#[derive(Debug)]
+pub struct RecurrenceSketch {
+    salt: [u8; 32],
+    width: usize,
+    rows: Vec<Vec<u32>>,
+}
+
+impl RecurrenceSketch {
+    /// Create an empty sketch of `depth` rows of `width` counters
+    pub fn new(width: usize, depth: usize) -> Self {
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        let width = width.max(1);
+        Self {
+            salt,
+            width,
+            rows: vec![vec![0; width]; depth.max(1)],
+        }
+    }
+
+    fn column(&self, row: usize, target: &str) -> usize {
+        let digest = Sha256::new()
+            .chain_update(self.salt)
+            .chain_update((row as u32).to_le_bytes())
+            .chain_update(target.as_bytes())
+            .finalize();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_le_bytes(bytes) % self.width as u64) as usize
+    }
+
+    /// Count one more occurrence of `target` and return its new estimate
+    ///
+    /// Uses conservative update: only the counters at the current minimum are
+    /// raised, which keeps collisions from inflating other targets as much.
+    pub fn record(&mut self, target: &str) -> u32 {
+        let columns: Vec<usize> = (0..self.rows.len())
+            .map(|row| self.column(row, target))
+            .collect();
+        let estimate = self.estimate_at(&columns).saturating_add(1);
+        for (row, column) in columns.into_iter().enumerate() {
+            let counter = &mut self.rows[row][column];
+            *counter = (*counter).max(estimate);
+        }
+        estimate
+    }
+
+    /// Estimated number of occurrences of `target`
+    pub fn estimate(&self, target: &str) -> u32 {
+        let columns: Vec<usize> = (0..self.rows.len())
+            .map(|row| self.column(row, target))
+            .collect();
+        self.estimate_at(&columns)
+    }
+
+    fn estimate_at(&self, columns: &[usize]) -> u32 {
+        columns
+            .iter()
+            .enumerate()
+            .map(|(row, column)| self.rows[row][*column])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Halve every counter, so old activity fades out
+    pub fn decay(&mut self) {
+        for counter in self.rows.iter_mut().flatten() {
+            *counter /= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DEFAULT_SKETCH_DEPTH, DEFAULT_SKETCH_WIDTH};
+
+    #[test]
+    fn test_record_counts_occurrences() {
+        let mut sketch = RecurrenceSketch::new(DEFAULT_SKETCH_WIDTH, DEFAULT_SKETCH_DEPTH);
+        assert_eq!(sketch.estimate("wallet"), 0);
+
+        for expected in 1..=5 {
+            assert_eq!(sketch.record("wallet"), expected);
+        }
+        assert_eq!(sketch.record("other"), 1);
+        assert_eq!(sketch.estimate("wallet"), 5);
+    }
+
+    #[test]
+    fn test_estimates_never_undercount() {
+        // A tiny sketch forces collisions
+        let mut sketch = RecurrenceSketch::new(4, 2);
+        let targets: Vec<String> = (0..20).map(|i| format!("target-{}", i)).collect();
+        for (i, target) in targets.iter().enumerate() {
+            for _ in 0..=i {
+                sketch.record(target);
+            }
+        }
+
+        for (i, target) in targets.iter().enumerate() {
+            assert!(sketch.estimate(target) as usize > i);
+        }
+    }
+
+    #[test]
+    fn test_decay_halves_counts() {
+        let mut sketch = RecurrenceSketch::new(DEFAULT_SKETCH_WIDTH, DEFAULT_SKETCH_DEPTH);
+        for _ in 0..6 {
+            sketch.record("wallet");
+        }
+
+        sketch.decay();
+        assert_eq!(sketch.estimate("wallet"), 3);
+    }
+
+    #[test]
+    fn test_salt_differs_per_sketch() {
+        let a = RecurrenceSketch::new(DEFAULT_SKETCH_WIDTH, 1);
+        let b = RecurrenceSketch::new(DEFAULT_SKETCH_WIDTH, 1);
+        assert_ne!(a.salt, b.salt);
+    }
+}
@@ -0,0 +1,47 @@
+//! Circuit breaker state enum
+
+use serde::{Deserialize, Serialize};
+
+/// State of an upstream's circuit breaker
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CircuitState {
+    /// Calls flow normally
+    #[default]
+    Closed,
+    /// The upstream is skipped until its cooldown ends
+    Open,
+    /// Cooldown over: a single probe call decides whether to close again
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_state_serialization() {
+        assert_eq!(
+            serde_json::to_string(&CircuitState::HalfOpen).unwrap(),
+            "\"half-open\""
+        );
+        assert_eq!(CircuitState::default(), CircuitState::Closed);
+    }
+}
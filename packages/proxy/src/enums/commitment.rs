@@ -29,6 +29,7 @@ impl CommitmentLevel {
     }
 
     /// Parse from string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<CommitmentLevel> {
         match s {
             "processed" => Some(CommitmentLevel::Processed),
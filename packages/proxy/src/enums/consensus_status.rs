@@ -0,0 +1,47 @@
+//! Result consensus status enum
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of checking a result against several upstream providers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConsensusStatus {
+    /// Every provider that answered returned the same result
+    Agreed,
+    /// Providers returned different results
+    Disagreed,
+    /// Fewer than two providers answered, so nothing could be compared
+    Unverified,
+}
+
+impl ConsensusStatus {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsensusStatus::Agreed => "agreed",
+            ConsensusStatus::Disagreed => "disagreed",
+            ConsensusStatus::Unverified => "unverified",
+        }
+    }
+}
+
+impl std::fmt::Display for ConsensusStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consensus_status_serialization() {
+        assert_eq!(
+            serde_json::to_string(&ConsensusStatus::Disagreed).unwrap(),
+            "\"disagreed\""
+        );
+        let parsed: ConsensusStatus = serde_json::from_str("\"unverified\"").unwrap();
+        assert_eq!(parsed, ConsensusStatus::Unverified);
+    }
+}
@@ -0,0 +1,62 @@
+//! Mixer deadline policy enum
+
+use serde::{Deserialize, Serialize};
+
+/// What the query mixer does when its deadline passes before k clients joined
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DeadlinePolicy {
+    /// Execute anyway, padding the batch with a decoy for every missing client
+    Pad,
+    /// Keep the pool open for an extension period, then reject
+    Wait,
+    /// Fail every pooled query
+    #[default]
+    Reject,
+}
+
+impl DeadlinePolicy {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadlinePolicy::Pad => "pad",
+            DeadlinePolicy::Wait => "wait",
+            DeadlinePolicy::Reject => "reject",
+        }
+    }
+
+    /// Parse from string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<DeadlinePolicy> {
+        match s {
+            "pad" => Some(DeadlinePolicy::Pad),
+            "wait" => Some(DeadlinePolicy::Wait),
+            "reject" => Some(DeadlinePolicy::Reject),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DeadlinePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_policy_round_trip() {
+        for policy in [
+            DeadlinePolicy::Pad,
+            DeadlinePolicy::Wait,
+            DeadlinePolicy::Reject,
+        ] {
+            assert_eq!(DeadlinePolicy::from_str(policy.as_str()), Some(policy));
+        }
+        assert_eq!(DeadlinePolicy::from_str("drop"), None);
+        assert_eq!(DeadlinePolicy::default(), DeadlinePolicy::Reject);
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Lifetime class of a proxy encryption key
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyKind {
+    /// Generated once and never expires
+    LongTerm,
+    /// Replaced every rotation interval, valid for a bounded window
+    Rotating,
+}
+
+impl std::fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyKind::LongTerm => write!(f, "longTerm"),
+            KeyKind::Rotating => write!(f, "rotating"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_kind_serialization() {
+        let json = serde_json::to_string(&KeyKind::LongTerm).unwrap();
+        assert_eq!(json, "\"longTerm\"");
+
+        let parsed: KeyKind = serde_json::from_str("\"rotating\"").unwrap();
+        assert_eq!(parsed, KeyKind::Rotating);
+        assert_eq!(parsed.to_string(), "rotating");
+    }
+}
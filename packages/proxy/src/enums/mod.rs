@@ -2,8 +2,28 @@
 
 mod rpc_method;
 mod batch_status;
+mod circuit_state;
 mod commitment;
+mod consensus_status;
+mod deadline_policy;
+mod key_kind;
+mod query_error_code;
+mod quorum_policy;
+mod result_delivery;
+mod sampling_distribution;
+mod shard_policy;
+mod verification_mode;
 
 pub use rpc_method::RpcMethod;
 pub use batch_status::BatchStatus;
+pub use circuit_state::CircuitState;
 pub use commitment::{CommitmentLevel, DEFAULT_COMMITMENT};
+pub use consensus_status::ConsensusStatus;
+pub use deadline_policy::DeadlinePolicy;
+pub use key_kind::KeyKind;
+pub use query_error_code::QueryErrorCode;
+pub use quorum_policy::QuorumPolicy;
+pub use result_delivery::ResultDelivery;
+pub use sampling_distribution::SamplingDistribution;
+pub use shard_policy::ShardPolicy;
+pub use verification_mode::VerificationMode;
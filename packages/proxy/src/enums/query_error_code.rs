@@ -0,0 +1,54 @@
+//! Query error code enum
+
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable reason a query failed, next to its error message
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueryErrorCode {
+    /// The query missed its own timeout or the batch deadline
+    Timeout,
+    /// Every upstream tried throttled the proxy (HTTP 429)
+    RateLimited,
+    /// No upstream tried could be reached or answer
+    Unavailable,
+    /// The upstream rejected the query; retrying would not help
+    Invalid,
+}
+
+impl QueryErrorCode {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryErrorCode::Timeout => "timeout",
+            QueryErrorCode::RateLimited => "rate-limited",
+            QueryErrorCode::Unavailable => "unavailable",
+            QueryErrorCode::Invalid => "invalid",
+        }
+    }
+}
+
+impl std::fmt::Display for QueryErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_error_code_serialization() {
+        assert_eq!(
+            serde_json::to_string(&QueryErrorCode::Timeout).unwrap(),
+            "\"timeout\""
+        );
+        let parsed: QueryErrorCode = serde_json::from_str("\"timeout\"").unwrap();
+        assert_eq!(parsed, QueryErrorCode::Timeout);
+        assert_eq!(
+            serde_json::to_string(&QueryErrorCode::RateLimited).unwrap(),
+            format!("\"{}\"", QueryErrorCode::RateLimited)
+        );
+    }
+}
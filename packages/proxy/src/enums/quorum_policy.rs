@@ -0,0 +1,64 @@
+//! Result consensus quorum policy enum
+
+use serde::{Deserialize, Serialize};
+
+/// Which result is returned when providers are compared
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuorumPolicy {
+    /// The result of the first provider, flagged if the others disagree
+    Primary,
+    /// The result a strict majority of providers returned; without one the
+    /// query fails
+    #[default]
+    Majority,
+    /// The result only if every provider returned it; otherwise the query
+    /// fails
+    Unanimous,
+}
+
+impl QuorumPolicy {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuorumPolicy::Primary => "primary",
+            QuorumPolicy::Majority => "majority",
+            QuorumPolicy::Unanimous => "unanimous",
+        }
+    }
+
+    /// Parse from string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<QuorumPolicy> {
+        match s {
+            "primary" => Some(QuorumPolicy::Primary),
+            "majority" => Some(QuorumPolicy::Majority),
+            "unanimous" => Some(QuorumPolicy::Unanimous),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for QuorumPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quorum_policy_round_trip() {
+        for policy in [
+            QuorumPolicy::Primary,
+            QuorumPolicy::Majority,
+            QuorumPolicy::Unanimous,
+        ] {
+            assert_eq!(QuorumPolicy::from_str(policy.as_str()), Some(policy));
+        }
+        assert_eq!(QuorumPolicy::from_str("all"), None);
+        assert_eq!(QuorumPolicy::default(), QuorumPolicy::Majority);
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// How query results of a batch are delivered to their submitters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultDelivery {
+    /// Results are returned in the `/execute-batch` response
+    Inline,
+    /// Results are withheld and served one at a time via `/results/{ticket}`
+    Ticket,
+}
+
+impl std::fmt::Display for ResultDelivery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultDelivery::Inline => write!(f, "inline"),
+            ResultDelivery::Ticket => write!(f, "ticket"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_delivery_serialization() {
+        let json = serde_json::to_string(&ResultDelivery::Ticket).unwrap();
+        assert_eq!(json, "\"ticket\"");
+
+        let parsed: ResultDelivery = serde_json::from_str("\"inline\"").unwrap();
+        assert_eq!(parsed, ResultDelivery::Inline);
+        assert_eq!(parsed.to_string(), "inline");
+    }
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Supported RPC methods for privacy batching
+#[allow(clippy::enum_variant_names)] // Variants mirror the Solana RPC method names
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum RpcMethod {
@@ -46,6 +47,7 @@ impl RpcMethod {
     }
 
     /// Check if a string is a valid RPC method name
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<RpcMethod> {
         match s {
             "getBalance" => Some(RpcMethod::GetBalance),
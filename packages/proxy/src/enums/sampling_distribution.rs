@@ -0,0 +1,68 @@
+//! Decoy sampling distribution enum
+
+use serde::{Deserialize, Serialize};
+
+/// How decoy targets are drawn from the decoy corpus
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingDistribution {
+    /// Every corpus entry is equally likely
+    Uniform,
+    /// Entries are drawn proportionally to their corpus weight
+    #[default]
+    Weighted,
+    /// Entries are drawn by rank (corpus order) following a Zipf law
+    Zipf,
+}
+
+impl SamplingDistribution {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SamplingDistribution::Uniform => "uniform",
+            SamplingDistribution::Weighted => "weighted",
+            SamplingDistribution::Zipf => "zipf",
+        }
+    }
+
+    /// Parse from string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<SamplingDistribution> {
+        match s {
+            "uniform" => Some(SamplingDistribution::Uniform),
+            "weighted" => Some(SamplingDistribution::Weighted),
+            "zipf" => Some(SamplingDistribution::Zipf),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SamplingDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_distribution_round_trip() {
+        for distribution in [
+            SamplingDistribution::Uniform,
+            SamplingDistribution::Weighted,
+            SamplingDistribution::Zipf,
+        ] {
+            assert_eq!(
+                SamplingDistribution::from_str(distribution.as_str()),
+                Some(distribution)
+            );
+        }
+        assert_eq!(SamplingDistribution::from_str("normal"), None);
+        assert_eq!(
+            SamplingDistribution::default(),
+            SamplingDistribution::Weighted
+        );
+    }
+}
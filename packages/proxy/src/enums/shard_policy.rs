@@ -0,0 +1,67 @@
+//! Upstream shard policy enum
+
+use serde::{Deserialize, Serialize};
+
+/// How the calls of a batch are split across upstream providers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShardPolicy {
+    /// Each call goes to a random provider
+    Random,
+    /// Calls cycle through the providers
+    RoundRobin,
+    /// Each target always goes to the same provider, so no other provider
+    /// learns it is being watched
+    #[default]
+    Keyed,
+}
+
+impl ShardPolicy {
+    /// Convert to string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShardPolicy::Random => "random",
+            ShardPolicy::RoundRobin => "round-robin",
+            ShardPolicy::Keyed => "keyed",
+        }
+    }
+
+    /// Parse from string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<ShardPolicy> {
+        match s {
+            "random" => Some(ShardPolicy::Random),
+            "round-robin" => Some(ShardPolicy::RoundRobin),
+            "keyed" => Some(ShardPolicy::Keyed),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ShardPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_policy_round_trip() {
+        for policy in [
+            ShardPolicy::Random,
+            ShardPolicy::RoundRobin,
+            ShardPolicy::Keyed,
+        ] {
+            assert_eq!(ShardPolicy::from_str(policy.as_str()), Some(policy));
+        }
+        assert_eq!(ShardPolicy::from_str("roundrobin"), None);
+        assert_eq!(ShardPolicy::default(), ShardPolicy::Keyed);
+        assert_eq!(
+            serde_json::to_string(&ShardPolicy::RoundRobin).unwrap(),
+            "\"round-robin\""
+        );
+    }
+}
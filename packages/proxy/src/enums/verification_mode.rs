@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// How a batch was checked against the on-chain coordinator before execution
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationMode {
+    /// Batch was verified as finalized on-chain with matching query hashes
+    Onchain,
+    /// Batch referenced an on-chain batch, but no coordinator was available
+    Unverified,
+    /// Batch did not reference an on-chain batch
+    Uncoordinated,
+}
+
+impl VerificationMode {
+    /// Check if the batch was verified against the coordinator
+    pub fn is_verified(&self) -> bool {
+        matches!(self, VerificationMode::Onchain)
+    }
+}
+
+impl std::fmt::Display for VerificationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationMode::Onchain => write!(f, "onchain"),
+            VerificationMode::Unverified => write!(f, "unverified"),
+            VerificationMode::Uncoordinated => write!(f, "uncoordinated"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verification_mode_is_verified() {
+        assert!(VerificationMode::Onchain.is_verified());
+        assert!(!VerificationMode::Unverified.is_verified());
+        assert!(!VerificationMode::Uncoordinated.is_verified());
+    }
+
+    #[test]
+    fn test_verification_mode_serialization() {
+        let json = serde_json::to_string(&VerificationMode::Onchain).unwrap();
+        assert_eq!(json, "\"onchain\"");
+
+        let parsed: VerificationMode = serde_json::from_str("\"uncoordinated\"").unwrap();
+        assert_eq!(parsed, VerificationMode::Uncoordinated);
+        assert_eq!(parsed.to_string(), "uncoordinated");
+    }
+}
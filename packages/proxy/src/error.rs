@@ -16,8 +16,10 @@ use crate::types::MAX_BATCH_SIZE;
 #[derive(Debug, Error)]
 pub enum ProxyError {
     /// Error communicating with Solana RPC
+    ///
+    /// Boxed because `ClientError` is large and would bloat every `ProxyResult`.
     #[error("Solana RPC error: {0}")]
-    SolanaRpc(#[from] solana_client::client_error::ClientError),
+    SolanaRpc(Box<solana_client::client_error::ClientError>),
 
     /// Invalid query parameters
     #[error("Invalid query: {0}")]
@@ -35,10 +37,50 @@ pub enum ProxyError {
     #[error("Batch cannot be empty")]
     EmptyBatch,
 
+    /// Batch anonymity set below the k-anonymity threshold
+    #[error("Batch anonymity set of {actual} is below the minimum of {min}")]
+    BatchTooSmall { actual: usize, min: usize },
+
+    /// Mixer deadline passed before enough clients joined the batch
+    #[error("Only {actual} of {min} required clients joined the batch before the deadline")]
+    AnonymitySetNotReached { actual: usize, min: usize },
+
+    /// Query mixer pool is at capacity
+    #[error("Query pool is full ({0} queries)")]
+    MixerFull(usize),
+
+    /// Cover traffic queue cannot hold the batch
+    #[error("Cover traffic queue is full ({0} calls waiting)")]
+    CoverQueueFull(usize),
+
+    /// On-chain batch was already executed or is executing
+    #[error("Batch {batch_id} cannot be executed again: {reason}")]
+    BatchReplay { batch_id: u64, reason: String },
+
+    /// Result ticket is unknown, expired or already used
+    #[error("Result not found")]
+    ResultNotFound,
+
+    /// Strict coordination requires a verified on-chain batch
+    #[error("On-chain coordination required: {0}")]
+    CoordinationRequired(String),
+
+    /// Optional feature is not enabled on this proxy
+    #[error("Not configured: {0}")]
+    NotConfigured(String),
+
+    /// Result encryption or decryption failure
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+
     /// Internal server error
     #[error("Internal error: {0}")]
     Internal(String),
 
+    /// Submitted queries do not match the hashes committed on-chain
+    #[error("Queries do not match on-chain batch {batch_id}: {reason}")]
+    QueryHashMismatch { batch_id: u64, reason: String },
+
     /// Query execution timeout
     #[error("Query execution timed out after {0}ms")]
     Timeout(u64),
@@ -54,6 +96,12 @@ impl ProxyError {
     }
 }
 
+impl From<solana_client::client_error::ClientError> for ProxyError {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        Self::SolanaRpc(Box::new(err))
+    }
+}
+
 impl IntoResponse for ProxyError {
     fn into_response(self) -> Response {
         let (status, error_message) = match &self {
@@ -65,10 +113,39 @@ impl IntoResponse for ProxyError {
                 tracing::warn!(error = %self, "Invalid request");
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
-            ProxyError::BatchTooLarge { .. } | ProxyError::EmptyBatch => {
+            ProxyError::BatchTooLarge { .. }
+            | ProxyError::BatchTooSmall { .. }
+            | ProxyError::EmptyBatch => {
                 tracing::warn!(error = %self, "Invalid batch");
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
+            ProxyError::AnonymitySetNotReached { .. } | ProxyError::MixerFull(_) => {
+                tracing::warn!(error = %self, "Mixed batch rejected");
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+            }
+            ProxyError::CoverQueueFull(_) => {
+                tracing::warn!(error = %self, "Batch rejected by cover traffic");
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+            }
+            ProxyError::QueryHashMismatch { .. } => {
+                tracing::warn!(error = %self, "Query hash verification failed");
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
+            ProxyError::BatchReplay { .. } => {
+                tracing::warn!(error = %self, "Batch replay rejected");
+                (StatusCode::CONFLICT, self.to_string())
+            }
+            ProxyError::ResultNotFound | ProxyError::NotConfigured(_) => {
+                (StatusCode::NOT_FOUND, self.to_string())
+            }
+            ProxyError::CoordinationRequired(_) => {
+                tracing::warn!(error = %self, "Uncoordinated batch rejected");
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
+            ProxyError::Crypto(msg) => {
+                tracing::error!(error = %msg, "Encryption error");
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
+            }
             ProxyError::Internal(msg) => {
                 tracing::error!(error = %msg, "Internal error");
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
@@ -119,4 +196,21 @@ mod tests {
         let err = ProxyError::InvalidQuery("missing field".to_string());
         assert_eq!(err.to_string(), "Invalid query: missing field");
     }
+
+    #[test]
+    fn test_not_configured_error() {
+        let err = ProxyError::NotConfigured("encryption keys".to_string());
+        assert_eq!(err.to_string(), "Not configured: encryption keys");
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_query_hash_mismatch_error() {
+        let err = ProxyError::QueryHashMismatch {
+            batch_id: 42,
+            reason: "expected 3 queries, got 4".to_string(),
+        };
+        assert!(err.to_string().contains("batch 42"));
+        assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+    }
 }
@@ -0,0 +1,158 @@
+//! Classification of RPC client errors
+//!
+//! Decides what a failed call becomes: a fault the upstream pool retries
+//! (possibly on another upstream), a `null` result for an object that does
+//! not exist, or a failure returned as it is.
+
+use crate::enums::QueryErrorCode;
+use crate::types::QueryResult;
+use crate::upstream::UpstreamFault;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_request::RpcError;
+
+/// JSON-RPC error codes of an upstream that cannot answer yet
+const RETRYABLE_RPC_CODES: &[i64] = &[
+    -32603, // Internal error
+    -32004, // Block not available for slot
+    -32005, // Node is unhealthy
+    -32014, // Block status not yet available
+    -32016, // Minimum context slot not reached
+];
+
+/// JSON-RPC error code of a method the upstream does not serve
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// What a failed call says about the query and the upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Transport error, HTTP 429 or 5xx: worth another attempt
+    Retryable(UpstreamFault),
+    /// The account or transaction does not exist: answered with `null`
+    NotFound,
+    /// The upstream rejected the query (invalid params and the like)
+    Invalid,
+}
+
+impl ErrorClass {
+    /// Classify an error of the RPC client
+    pub fn of(error: &ClientError) -> Self {
+        match error.kind() {
+            ClientErrorKind::Io(_) => ErrorClass::Retryable(UpstreamFault::Unavailable),
+            ClientErrorKind::Reqwest(e) => match e.status().map(|status| status.as_u16()) {
+                Some(429) => ErrorClass::Retryable(UpstreamFault::RateLimited),
+                Some(500..=599) => ErrorClass::Retryable(UpstreamFault::Unavailable),
+                Some(_) => ErrorClass::Invalid,
+                // No status: the request never got an answer
+                None => ErrorClass::Retryable(UpstreamFault::Unavailable),
+            },
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+                ErrorClass::of_rpc_error(*code, message)
+            }
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => {
+                ErrorClass::Retryable(UpstreamFault::Unavailable)
+            }
+            ClientErrorKind::RpcError(RpcError::ForUser(message))
+                if message.starts_with("AccountNotFound") =>
+            {
+                ErrorClass::NotFound
+            }
+            // A `null` result where the client expected an object
+            ClientErrorKind::SerdeJson(e) if e.to_string().starts_with("invalid type: null") => {
+                ErrorClass::NotFound
+            }
+            _ => ErrorClass::Invalid,
+        }
+    }
+
+    /// Classify a JSON-RPC error object
+    pub fn of_rpc_error(code: i64, message: &str) -> Self {
+        let message = message.to_lowercase();
+        if code == METHOD_NOT_FOUND {
+            ErrorClass::Invalid
+        } else if message.contains("not found") || message.contains("could not find") {
+            ErrorClass::NotFound
+        } else if RETRYABLE_RPC_CODES.contains(&code) {
+            ErrorClass::Retryable(UpstreamFault::Unavailable)
+        } else {
+            ErrorClass::Invalid
+        }
+    }
+
+    /// Result of query `id` whose call failed with `error`
+    pub fn result(self, id: String, error: String) -> QueryResult {
+        match self {
+            ErrorClass::NotFound => QueryResult::success(id, serde_json::Value::Null),
+            ErrorClass::Retryable(fault) => {
+                QueryResult::failure(id, error).with_error_code(fault.code())
+            }
+            ErrorClass::Invalid => {
+                QueryResult::failure(id, error).with_error_code(QueryErrorCode::Invalid)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_request::RpcResponseErrorData;
+
+    fn rpc_error(code: i64, message: &str) -> ClientError {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code,
+            message: message.to_string(),
+            data: RpcResponseErrorData::Empty,
+        })
+        .into()
+    }
+
+    #[test]
+    fn test_classifies_client_errors() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            ErrorClass::of(&ClientErrorKind::Io(refused).into()),
+            ErrorClass::Retryable(UpstreamFault::Unavailable)
+        );
+        assert_eq!(
+            ErrorClass::of(&rpc_error(-32005, "Node is unhealthy")),
+            ErrorClass::Retryable(UpstreamFault::Unavailable)
+        );
+        assert_eq!(
+            ErrorClass::of(&rpc_error(-32602, "Invalid param: could not find account")),
+            ErrorClass::NotFound
+        );
+        assert_eq!(
+            ErrorClass::of(&rpc_error(-32602, "Invalid param: WrongSize")),
+            ErrorClass::Invalid
+        );
+        assert_eq!(
+            ErrorClass::of(&rpc_error(-32601, "Method not found")),
+            ErrorClass::Invalid
+        );
+        let missing = RpcError::ForUser("AccountNotFound: pubkey=abc".to_string());
+        assert_eq!(
+            ErrorClass::of(&ClientErrorKind::RpcError(missing).into()),
+            ErrorClass::NotFound
+        );
+        let null = serde_json::from_value::<Vec<u8>>(serde_json::Value::Null).unwrap_err();
+        assert_eq!(
+            ErrorClass::of(&ClientErrorKind::SerdeJson(null).into()),
+            ErrorClass::NotFound
+        );
+    }
+
+    #[test]
+    fn test_class_results() {
+        let missing = ErrorClass::NotFound.result("a".to_string(), "not found".to_string());
+        assert!(missing.success);
+        assert_eq!(missing.data, Some(serde_json::Value::Null));
+
+        let throttled = ErrorClass::Retryable(UpstreamFault::RateLimited)
+            .result("b".to_string(), "429".to_string());
+        assert!(!throttled.success);
+        assert_eq!(throttled.error_code, Some(QueryErrorCode::RateLimited));
+
+        let invalid = ErrorClass::Invalid.result("c".to_string(), "bad".to_string());
+        assert_eq!(invalid.error_code, Some(QueryErrorCode::Invalid));
+    }
+}
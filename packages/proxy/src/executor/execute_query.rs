@@ -6,7 +6,7 @@ use crate::executor::{
     execute_get_multiple_accounts, execute_get_token_account_balance, execute_get_transaction,
 };
 use crate::types::{Query, QueryResult};
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -14,8 +14,9 @@ use tracing::debug;
 
 /// Execute a single query against the RPC
 ///
-/// This function is designed to be run in a Tokio blocking task for parallel execution.
-pub fn execute_single_query(client: Arc<RpcClient>, query: Query) -> QueryResult {
+/// Calls go through the nonblocking client, so many queries can run
+/// concurrently on the Tokio runtime without holding a thread each.
+pub async fn execute_single_query(client: Arc<RpcClient>, query: Query) -> QueryResult {
     let query_id = query.id.clone();
 
     debug!(
@@ -43,7 +44,7 @@ pub fn execute_single_query(client: Arc<RpcClient>, query: Query) -> QueryResult
                     );
                 }
             };
-            execute_get_balance(&client, &query_id, &pubkey)
+            execute_get_balance(&client, &query_id, &pubkey, query.effective_commitment()).await
         }
         RpcMethod::GetAccountInfo => {
             // Legacy path - parse pubkey
@@ -62,16 +63,39 @@ pub fn execute_single_query(client: Arc<RpcClient>, query: Query) -> QueryResult
                     );
                 }
             };
-            execute_get_account_info(&client, &query_id, &pubkey)
+            execute_get_account_info(&client, &query_id, &pubkey, query.effective_commitment())
+                .await
         }
-        RpcMethod::GetTransaction => execute_get_transaction(client, query),
-        RpcMethod::GetTokenAccountBalance => execute_get_token_account_balance(client, query),
-        RpcMethod::GetBlockHeight => execute_get_block_height(client, query),
-        RpcMethod::GetMultipleAccounts => execute_get_multiple_accounts(client, query),
+        RpcMethod::GetTransaction => execute_get_transaction(client, query).await,
+        RpcMethod::GetTokenAccountBalance => execute_get_token_account_balance(client, query).await,
+        RpcMethod::GetBlockHeight => execute_get_block_height(client, query).await,
+        RpcMethod::GetMultipleAccounts => execute_get_multiple_accounts(client, query).await,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // Tests require a running RPC endpoint
+    use super::*;
+    use crate::upstream::MockUpstream;
+
+    const PUBKEY: &str = "So11111111111111111111111111111111111111112";
+
+    #[tokio::test]
+    async fn test_account_queries_run_at_the_hashed_commitment() {
+        let upstream = MockUpstream::start().await;
+        let client = Arc::new(RpcClient::new(upstream.url()));
+
+        let balance = Query::new("b".to_string(), RpcMethod::GetBalance, PUBKEY.to_string());
+        assert!(execute_single_query(Arc::clone(&client), balance).await.success);
+        let account = Query::new("a".to_string(), RpcMethod::GetAccountInfo, PUBKEY.to_string())
+            .with_commitment("processed".to_string());
+        assert!(execute_single_query(client, account).await.success);
+
+        let commitments: Vec<String> = upstream
+            .calls()
+            .iter()
+            .map(|call| call.params[1]["commitment"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(commitments, ["confirmed", "processed"]);
+    }
 }
@@ -1,18 +1,25 @@
 //! GetAccountInfo RPC method executor
 
+use super::ErrorClass;
 use crate::types::QueryResult;
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use tracing::{debug, warn};
 
 /// Execute getAccountInfo RPC method
-pub fn execute_get_account_info(
+pub async fn execute_get_account_info(
     client: &RpcClient,
     query_id: &str,
     pubkey: &Pubkey,
+    commitment: CommitmentConfig,
 ) -> QueryResult {
-    match client.get_account(pubkey) {
-        Ok(account) => {
+    match client.get_account_with_commitment(pubkey, commitment).await {
+        Ok(response) => {
+            let Some(account) = response.value else {
+                debug!(query_id = %query_id, "Account not found");
+                return QueryResult::success(query_id.to_string(), serde_json::Value::Null);
+            };
             debug!(
                 query_id = %query_id,
                 lamports = account.lamports,
@@ -32,14 +39,13 @@ pub fn execute_get_account_info(
         }
         Err(e) => {
             // Check if it's just an account not found error
-            let error_str = e.to_string();
-            if error_str.contains("AccountNotFound") {
+            let class = ErrorClass::of(&e);
+            if class == ErrorClass::NotFound {
                 debug!(query_id = %query_id, "Account not found");
-                QueryResult::success(query_id.to_string(), serde_json::json!(null))
             } else {
                 warn!(query_id = %query_id, error = %e, "getAccountInfo failed");
-                QueryResult::failure(query_id.to_string(), error_str)
             }
+            class.result(query_id.to_string(), e.to_string())
         }
     }
 }
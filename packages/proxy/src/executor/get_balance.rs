@@ -1,20 +1,28 @@
 //! GetBalance RPC method executor
 
+use super::ErrorClass;
 use crate::types::QueryResult;
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use tracing::{debug, warn};
 
 /// Execute getBalance RPC method
-pub fn execute_get_balance(client: &RpcClient, query_id: &str, pubkey: &Pubkey) -> QueryResult {
-    match client.get_balance(pubkey) {
-        Ok(balance) => {
+pub async fn execute_get_balance(
+    client: &RpcClient,
+    query_id: &str,
+    pubkey: &Pubkey,
+    commitment: CommitmentConfig,
+) -> QueryResult {
+    match client.get_balance_with_commitment(pubkey, commitment).await {
+        Ok(response) => {
+            let balance = response.value;
             debug!(query_id = %query_id, balance = balance, "getBalance succeeded");
             QueryResult::success(query_id.to_string(), serde_json::json!({ "lamports": balance }))
         }
         Err(e) => {
             warn!(query_id = %query_id, error = %e, "getBalance failed");
-            QueryResult::failure(query_id.to_string(), e.to_string())
+            ErrorClass::of(&e).result(query_id.to_string(), e.to_string())
         }
     }
 }
@@ -2,8 +2,9 @@
 //!
 //! Fetches the current block height of the cluster.
 
+use super::ErrorClass;
 use crate::types::{Query, QueryResult};
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -17,7 +18,7 @@ use tracing::warn;
 ///
 /// # Returns
 /// QueryResult with block height or error
-pub fn execute_get_block_height(client: Arc<RpcClient>, query: Query) -> QueryResult {
+pub async fn execute_get_block_height(client: Arc<RpcClient>, query: Query) -> QueryResult {
     // Parse commitment
     let commitment = query
         .commitment
@@ -26,11 +27,11 @@ pub fn execute_get_block_height(client: Arc<RpcClient>, query: Query) -> QueryRe
         .unwrap_or(CommitmentConfig::confirmed());
 
     // Execute RPC call
-    match client.get_block_height_with_commitment(commitment) {
+    match client.get_block_height_with_commitment(commitment).await {
         Ok(height) => QueryResult::success(query.id, serde_json::json!(height)),
         Err(e) => {
             warn!(error = %e, "Failed to get block height");
-            QueryResult::failure(query.id, format!("RPC error: {}", e))
+            ErrorClass::of(&e).result(query.id, format!("RPC error: {}", e))
         }
     }
 }
@@ -40,8 +41,8 @@ mod tests {
     use super::*;
     use crate::enums::RpcMethod;
 
-    #[test]
-    fn test_get_block_height_no_params_needed() {
+    #[tokio::test]
+    async fn test_get_block_height_no_params_needed() {
         // This test would require a running validator
         // Just verify the function signature is correct
         let client = Arc::new(RpcClient::new("http://localhost:8899".to_string()));
@@ -51,7 +52,7 @@ mod tests {
             serde_json::json!(null),
         );
 
-        let _result = execute_get_block_height(client, query);
+        let _result = execute_get_block_height(client, query).await;
         // Can't assert success without running validator
     }
 }
@@ -1,14 +1,38 @@
 //! Get multiple accounts executor
 //!
-//! Fetches multiple account data in a single RPC call (efficient batching).
+//! Fetches multiple account data in a single RPC call (efficient batching),
+//! or in concurrent chunks when there are more accounts than the upstream
+//! accepts at once.
 
+use super::ErrorClass;
 use crate::types::{Query, QueryResult};
-use solana_client::rpc_client::RpcClient;
+use crate::upstream::UpstreamFault;
+use futures::future::try_join_all;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 use std::sync::Arc;
-use tracing::warn;
+use tracing::{debug, warn};
+
+/// Most accounts the RPC accepts in a single getMultipleAccounts call
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Rounds of chunk fetches before chunks at different slots fail the query
+const CHUNK_SLOT_ATTEMPTS: usize = 3;
+
+/// Number of accounts a getMultipleAccounts query asks for
+pub fn requested_accounts(query: &Query) -> usize {
+    match &query.params {
+        Some(serde_json::Value::Array(items)) => items.len(),
+        Some(serde_json::Value::String(_)) => 1,
+        _ => 0,
+    }
+}
 
 /// Execute getMultipleAccounts RPC call
 ///
@@ -18,7 +42,7 @@ use tracing::warn;
 ///
 /// # Returns
 /// QueryResult with array of account info or error
-pub fn execute_get_multiple_accounts(client: Arc<RpcClient>, query: Query) -> QueryResult {
+pub async fn execute_get_multiple_accounts(client: Arc<RpcClient>, query: Query) -> QueryResult {
     // Extract pubkeys from params
     let pubkeys_str: Vec<String> = match &query.params {
         Some(params) => {
@@ -67,11 +91,10 @@ pub fn execute_get_multiple_accounts(client: Arc<RpcClient>, query: Query) -> Qu
         .unwrap_or(CommitmentConfig::confirmed());
 
     // Execute RPC call
-    match client.get_multiple_accounts_with_commitment(&pubkeys, commitment) {
-        Ok(response) => {
+    match fetch_accounts(&client, &pubkeys, commitment).await {
+        Ok(accounts) => {
             // Convert accounts to JSON format
-            let accounts_json: Vec<serde_json::Value> = response
-                .value
+            let accounts_json: Vec<serde_json::Value> = accounts
                 .into_iter()
                 .map(|opt_account| match opt_account {
                     Some(account) => serde_json::json!({
@@ -87,24 +110,88 @@ pub fn execute_get_multiple_accounts(client: Arc<RpcClient>, query: Query) -> Qu
 
             QueryResult::success(query.id, serde_json::json!(accounts_json))
         }
-        Err(e) => {
+        Err((class, e)) => {
             warn!(
                 pubkeys_count = pubkeys.len(),
                 error = %e,
                 "Failed to get multiple accounts"
             );
-            QueryResult::failure(query.id, format!("RPC error: {}", e))
+            class.result(query.id, format!("RPC error: {}", e))
         }
     }
 }
 
+/// Fetch accounts in as many calls as the upstream requires
+///
+/// Chunks are fetched concurrently and must all come from the same slot, so
+/// the accounts form one consistent snapshot. Chunks at different slots are
+/// fetched again, no older than the newest of them, up to
+/// `CHUNK_SLOT_ATTEMPTS` times; chunks that never agree count as an
+/// unavailable upstream.
+async fn fetch_accounts(
+    client: &RpcClient,
+    pubkeys: &[Pubkey],
+    commitment: CommitmentConfig,
+) -> Result<Vec<Option<Account>>, (ErrorClass, String)> {
+    let classify = |e: ClientError| (ErrorClass::of(&e), e.to_string());
+
+    if pubkeys.len() <= MAX_MULTIPLE_ACCOUNTS {
+        return client
+            .get_multiple_accounts_with_commitment(pubkeys, commitment)
+            .await
+            .map(|response| response.value)
+            .map_err(classify);
+    }
+
+    let mut min_context_slot = None;
+    let mut spread = (0, 0);
+    for _ in 0..CHUNK_SLOT_ATTEMPTS {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            data_slice: None,
+            commitment: Some(commitment),
+            min_context_slot,
+        };
+        let chunks = pubkeys
+            .chunks(MAX_MULTIPLE_ACCOUNTS)
+            .map(|chunk| client.get_multiple_accounts_with_config(chunk, config.clone()));
+        let responses = try_join_all(chunks).await.map_err(classify)?;
+
+        let slots = responses.iter().map(|response| response.context.slot);
+        let lowest = slots.clone().min().unwrap_or(0);
+        let highest = slots.max().unwrap_or(0);
+        if lowest == highest {
+            return Ok(responses
+                .into_iter()
+                .flat_map(|response| response.value)
+                .collect());
+        }
+        debug!(
+            lowest = lowest,
+            highest = highest,
+            "getMultipleAccounts chunks at different slots, fetching again"
+        );
+        min_context_slot = Some(highest);
+        spread = (lowest, highest);
+    }
+
+    Err((
+        ErrorClass::Retryable(UpstreamFault::Unavailable),
+        format!(
+            "getMultipleAccounts chunks came back at different slots ({} to {})",
+            spread.0, spread.1
+        ),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::enums::RpcMethod;
+    use crate::enums::{QueryErrorCode, RpcMethod};
+    use crate::upstream::MockUpstream;
 
-    #[test]
-    fn test_empty_pubkeys() {
+    #[tokio::test]
+    async fn test_empty_pubkeys() {
         let client = Arc::new(RpcClient::new("http://localhost:8899".to_string()));
         let query = Query::with_params(
             "test-1".to_string(),
@@ -112,13 +199,13 @@ mod tests {
             serde_json::json!([]),
         );
 
-        let result = execute_get_multiple_accounts(client, query);
+        let result = execute_get_multiple_accounts(client, query).await;
         assert!(!result.success);
         assert!(result.error.unwrap().contains("Empty pubkeys"));
     }
 
-    #[test]
-    fn test_invalid_pubkey_in_array() {
+    #[tokio::test]
+    async fn test_invalid_pubkey_in_array() {
         let client = Arc::new(RpcClient::new("http://localhost:8899".to_string()));
         let query = Query::with_params(
             "test-1".to_string(),
@@ -126,8 +213,72 @@ mod tests {
             serde_json::json!(["invalid-pubkey", "also-invalid"]),
         );
 
-        let result = execute_get_multiple_accounts(client, query);
+        let result = execute_get_multiple_accounts(client, query).await;
         assert!(!result.success);
         assert!(result.error.unwrap().contains("Invalid pubkey"));
     }
+
+    #[test]
+    fn test_requested_accounts() {
+        let query = |params| {
+            Query::with_params("test-1".to_string(), RpcMethod::GetMultipleAccounts, params)
+        };
+        assert_eq!(requested_accounts(&query(serde_json::json!(["a", "b"]))), 2);
+        assert_eq!(requested_accounts(&query(serde_json::json!("a"))), 1);
+        assert_eq!(requested_accounts(&query(serde_json::Value::Null)), 0);
+    }
+
+    fn chunked_query(count: u32) -> Query {
+        let pubkeys: Vec<String> = (0..count)
+            .map(|i| {
+                let mut bytes = [0u8; 32];
+                bytes[..4].copy_from_slice(&i.to_le_bytes());
+                Pubkey::new_from_array(bytes).to_string()
+            })
+            .collect();
+        Query::with_params(
+            "test-1".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            serde_json::json!(pubkeys),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_large_request_is_split_into_chunks() {
+        let upstream = MockUpstream::start().await;
+        let client = Arc::new(RpcClient::new(upstream.url()));
+        let query = chunked_query(250);
+        let requested = query.params.clone().unwrap();
+
+        let result = execute_get_multiple_accounts(client, query).await;
+        assert!(result.success);
+        assert_eq!(result.data.unwrap().as_array().unwrap().len(), 250);
+
+        // Chunks of at most 100 keys that together ask for every account,
+        // in order
+        let calls = upstream.calls();
+        let sizes: Vec<usize> = calls.iter().map(|call| call.targets().len()).collect();
+        assert_eq!(sizes, [100, 100, 50]);
+        let mut targets: Vec<serde_json::Value> = calls
+            .iter()
+            .flat_map(|call| call.targets())
+            .map(serde_json::Value::from)
+            .collect();
+        targets.sort_by_key(|target| target.to_string());
+        let mut requested = requested.as_array().unwrap().clone();
+        requested.sort_by_key(|target| target.to_string());
+        assert_eq!(targets, requested);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_request_reports_rpc_errors() {
+        let upstream = MockUpstream::start().await;
+        upstream.fail_with(503);
+        let client = Arc::new(RpcClient::new(upstream.url()));
+
+        let result = execute_get_multiple_accounts(client, chunked_query(250)).await;
+        assert!(!result.success);
+        assert_eq!(result.error_code, Some(QueryErrorCode::Unavailable));
+        assert!(result.error.unwrap().starts_with("RPC error: "));
+    }
 }
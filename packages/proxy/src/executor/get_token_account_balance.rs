@@ -2,8 +2,9 @@
 //!
 //! Fetches SPL token account balance.
 
+use super::ErrorClass;
 use crate::types::{Query, QueryResult};
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
@@ -18,7 +19,10 @@ use tracing::warn;
 ///
 /// # Returns
 /// QueryResult with token balance or error
-pub fn execute_get_token_account_balance(client: Arc<RpcClient>, query: Query) -> QueryResult {
+pub async fn execute_get_token_account_balance(
+    client: Arc<RpcClient>,
+    query: Query,
+) -> QueryResult {
     // Extract pubkey
     let pubkey_str = match query.get_primary_param() {
         Some(pk) => pk,
@@ -47,7 +51,10 @@ pub fn execute_get_token_account_balance(client: Arc<RpcClient>, query: Query) -
         .unwrap_or(CommitmentConfig::confirmed());
 
     // Execute RPC call
-    match client.get_token_account_balance_with_commitment(&pubkey, commitment) {
+    match client
+        .get_token_account_balance_with_commitment(&pubkey, commitment)
+        .await
+    {
         Ok(balance) => {
             // Convert to JSON
             match serde_json::to_value(&balance.value) {
@@ -60,7 +67,7 @@ pub fn execute_get_token_account_balance(client: Arc<RpcClient>, query: Query) -
         }
         Err(e) => {
             warn!(pubkey = %pubkey_str, error = %e, "Failed to get token account balance");
-            QueryResult::failure(query.id, format!("RPC error: {}", e))
+            ErrorClass::of(&e).result(query.id, format!("RPC error: {}", e))
         }
     }
 }
@@ -70,8 +77,8 @@ mod tests {
     use super::*;
     use crate::enums::RpcMethod;
 
-    #[test]
-    fn test_invalid_pubkey() {
+    #[tokio::test]
+    async fn test_invalid_pubkey() {
         let client = Arc::new(RpcClient::new("http://localhost:8899".to_string()));
         let query = Query::new(
             "test-1".to_string(),
@@ -79,7 +86,7 @@ mod tests {
             "invalid-pubkey".to_string(),
         );
 
-        let result = execute_get_token_account_balance(client, query);
+        let result = execute_get_token_account_balance(client, query).await;
         assert!(!result.success);
         assert!(result.error.unwrap().contains("Invalid pubkey"));
     }
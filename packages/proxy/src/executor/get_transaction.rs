@@ -2,14 +2,15 @@
 //!
 //! Fetches transaction details by signature.
 
+use super::ErrorClass;
 use crate::types::{Query, QueryResult};
-use solana_client::rpc_client::RpcClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::Signature;
 use solana_transaction_status::UiTransactionEncoding;
 use std::str::FromStr;
 use std::sync::Arc;
-use tracing::warn;
+use tracing::{debug, warn};
 
 /// Execute getTransaction RPC call
 ///
@@ -19,7 +20,7 @@ use tracing::warn;
 ///
 /// # Returns
 /// QueryResult with transaction data or error
-pub fn execute_get_transaction(client: Arc<RpcClient>, query: Query) -> QueryResult {
+pub async fn execute_get_transaction(client: Arc<RpcClient>, query: Query) -> QueryResult {
     // Extract signature from query
     let signature_str = match query.get_primary_param() {
         Some(sig) => sig,
@@ -48,14 +49,17 @@ pub fn execute_get_transaction(client: Arc<RpcClient>, query: Query) -> QueryRes
         .unwrap_or(CommitmentConfig::confirmed());
 
     // Execute RPC call
-    match client.get_transaction_with_config(
-        &signature,
-        solana_client::rpc_config::RpcTransactionConfig {
-            encoding: Some(UiTransactionEncoding::Json),
-            commitment: Some(commitment),
-            max_supported_transaction_version: Some(0),
-        },
-    ) {
+    match client
+        .get_transaction_with_config(
+            &signature,
+            solana_client::rpc_config::RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+    {
         Ok(transaction) => {
             // Convert to JSON
             match serde_json::to_value(&transaction) {
@@ -67,8 +71,13 @@ pub fn execute_get_transaction(client: Arc<RpcClient>, query: Query) -> QueryRes
             }
         }
         Err(e) => {
-            warn!(signature = %signature_str, error = %e, "Failed to get transaction");
-            QueryResult::failure(query.id, format!("RPC error: {}", e))
+            let class = ErrorClass::of(&e);
+            if class == ErrorClass::NotFound {
+                debug!(signature = %signature_str, "Transaction not found");
+            } else {
+                warn!(signature = %signature_str, error = %e, "Failed to get transaction");
+            }
+            class.result(query.id, format!("RPC error: {}", e))
         }
     }
 }
@@ -76,9 +85,10 @@ pub fn execute_get_transaction(client: Arc<RpcClient>, query: Query) -> QueryRes
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::enums::RpcMethod;
 
-    #[test]
-    fn test_invalid_signature() {
+    #[tokio::test]
+    async fn test_invalid_signature() {
         let client = Arc::new(RpcClient::new("http://localhost:8899".to_string()));
         let query = Query::new(
             "test-1".to_string(),
@@ -86,7 +96,7 @@ mod tests {
             "invalid-signature".to_string(),
         );
 
-        let result = execute_get_transaction(client, query);
+        let result = execute_get_transaction(client, query).await;
         assert!(!result.success);
         assert!(result.error.unwrap().contains("Invalid signature"));
     }
@@ -5,6 +5,7 @@
 //!
 //! Each RPC method has its own executor file for modularity.
 
+mod error_class;
 mod execute_query;
 mod get_account_info;
 mod get_balance;
@@ -12,40 +13,289 @@ mod get_block_height;
 mod get_multiple_accounts;
 mod get_token_account_balance;
 mod get_transaction;
+mod query_planner;
 
+pub use error_class::ErrorClass;
 pub use execute_query::execute_single_query;
 pub use get_account_info::execute_get_account_info;
 pub use get_balance::execute_get_balance;
 pub use get_block_height::execute_get_block_height;
-pub use get_multiple_accounts::execute_get_multiple_accounts;
+pub use get_multiple_accounts::{
+    execute_get_multiple_accounts, requested_accounts, MAX_MULTIPLE_ACCOUNTS,
+};
 pub use get_token_account_balance::execute_get_token_account_balance;
 pub use get_transaction::execute_get_transaction;
+pub use query_planner::{PlannedCall, QueryPlanner, ResultShare};
 
+use crate::cover::CoverTraffic;
+use crate::crypto::{parse_public_key, seal_result};
+use crate::decoy::{DecoyGenerator, IntersectionGuard};
+use crate::enums::{QueryErrorCode, RpcMethod};
 use crate::error::{ProxyError, ProxyResult};
-use crate::types::{BatchRequest, BatchResponse, QueryResult, MAX_BATCH_SIZE};
-use solana_client::rpc_client::RpcClient;
+use crate::scheduler::{DispatchScheduler, UpstreamShards};
+use crate::types::{
+    BatchRequest, BatchResponse, Query, QueryResult, UpstreamMetrics, UpstreamStatus,
+    DEFAULT_K_ANONYMITY, DEFAULT_MAX_QUERY_ACCOUNTS, MAX_BATCH_SIZE,
+};
+use crate::upstream::{BatchTransport, CallSlots, Departure, ResultConsensus, UpstreamPool};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::task::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::{JoinError, JoinHandle};
 use tracing::{info, warn};
 
 /// Executor for batched RPC queries
 pub struct BatchExecutor {
-    /// Solana RPC client
-    rpc_client: Arc<RpcClient>,
+    /// Upstream RPC endpoints with failover
+    upstreams: Arc<UpstreamPool>,
+
+    /// Minimum anonymity set size (k) a batch must reach
+    min_batch_size: usize,
+
+    /// Count distinct query targets instead of raw queries towards k
+    count_distinct_targets: bool,
+
+    /// Decoy queries dispatched upstream alongside each batch
+    decoys: Option<Arc<DecoyGenerator>>,
+
+    /// Persistent decoy cohorts of recurring targets
+    intersection_guard: Option<Arc<IntersectionGuard>>,
+
+    /// Upstream batch sizes every batch is rounded up to (ascending)
+    batch_buckets: Vec<usize>,
+
+    /// Order and timing of upstream calls
+    scheduler: DispatchScheduler,
+
+    /// Constant-rate emitter upstream calls are queued into (cover traffic)
+    cover_traffic: Option<Arc<CoverTraffic>>,
+
+    /// Providers the calls of each batch are split across (sharding)
+    shards: Option<Arc<UpstreamShards>>,
+
+    /// Cross-provider check of selected calls (consensus)
+    consensus: Option<Arc<ResultConsensus>>,
+
+    /// Transport sending each batch as JSON-RPC batch arrays
+    batch_transport: Option<Arc<BatchTransport>>,
+
+    /// Coalescing of single-account queries into getMultipleAccounts calls
+    planner: Option<QueryPlanner>,
+
+    /// Most accounts a single getMultipleAccounts query may ask for
+    max_query_accounts: usize,
+
+    /// Time a call may take once it leaves
+    query_timeout: Option<Duration>,
+
+    /// Time after which a batch is returned with the results it has
+    batch_timeout: Option<Duration>,
 }
 
 impl BatchExecutor {
     /// Create a new batch executor with the given RPC URL
     pub fn new(rpc_url: &str) -> Self {
-        let rpc_client = Arc::new(RpcClient::new(rpc_url.to_string()));
-        Self { rpc_client }
+        Self {
+            upstreams: Arc::new(UpstreamPool::single(rpc_url)),
+            min_batch_size: DEFAULT_K_ANONYMITY,
+            count_distinct_targets: false,
+            decoys: None,
+            intersection_guard: None,
+            batch_buckets: Vec::new(),
+            scheduler: DispatchScheduler::default(),
+            cover_traffic: None,
+            shards: None,
+            consensus: None,
+            batch_transport: None,
+            planner: None,
+            max_query_accounts: DEFAULT_MAX_QUERY_ACCOUNTS,
+            query_timeout: None,
+            batch_timeout: None,
+        }
+    }
+
+    /// Send upstream calls to a pool of endpoints with failover
+    ///
+    /// Replaces the single endpoint the executor was created with.
+    pub fn with_upstreams(mut self, upstreams: Arc<UpstreamPool>) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+
+    /// Set the minimum anonymity set size (k)
+    pub fn with_min_batch_size(mut self, k: usize) -> Self {
+        self.min_batch_size = k;
+        self
+    }
+
+    /// Count distinct query targets towards k instead of raw queries
+    ///
+    /// Ten queries for the same pubkey then form an anonymity set of one.
+    pub fn with_distinct_targets(mut self, enabled: bool) -> Self {
+        self.count_distinct_targets = enabled;
+        self
+    }
+
+    /// Pad every batch with decoy queries before dispatching it upstream
+    ///
+    /// Decoys do not count towards k; their results are dropped.
+    pub fn with_decoys(mut self, decoys: Arc<DecoyGenerator>) -> Self {
+        self.decoys = Some(decoys);
+        self
+    }
+
+    /// Add the decoy cohorts of recurring targets to every batch
+    ///
+    /// Cohorts are dispatched like any other decoy, so the guard only applies
+    /// together with [`with_decoys`](Self::with_decoys).
+    pub fn with_intersection_guard(mut self, guard: Arc<IntersectionGuard>) -> Self {
+        self.intersection_guard = Some(guard);
+        self
+    }
+
+    /// Round the upstream calls of every batch up to the next of these sizes
+    ///
+    /// Calls are counted after coalescing. The padding is made of decoys
+    /// mirroring the calls, so
+    /// it only applies together with [`with_decoys`](Self::with_decoys).
+    pub fn with_batch_buckets(mut self, mut buckets: Vec<usize>) -> Self {
+        buckets.retain(|bucket| *bucket > 0);
+        buckets.sort_unstable();
+        buckets.dedup();
+        self.batch_buckets = buckets;
+        self
+    }
+
+    /// Spread the upstream calls of each batch across `window`
+    ///
+    /// Calls are always dispatched in random order; a non-zero window also
+    /// randomizes the delays between them, at the cost of batch latency.
+    pub fn with_dispatch_window(mut self, window: Duration) -> Self {
+        self.scheduler = DispatchScheduler::new(window);
+        self
+    }
+
+    /// Send upstream calls through a cover traffic emitter
+    ///
+    /// Calls are queued, in dispatch order, into the emitter's next free
+    /// slots instead of being sent right away.
+    pub fn with_cover_traffic(mut self, cover_traffic: Arc<CoverTraffic>) -> Self {
+        self.cover_traffic = Some(cover_traffic);
+        self
+    }
+
+    /// Split the upstream calls of each batch across several providers
+    ///
+    /// Shard `i` is upstream `i` of the pool; a call fails over to the other
+    /// upstreams only when its shard is unavailable.
+    pub fn with_shards(mut self, shards: Arc<UpstreamShards>) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    /// Check selected upstream calls against several providers
+    ///
+    /// Decoys are selected like real queries, so the duplicated calls do not
+    /// single out real ones.
+    pub fn with_consensus(mut self, consensus: Arc<ResultConsensus>) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    /// Send the upstream calls of each batch as JSON-RPC batch arrays
+    ///
+    /// The transport sends everything at once, so the dispatch window does
+    /// not apply; the dispatch order still does.
+    pub fn with_batch_transport(mut self, transport: Arc<BatchTransport>) -> Self {
+        self.batch_transport = Some(transport);
+        self
+    }
+
+    /// Merge the single-account queries of each batch into
+    /// getMultipleAccounts calls
+    ///
+    /// Queries of the methods consensus always verifies keep their own call.
+    pub fn with_query_planner(mut self, planner: QueryPlanner) -> Self {
+        self.planner = Some(planner);
+        self
+    }
+
+    /// Set the most accounts a getMultipleAccounts query may ask for
+    ///
+    /// Queries above the upstream's own limit are split into chunks.
+    pub fn with_max_query_accounts(mut self, limit: usize) -> Self {
+        self.max_query_accounts = limit;
+        self
+    }
+
+    /// Fail calls still running `timeout` after they leave (zero: never)
+    ///
+    /// A call leaves once its first request has a call slot; the wait for a
+    /// slot or a cover traffic slot is bounded by the batch timeout alone.
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Return batches after at most `timeout`, failing the calls still
+    /// running and dropping those still queued unsent (zero: never)
+    pub fn with_batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch_timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Reject a getMultipleAccounts query asking for more accounts than allowed
+    pub fn check_account_limit(&self, query: &Query) -> ProxyResult<()> {
+        let requested = match query.method {
+            RpcMethod::GetMultipleAccounts => requested_accounts(query),
+            _ => return Ok(()),
+        };
+        if requested > self.max_query_accounts {
+            return Err(ProxyError::InvalidQuery(format!(
+                "Query '{}' asks for {} accounts, more than the limit of {}",
+                query.id, requested, self.max_query_accounts
+            )));
+        }
+        Ok(())
+    }
+
+    /// Number of upstream calls a batch of `calls` calls is padded up to
+    ///
+    /// Batches beyond the largest bucket are padded to a multiple of it.
+    pub fn padded_size(&self, calls: usize) -> usize {
+        match self.batch_buckets.iter().find(|bucket| **bucket >= calls) {
+            Some(bucket) => *bucket,
+            None => match self.batch_buckets.last() {
+                Some(largest) => calls.div_ceil(*largest) * largest,
+                None => calls,
+            },
+        }
+    }
+
+    /// Size of the anonymity set a batch of queries provides
+    pub fn anonymity_set_size(&self, queries: &[Query]) -> usize {
+        if self.count_distinct_targets {
+            queries
+                .iter()
+                .map(Query::target)
+                .collect::<HashSet<_>>()
+                .len()
+        } else {
+            queries.len()
+        }
     }
 
     /// Execute a batch of queries in parallel
     ///
     /// Each query is executed as a separate Tokio task, allowing for
     /// parallel execution while maintaining individual error handling.
+    /// Queries are dispatched in random order across the dispatch window;
+    /// results come back in request order unless `shuffle_results` is set.
+    /// Queries still running at their deadline fail with a timeout, and the
+    /// batch is returned on time marked as partial.
     pub async fn execute_batch(&self, request: BatchRequest) -> ProxyResult<BatchResponse> {
         // Validate batch
         if request.is_empty() {
@@ -54,6 +304,16 @@ impl BatchExecutor {
         if request.len() > MAX_BATCH_SIZE {
             return Err(ProxyError::batch_too_large(request.len()));
         }
+        let anonymity_set = self.anonymity_set_size(&request.queries);
+        if anonymity_set < self.min_batch_size {
+            return Err(ProxyError::BatchTooSmall {
+                actual: anonymity_set,
+                min: self.min_batch_size,
+            });
+        }
+        for query in &request.queries {
+            self.check_account_limit(query)?;
+        }
 
         let batch_id = request
             .batch_hash
@@ -67,44 +327,257 @@ impl BatchExecutor {
             "Executing batch"
         );
 
-        let start = Instant::now();
-
-        // Spawn parallel tasks for each query
-        // Use spawn_blocking since RpcClient is blocking
-        let handles: Vec<JoinHandle<QueryResult>> = request
+        // Validate encryption keys up front so no result is ever returned in
+        // plaintext to a client that asked for encryption
+        let encryption_keys = request
             .queries
-            .into_iter()
+            .iter()
             .map(|query| {
-                let client = Arc::clone(&self.rpc_client);
-                tokio::task::spawn_blocking(move || execute_single_query(client, query))
+                query
+                    .encryption_key
+                    .as_deref()
+                    .map(parse_public_key)
+                    .transpose()
             })
+            .collect::<ProxyResult<Vec<_>>>()?;
+
+        let mut decoys = Vec::new();
+        if let Some(generator) = &self.decoys {
+            decoys = generator.generate(&request.queries);
+
+            // Recurring targets bring the same cohort every time, so
+            // intersecting their batches does not single them out
+            if let Some(guard) = &self.intersection_guard {
+                decoys.extend(guard.protect(&request.queries));
+            }
+        }
+
+        let shuffle_results = request.shuffle_results;
+        // The request may only tighten the proxy's deadlines
+        let query_timeout = earliest(
+            self.query_timeout,
+            requested_timeout(request.query_timeout_ms),
+        );
+        let batch_timeout = earliest(
+            self.batch_timeout,
+            requested_timeout(request.batch_timeout_ms),
+        );
+
+        // Real queries carry their result slot, decoys none. They are
+        // shuffled together, so decoys do not stand out by position and
+        // neither the calls nor the accounts of coalesced calls follow the
+        // client's ordering.
+        let mut entries: Vec<_> = request
+            .queries
+            .into_iter()
+            .zip(encryption_keys)
+            .enumerate()
+            .map(|(slot, (query, encryption_key))| (Some(slot), query, encryption_key))
+            .chain(decoys.into_iter().map(|query| (None, query, None)))
             .collect();
+        entries.shuffle(&mut OsRng);
 
-        // Collect results
-        let mut results = Vec::with_capacity(handles.len());
+        let mut recipients = Vec::with_capacity(entries.len());
+        let mut targets = Vec::with_capacity(entries.len());
+        let mut queries = Vec::with_capacity(entries.len());
+        for (slot, query, encryption_key) in entries {
+            recipients.push((slot, query.id.clone(), encryption_key));
+            targets.push(query.target());
+            queries.push(query);
+        }
+        let mut decoy_count = queries.len() - query_count;
 
-        for handle in handles {
-            match handle.await {
-                Ok(result) => {
-                    results.push(result);
-                }
-                Err(join_error) => {
+        // Under keyed sharding only accounts pinned to the same provider are
+        // merged, so coalescing does not move an account off its provider
+        let homes: Vec<Option<usize>> = match &self.shards {
+            Some(shards) => targets
+                .iter()
+                .map(|target| shards.home(target.as_deref()))
+                .collect(),
+            None => vec![None; queries.len()],
+        };
+        let mut planned: Vec<PlannedCall> = match &self.planner {
+            // Queries that are always verified keep a call of their own
+            Some(planner) => planner.plan(queries, &homes, |query| {
+                self.consensus
+                    .as_ref()
+                    .is_none_or(|consensus| !consensus.always_selects(query.method))
+            }),
+            None => queries
+                .into_iter()
+                .enumerate()
+                .map(|(index, query)| PlannedCall::single(index, query))
+                .collect(),
+        };
+
+        // Round the upstream calls up to their bucket so their number does
+        // not reveal how many queries are real. The padding mirrors the
+        // calls, coalesced ones included.
+        if let Some(generator) = &self.decoys {
+            let padding = self.padded_size(planned.len()) - planned.len();
+            let shapes: Vec<Query> = planned.iter().map(|call| call.query.clone()).collect();
+            for query in generator.generate_like(&shapes, padding) {
+                let index = recipients.len();
+                recipients.push((None, query.id.clone(), None));
+                targets.push(query.target());
+                planned.push(PlannedCall::single(index, query));
+                decoy_count += 1;
+            }
+        }
+        let upstream_calls = planned.len();
+
+        // The scheduler spreads the calls across the dispatch window.
+        // Upstreams are picked per call in dispatch order, so round-robin
+        // shards do not follow the client's ordering either; a call is keyed
+        // by its first query, which shares its provider with the others.
+        let schedule = self.scheduler.schedule(planned);
+        // Without sharding the pool picks the best scored upstream. With it,
+        // failover may not push a provider past its share of the calls.
+        let (preferred, room): (Vec<Option<usize>>, _) = match &self.shards {
+            Some(shards) => {
+                let keys: Vec<_> = schedule
+                    .iter()
+                    .map(|(_, call)| targets[call.first()].clone())
+                    .collect();
+                let plan = shards.plan(&keys);
+                let room = Arc::new(shards.room(&plan));
+                (plan.into_iter().map(Some).collect(), Some(room))
+            }
+            None => (vec![None; schedule.len()], None),
+        };
+
+        let (shares, calls): (Vec<_>, Vec<_>) = schedule
+            .into_iter()
+            .zip(preferred)
+            .map(|((offset, call), preferred)| {
+                let share = (call.members, call.query.id.clone(), offset);
+                (share, (offset, call.query, preferred))
+            })
+            .unzip();
+
+        let start = Instant::now();
+        let dispatch_start = tokio::time::Instant::now();
+        let batch_deadline = batch_timeout.map(|timeout| dispatch_start + timeout);
+
+        // Each call reports when it leaves, which starts its query timeout
+        let (departures, departed): (Vec<_>, Vec<_>) =
+            calls.iter().map(|_| Departure::channel()).unzip();
+        let calls = calls.into_iter().zip(departures);
+
+        let handles = match (&self.cover_traffic, &self.batch_transport) {
+            // Queue the calls for the emitter's slots; its constant rate
+            // replaces the dispatch window
+            (Some(cover_traffic), _) => {
+                let calls = calls
+                    .map(|((_, query, _), departure)| (query, departure))
+                    .collect();
+                let receivers = cover_traffic.submit(calls)?;
+                forward_results(receivers, "Cover traffic emitter dropped the call")
+            }
+            // Send the calls together as JSON-RPC batch arrays, in dispatch
+            // order; one request per upstream leaves no window to spread
+            (None, Some(transport)) => {
+                let calls = calls
+                    .map(|((_, query, preferred), departure)| (query, preferred, departure))
+                    .collect();
+                let receivers = transport.submit(calls, room);
+                forward_results(receivers, "Upstream batch transport dropped the call")
+            }
+            // Spawn a task per call that waits for its dispatch offset, then
+            // runs the call on the pool, which bounds the requests in flight
+            (None, None) => calls
+                .map(|((offset, query, preferred), departure)| {
+                    let upstreams = Arc::clone(&self.upstreams);
+                    let room = room.clone();
+                    let consensus = self
+                        .consensus
+                        .as_ref()
+                        .filter(|consensus| consensus.selects(&query))
+                        .map(Arc::clone);
+                    tokio::spawn(async move {
+                        tokio::time::sleep_until(dispatch_start + offset).await;
+                        let execute = async {
+                            match consensus {
+                                Some(consensus) => {
+                                    consensus.execute(&upstreams, query, preferred).await
+                                }
+                                None => upstreams.execute(query, preferred, room.as_deref()).await,
+                            }
+                        };
+                        CallSlots::departing(departure, execute).await
+                    })
+                })
+                .collect(),
+        };
+
+        // Split the results of coalesced calls back into per-query results,
+        // dropping those of decoys. Calls still running or queued at their
+        // deadline are abandoned, which cancels those that have not left;
+        // the tasks run concurrently, so waiting for them in turn ends by the
+        // latest deadline.
+        let mut results = Vec::with_capacity(query_count);
+        let mut timed_out = 0;
+
+        let tasks = handles.into_iter().zip(departed);
+        for ((members, call_id, offset), (mut handle, departed)) in shares.into_iter().zip(tasks) {
+            let outcome = await_call(
+                &mut handle,
+                departed,
+                query_timeout,
+                batch_deadline,
+                dispatch_start + offset,
+            )
+            .await;
+            let result = match outcome {
+                Ok(Ok(result)) => result,
+                Ok(Err(join_error)) => {
                     // Task panicked or was cancelled
                     warn!(error = %join_error, "Query task failed");
-                    results.push(QueryResult::failure(
+                    QueryResult::failure(
                         "unknown".to_string(),
                         format!("Task execution failed: {}", join_error),
-                    ));
+                    )
                 }
+                Err(budget) => {
+                    handle.abort();
+                    QueryResult::timeout(call_id, budget.as_millis() as u64)
+                }
+            };
+            for (index, share) in members {
+                let (slot, id, encryption_key) = &recipients[index];
+                let Some(slot) = slot else {
+                    continue;
+                };
+                if result.error_code == Some(QueryErrorCode::Timeout) {
+                    timed_out += 1;
+                }
+                let result = share.extract(id, &result);
+                let result = match encryption_key {
+                    Some(key) => seal_result(result, key),
+                    None => result,
+                };
+                results.push((*slot, result));
             }
         }
 
+        // Return results in request order unless the client asked otherwise
+        if shuffle_results {
+            results.shuffle(&mut OsRng);
+        } else {
+            results.sort_by_key(|(slot, _)| *slot);
+        }
+        let results = results.into_iter().map(|(_, result)| result).collect();
+
         let execution_time_ms = start.elapsed().as_millis() as u64;
-        let response = BatchResponse::from_results(results, execution_time_ms);
+        let response =
+            BatchResponse::from_results(results, execution_time_ms).with_partial(timed_out > 0);
 
         info!(
             batch_id = %batch_id,
             execution_time_ms = execution_time_ms,
+            decoys = decoy_count,
+            upstream_calls = upstream_calls,
+            timed_out = timed_out,
             succeeded = response.succeeded_count,
             failed = response.failed_count,
             "Batch complete"
@@ -113,22 +586,152 @@ impl BatchExecutor {
         Ok(response)
     }
 
+    /// Probe every upstream and report its state
+    pub async fn upstream_health(&self) -> Vec<UpstreamStatus> {
+        self.upstreams.check_health().await
+    }
+
+    /// Report the upstream call slot usage
+    pub fn upstream_metrics(&self) -> UpstreamMetrics {
+        self.upstreams.metrics()
+    }
+
     /// Check if the RPC connection is healthy
+    ///
+    /// Queries fail over, so one healthy upstream is enough.
     pub async fn check_health(&self) -> bool {
-        let client = Arc::clone(&self.rpc_client);
+        self.upstream_health()
+            .await
+            .iter()
+            .any(|status| status.healthy)
+    }
+}
 
-        // Run the blocking RPC call in a blocking task
-        tokio::task::spawn_blocking(move || client.get_health().is_ok())
+/// The earlier of two optional limits
+fn earliest<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Timeout a request asks for in milliseconds (zero: none)
+fn requested_timeout(timeout_ms: Option<u64>) -> Option<Duration> {
+    timeout_ms
+        .filter(|timeout_ms| *timeout_ms > 0)
+        .map(Duration::from_millis)
+}
+
+/// Wait for the result of a call until its deadline
+///
+/// The query timeout runs from the moment the call leaves, so time spent in
+/// the cover traffic queue or waiting for a call slot does not use it up;
+/// only the batch deadline bounds that wait. On timeout, returns the limit
+/// that ran out: the query timeout, or the batch's from `dispatched`.
+async fn await_call(
+    handle: &mut JoinHandle<QueryResult>,
+    departed: oneshot::Receiver<tokio::time::Instant>,
+    query_timeout: Option<Duration>,
+    batch_deadline: Option<tokio::time::Instant>,
+    dispatched: tokio::time::Instant,
+) -> Result<Result<QueryResult, JoinError>, Duration> {
+    let batch_budget =
+        |deadline: tokio::time::Instant| deadline.saturating_duration_since(dispatched);
+    let batch_expired = async {
+        match batch_deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    };
+    // A call that ends without leaving, such as an invalid one, reports no
+    // departure
+    let departed = tokio::select! {
+        result = &mut *handle => return Ok(result),
+        departed = departed => departed.ok(),
+        () = batch_expired => return Err(batch_budget(batch_deadline.unwrap_or(dispatched))),
+    };
+
+    let query_deadline = departed
+        .zip(query_timeout)
+        .map(|(departed, timeout)| departed + timeout);
+    match earliest(query_deadline, batch_deadline) {
+        Some(deadline) => tokio::time::timeout_at(deadline, handle)
             .await
-            .unwrap_or(false)
+            .map_err(|_| match query_timeout {
+                Some(timeout) if query_deadline == Some(deadline) => timeout,
+                _ => batch_budget(deadline),
+            }),
+        None => Ok(handle.await),
     }
 }
 
+/// Spawn a task per call that waits for its result on `receiver`
+///
+/// `dropped` is the error of calls whose sender went away.
+fn forward_results(
+    receivers: Vec<oneshot::Receiver<QueryResult>>,
+    dropped: &'static str,
+) -> Vec<JoinHandle<QueryResult>> {
+    receivers
+        .into_iter()
+        .map(|receiver| {
+            tokio::spawn(async move {
+                receiver.await.unwrap_or_else(|_| {
+                    QueryResult::failure("unknown".to_string(), dropped.to_string())
+                })
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::decoy::{CorpusEntry, DecoyCorpus};
     use crate::enums::RpcMethod;
-    use crate::types::Query;
+    use crate::types::{DecoyConfig, FailoverConfig, Query};
+    use crate::upstream::{MockUpstream, MOCK_LAMPORTS};
+    use serde_json::json;
+
+    /// `count` distinct valid pubkeys, starting from seed `first`
+    fn accounts(first: u8, count: u8) -> Vec<String> {
+        (first..first + count)
+            .map(|i| solana_sdk::pubkey::Pubkey::new_from_array([i; 32]).to_string())
+            .collect()
+    }
+
+    fn balance_queries(pubkeys: &[String]) -> Vec<Query> {
+        pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| {
+                Query::new(
+                    format!("query-{}", i),
+                    RpcMethod::GetBalance,
+                    pubkey.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Decoys drawn from `corpus`, padding batches up to `target_size`
+    fn decoys(corpus: &[String], target_size: usize) -> Arc<DecoyGenerator> {
+        let corpus = DecoyCorpus {
+            accounts: corpus
+                .iter()
+                .map(|pubkey| CorpusEntry {
+                    pubkey: pubkey.clone(),
+                    weight: 1.0,
+                })
+                .collect(),
+            token_accounts: Vec::new(),
+        };
+        Arc::new(DecoyGenerator::new(corpus, DecoyConfig::new(String::new(), target_size)).unwrap())
+    }
+
+    fn ids(response: &BatchResponse) -> Vec<String> {
+        response.results.iter().map(|r| r.id.clone()).collect()
+    }
 
     #[test]
     fn test_batch_executor_validates_empty_batch() {
@@ -164,4 +767,529 @@ mod tests {
             Err(ProxyError::BatchTooLarge { actual: 150, .. })
         ));
     }
+
+    #[test]
+    fn test_batch_executor_enforces_k_anonymity() {
+        let executor = BatchExecutor::new("http://localhost:8899").with_min_batch_size(3);
+        let request = BatchRequest::new(balance_queries(&accounts(0, 2)));
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(executor.execute_batch(request));
+
+        assert!(matches!(
+            result,
+            Err(ProxyError::BatchTooSmall { actual: 2, min: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_batch_executor_rejects_invalid_encryption_key() {
+        let executor = BatchExecutor::new("http://localhost:8899").with_min_batch_size(1);
+        let queries = balance_queries(&accounts(0, 1))
+            .into_iter()
+            .map(|query| query.with_encryption_key("not-a-key".to_string()))
+            .collect();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(executor.execute_batch(BatchRequest::new(queries)));
+
+        assert!(matches!(result, Err(ProxyError::InvalidQuery(_))));
+    }
+
+    #[tokio::test]
+    async fn test_decoys_reach_the_upstream_and_are_dropped() {
+        let upstream = MockUpstream::start().await;
+        let corpus = accounts(100, 4);
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(2)
+            .with_decoys(decoys(&corpus, 8));
+        let real = accounts(0, 2);
+
+        let response = executor
+            .execute_batch(BatchRequest::new(balance_queries(&real)))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&response), ["query-0", "query-1"]);
+        assert_eq!(response.succeeded_count, 2);
+        // Six decoys on corpus accounts padded the batch up to eight calls
+        let targets = upstream.targets();
+        assert_eq!(targets.len(), 8);
+        let padding: Vec<&String> = targets.iter().filter(|t| !real.contains(t)).collect();
+        assert_eq!(padding.len(), 6);
+        assert!(padding.iter().all(|target| corpus.contains(target)));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_sees_only_bucket_sizes() {
+        let upstream = MockUpstream::start().await;
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_decoys(decoys(&accounts(100, 4), 0))
+            .with_batch_buckets(vec![8, 16]);
+
+        for (real, bucket) in [(3, 8), (9, 16)] {
+            let before = upstream.call_count();
+            let response = executor
+                .execute_batch(BatchRequest::new(balance_queries(&accounts(0, real))))
+                .await
+                .unwrap();
+            assert_eq!(response.results.len(), real as usize);
+            assert_eq!(upstream.call_count() - before, bucket);
+        }
+        // The padding mirrors the methods of the batch
+        assert!(upstream
+            .calls()
+            .iter()
+            .all(|call| call.method == "getBalance"));
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_calls_are_padded_to_the_bucket() {
+        let upstream = MockUpstream::start().await;
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_decoys(decoys(&accounts(100, 4), 0))
+            .with_batch_buckets(vec![8])
+            .with_query_planner(QueryPlanner::new(5).unwrap());
+
+        let response = executor
+            .execute_batch(BatchRequest::new(balance_queries(&accounts(0, 15))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.succeeded_count, 15);
+        // Fifteen accounts in three calls, padded with five more like them
+        let calls = upstream.calls();
+        assert_eq!(calls.len(), 8);
+        assert!(calls
+            .iter()
+            .all(|call| call.method == "getMultipleAccounts" && call.targets().len() == 5));
+    }
+
+    #[tokio::test]
+    async fn test_recurring_target_brings_the_same_cohort() {
+        use crate::types::CohortConfig;
+
+        let upstream = MockUpstream::start().await;
+        let corpus = accounts(100, 8);
+        let decoys = decoys(&corpus, 0);
+        let guard = Arc::new(IntersectionGuard::new(
+            Arc::clone(&decoys),
+            CohortConfig::new(1, 4),
+            [0; 32],
+        ));
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_decoys(decoys)
+            .with_intersection_guard(Arc::clone(&guard));
+        let real = accounts(0, 1);
+
+        let mut cohorts = Vec::new();
+        for _ in 0..2 {
+            let before = upstream.call_count();
+            let response = executor
+                .execute_batch(BatchRequest::new(balance_queries(&real)))
+                .await
+                .unwrap();
+            assert_eq!(ids(&response), ["query-0"]);
+
+            let mut cohort: Vec<String> = upstream.calls()[before..]
+                .iter()
+                .flat_map(|call| call.targets())
+                .filter(|target| !real.contains(target))
+                .collect();
+            cohort.sort();
+            cohorts.push(cohort);
+        }
+
+        assert_eq!(cohorts[0].len(), 4);
+        assert!(cohorts[0].iter().all(|target| corpus.contains(target)));
+        assert_eq!(cohorts[0], cohorts[1]);
+        assert_eq!(guard.metrics().cohort_decoys, 8);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_is_shuffled_and_spread_but_results_are_not() {
+        let upstream = MockUpstream::start().await;
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_dispatch_window(Duration::from_millis(50));
+        let pubkeys = accounts(0, 16);
+        let queries = balance_queries(&pubkeys);
+        let expected: Vec<String> = queries.iter().map(|q| q.id.clone()).collect();
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries.clone()))
+            .await
+            .unwrap();
+        assert_eq!(ids(&response), expected);
+
+        // The upstream saw every target, in another order and not at once
+        let calls = upstream.calls();
+        let mut seen = upstream.targets();
+        assert_ne!(seen, pubkeys);
+        seen.sort();
+        let mut sorted = pubkeys.clone();
+        sorted.sort();
+        assert_eq!(seen, sorted);
+        let first = calls.iter().map(|call| call.at).min().unwrap();
+        let last = calls.iter().map(|call| call.at).max().unwrap();
+        assert!(last - first >= Duration::from_millis(5));
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries).with_shuffled_results())
+            .await
+            .unwrap();
+        let mut shuffled = ids(&response);
+        assert_ne!(shuffled, expected);
+        shuffled.sort();
+        let mut sorted = expected;
+        sorted.sort();
+        assert_eq!(shuffled, sorted);
+    }
+
+    #[tokio::test]
+    async fn test_cover_traffic_carries_batch_calls() {
+        use crate::types::CoverTrafficConfig;
+
+        let upstream = MockUpstream::start().await;
+        let cover_traffic = Arc::new(CoverTraffic::new(
+            Arc::new(UpstreamPool::single(&upstream.url())),
+            decoys(&accounts(100, 4), 0),
+            CoverTrafficConfig::new(1000),
+        ));
+        let _clock = cover_traffic.start();
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_cover_traffic(Arc::clone(&cover_traffic));
+        let pubkeys = accounts(0, 16);
+
+        let response = executor
+            .execute_batch(BatchRequest::new(balance_queries(&pubkeys)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.succeeded_count, 16);
+        assert_eq!(cover_traffic.metrics().real_calls, 16);
+        let targets = upstream.targets();
+        assert!(pubkeys.iter().all(|pubkey| targets.contains(pubkey)));
+    }
+
+    #[tokio::test]
+    async fn test_no_provider_sees_more_than_its_share() {
+        use crate::enums::ShardPolicy;
+        use crate::types::ShardConfig;
+
+        let upstreams = [
+            MockUpstream::start().await,
+            MockUpstream::start().await,
+            MockUpstream::start().await,
+        ];
+        let urls: Vec<String> = upstreams.iter().map(MockUpstream::url).collect();
+        let pool = UpstreamPool::new(&urls, FailoverConfig::default()).unwrap();
+        let shards = Arc::new(
+            UpstreamShards::new(
+                pool.len(),
+                &ShardConfig::default()
+                    .with_policy(ShardPolicy::RoundRobin)
+                    .with_max_fraction(0.4),
+            )
+            .unwrap(),
+        );
+        let executor = BatchExecutor::new(&urls[0])
+            .with_min_batch_size(1)
+            .with_upstreams(Arc::new(pool))
+            .with_shards(Arc::clone(&shards));
+        let queries = balance_queries(&accounts(0, 16));
+        let expected: Vec<String> = queries.iter().map(|q| q.id.clone()).collect();
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&response), expected);
+        assert_eq!(response.succeeded_count, 16);
+        let loads: Vec<usize> = upstreams.iter().map(MockUpstream::call_count).collect();
+        assert_eq!(loads.iter().sum::<usize>(), 16);
+        assert!(loads.iter().all(|load| *load <= shards.capacity(16)));
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_calls_keep_accounts_on_their_keyed_provider() {
+        use crate::enums::ShardPolicy;
+        use crate::types::ShardConfig;
+
+        let upstreams = [MockUpstream::start().await, MockUpstream::start().await];
+        let urls: Vec<String> = upstreams.iter().map(MockUpstream::url).collect();
+        let pool = UpstreamPool::new(&urls, FailoverConfig::default()).unwrap();
+        let shards = Arc::new(
+            UpstreamShards::new(
+                pool.len(),
+                &ShardConfig::default()
+                    .with_policy(ShardPolicy::Keyed)
+                    .with_max_fraction(1.0),
+            )
+            .unwrap(),
+        );
+        let executor = BatchExecutor::new(&urls[0])
+            .with_min_batch_size(1)
+            .with_upstreams(Arc::new(pool))
+            .with_shards(Arc::clone(&shards))
+            .with_query_planner(QueryPlanner::new(MAX_MULTIPLE_ACCOUNTS).unwrap());
+
+        let response = executor
+            .execute_batch(BatchRequest::new(balance_queries(&accounts(0, 16))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.succeeded_count, 16);
+        // One merged call per provider, holding only the accounts pinned to it
+        for (index, upstream) in upstreams.iter().enumerate() {
+            assert!(upstream.call_count() <= 1);
+            assert!(upstream
+                .targets()
+                .iter()
+                .all(|target| shards.home(Some(target)) == Some(index)));
+        }
+        let merged: usize = upstreams.iter().map(|u| u.targets().len()).sum();
+        assert_eq!(merged, 16);
+    }
+
+    #[tokio::test]
+    async fn test_verified_results_are_flagged() {
+        use crate::enums::ConsensusStatus;
+        use crate::types::ConsensusConfig;
+
+        let upstreams = [MockUpstream::start().await, MockUpstream::start().await];
+        let urls: Vec<String> = upstreams.iter().map(MockUpstream::url).collect();
+        let pool = UpstreamPool::new(&urls, FailoverConfig::default()).unwrap();
+        let consensus = Arc::new(ResultConsensus::new(ConsensusConfig::new(1.0)).unwrap());
+        let executor = BatchExecutor::new(&urls[0])
+            .with_min_batch_size(1)
+            .with_upstreams(Arc::new(pool))
+            .with_consensus(Arc::clone(&consensus));
+        let queries = balance_queries(&accounts(0, 4));
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries.clone()))
+            .await
+            .unwrap();
+        assert!(response
+            .results
+            .iter()
+            .all(|result| result.consensus == Some(ConsensusStatus::Agreed)));
+        assert!(upstreams.iter().all(|upstream| upstream.call_count() == 4));
+
+        // One provider now lies about every balance
+        upstreams[1].set_lamports(MOCK_LAMPORTS + 1);
+        let response = executor
+            .execute_batch(BatchRequest::new(queries))
+            .await
+            .unwrap();
+        assert!(response
+            .results
+            .iter()
+            .all(|result| result.consensus == Some(ConsensusStatus::Disagreed)));
+        assert_eq!(consensus.metrics().disagreed, 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_transport_sends_batch_arrays() {
+        let upstream = MockUpstream::start().await;
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()));
+        let transport = BatchTransport::new(Arc::clone(&pool), 5).unwrap();
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_upstreams(pool)
+            .with_batch_transport(Arc::new(transport));
+        let queries = balance_queries(&accounts(0, 16));
+        let expected: Vec<String> = queries.iter().map(|q| q.id.clone()).collect();
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&response), expected);
+        assert!(response
+            .results
+            .iter()
+            .all(|result| result.data == Some(json!({ "lamports": MOCK_LAMPORTS }))));
+        // Sixteen calls in four arrays of at most five, none on its own
+        assert_eq!((upstream.requests(), upstream.batches()), (4, 4));
+        assert!(upstream.calls().iter().all(|call| call.batched));
+    }
+
+    #[tokio::test]
+    async fn test_hung_queries_time_out() {
+        let upstream = MockUpstream::start().await;
+        upstream.hang();
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(2)
+            .with_query_timeout(Duration::from_millis(100));
+        let request = BatchRequest::new(balance_queries(&accounts(0, 2)));
+
+        let response = executor.execute_batch(request).await.unwrap();
+
+        assert!(response.partial);
+        assert_eq!(response.failed_count, 2);
+        assert_eq!(ids(&response), ["query-0", "query-1"]);
+        assert!(response
+            .results
+            .iter()
+            .all(|result| result.error_code == Some(QueryErrorCode::Timeout)));
+        assert_eq!(
+            response.results[0].error.as_deref(),
+            Some("Query execution timed out after 100ms")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_can_only_tighten_batch_deadline() {
+        let upstream = MockUpstream::start().await;
+        upstream.hang();
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_batch_timeout(Duration::from_millis(200));
+        let queries = balance_queries(&accounts(0, 1));
+
+        let started = Instant::now();
+        let request = BatchRequest::new(queries.clone()).with_batch_timeout(50);
+        let response = executor.execute_batch(request).await.unwrap();
+        assert!(response.partial);
+        assert!(started.elapsed() < Duration::from_millis(200));
+
+        let request = BatchRequest::new(queries).with_batch_timeout(60_000);
+        let response = executor.execute_batch(request).await.unwrap();
+        assert_eq!(
+            response.results[0].error.as_deref(),
+            Some("Query execution timed out after 200ms")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_timeout_starts_when_calls_leave() {
+        let upstream = MockUpstream::start().await;
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()).with_max_in_flight(1));
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_upstreams(Arc::clone(&pool))
+            .with_query_timeout(Duration::from_millis(100));
+        let request = BatchRequest::new(balance_queries(&accounts(0, 1)));
+
+        // The call waits longer than its timeout for a slot, then succeeds
+        let slot = pool.try_acquire_slot().unwrap();
+        let batch = tokio::spawn(async move { executor.execute_batch(request).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        drop(slot);
+
+        let response = batch.await.unwrap().unwrap();
+        assert!(!response.partial);
+        assert_eq!(response.succeeded_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_queued_calls_are_dropped_at_the_batch_deadline() {
+        let upstream = MockUpstream::start().await;
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()).with_max_in_flight(1));
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_upstreams(Arc::clone(&pool))
+            .with_batch_timeout(Duration::from_millis(100));
+        let request = BatchRequest::new(balance_queries(&accounts(0, 1)));
+
+        let slot = pool.try_acquire_slot().unwrap();
+        let response = executor.execute_batch(request).await.unwrap();
+        assert!(response.partial);
+        assert_eq!(
+            response.results[0].error.as_deref(),
+            Some("Query execution timed out after 100ms")
+        );
+
+        drop(slot);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(upstream.requests(), 0);
+        assert_eq!(pool.metrics().queue_depth, 0);
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_results_keep_their_method_shape() {
+        let upstream = MockUpstream::start().await;
+        let executor = BatchExecutor::new(&upstream.url())
+            .with_min_batch_size(1)
+            .with_query_planner(QueryPlanner::new(5).unwrap());
+        let mut queries = balance_queries(&accounts(0, 16));
+        queries[3].method = RpcMethod::GetAccountInfo;
+        let expected: Vec<String> = queries.iter().map(|q| q.id.clone()).collect();
+
+        let response = executor
+            .execute_batch(BatchRequest::new(queries))
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&response), expected);
+        assert_eq!(response.succeeded_count, 16);
+        assert_eq!(
+            response.results[0].data,
+            Some(json!({ "lamports": MOCK_LAMPORTS }))
+        );
+        assert_eq!(response.results[3].data.as_ref().unwrap()["dataLength"], 3);
+        // Sixteen accounts in calls of at most five; the last one is alone,
+        // so it goes out as the query it is
+        let calls = upstream.calls();
+        assert_eq!(calls.len(), 4);
+        let merged = calls
+            .iter()
+            .filter(|call| call.method == "getMultipleAccounts")
+            .count();
+        assert_eq!(merged, 3);
+    }
+
+    #[test]
+    fn test_batch_executor_enforces_account_limit() {
+        let executor = BatchExecutor::new("http://localhost:8899")
+            .with_min_batch_size(1)
+            .with_max_query_accounts(2);
+        let accounts = |count: usize| {
+            Query::with_params(
+                "accounts".to_string(),
+                RpcMethod::GetMultipleAccounts,
+                serde_json::json!(vec!["11111111111111111111111111111111"; count]),
+            )
+        };
+        assert!(executor.check_account_limit(&accounts(2)).is_ok());
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(executor.execute_batch(BatchRequest::new(vec![accounts(3)])));
+        assert!(matches!(result, Err(ProxyError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_padded_size_rounds_up_to_bucket() {
+        let executor = BatchExecutor::new("http://localhost:8899");
+        assert_eq!(executor.padded_size(5), 5);
+
+        let executor = executor.with_batch_buckets(vec![32, 8, 0, 16, 64, 16]);
+        assert_eq!(executor.padded_size(1), 8);
+        assert_eq!(executor.padded_size(8), 8);
+        assert_eq!(executor.padded_size(9), 16);
+        assert_eq!(executor.padded_size(33), 64);
+        assert_eq!(executor.padded_size(100), 128);
+    }
+
+    #[test]
+    fn test_anonymity_set_counts_distinct_targets() {
+        let repeated = balance_queries(&vec![accounts(0, 1)[0].clone(); 10]);
+        let mut mixed = repeated.clone();
+        mixed.extend(balance_queries(&accounts(1, 1)));
+
+        let raw = BatchExecutor::new("http://localhost:8899");
+        assert_eq!(raw.anonymity_set_size(&repeated), 10);
+
+        let distinct = BatchExecutor::new("http://localhost:8899").with_distinct_targets(true);
+        assert_eq!(distinct.anonymity_set_size(&repeated), 1);
+        assert_eq!(distinct.anonymity_set_size(&mixed), 2);
+    }
 }
@@ -0,0 +1,325 @@
+//! Query planner coalescing single-account queries
+//!
+//! getBalance and getAccountInfo queries of a batch that would reach the same
+//! upstream at the same commitment are merged into getMultipleAccounts calls,
+//! and the accounts of each call are split back into per-query results.
+
+use super::get_multiple_accounts::MAX_MULTIPLE_ACCOUNTS;
+use crate::enums::RpcMethod;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{Query, QueryResult};
+use serde_json::{json, Value};
+use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Part of an upstream call's result that answers a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultShare {
+    /// The whole result: the query was sent as is
+    Whole,
+    /// One account of a getMultipleAccounts result, in the shape `method`
+    /// returns
+    Account { index: usize, method: RpcMethod },
+}
+
+impl ResultShare {
+    /// Extract the result of query `id` from the result of its upstream call
+    pub fn extract(&self, id: &str, result: &QueryResult) -> QueryResult {
+        let (index, method) = match *self {
+            ResultShare::Whole => return result.clone(),
+            ResultShare::Account { index, method } => (index, method),
+        };
+        let mut share = result.clone();
+        share.id = id.to_string();
+        if !result.success {
+            return share;
+        }
+
+        let account = result.data.as_ref().and_then(|data| data.get(index));
+        let data = match (method, account) {
+            (RpcMethod::GetAccountInfo, Some(account)) => account.clone(),
+            // The RPC reports a balance of 0 for accounts that do not exist
+            (RpcMethod::GetBalance, Some(account)) => json!({
+                "lamports": account["lamports"].as_u64().unwrap_or(0),
+            }),
+            _ => {
+                return QueryResult::failure(
+                    id.to_string(),
+                    "RPC error: account missing from getMultipleAccounts response".to_string(),
+                )
+            }
+        };
+        share.data = Some(data);
+        share
+    }
+}
+
+/// An upstream call and the queries it answers
+#[derive(Debug, Clone)]
+pub struct PlannedCall {
+    /// Query sent upstream
+    pub query: Query,
+
+    /// Queries answered by the call, as (index in the batch, share of the result)
+    pub members: Vec<(usize, ResultShare)>,
+}
+
+impl PlannedCall {
+    /// Call sending the query at `index` in the batch as is
+    pub fn single(index: usize, query: Query) -> Self {
+        Self {
+            query,
+            members: vec![(index, ResultShare::Whole)],
+        }
+    }
+
+    /// Index in the batch of the first query the call answers
+    pub fn first(&self) -> usize {
+        self.members[0].0
+    }
+}
+
+/// Queries of a batch waiting to be merged into one getMultipleAccounts call
+#[derive(Default)]
+struct Group {
+    first: Option<Query>,
+    pubkeys: Vec<String>,
+    members: Vec<(usize, ResultShare)>,
+}
+
+/// Merges single-account queries into getMultipleAccounts calls
+///
+/// A batch of twenty getBalance queries then costs the upstream a single
+/// call, and the upstream no longer sees how many separate requests there
+/// were. Repeated accounts are asked for once.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryPlanner {
+    max_accounts: usize,
+}
+
+impl QueryPlanner {
+    /// Create a planner asking for at most `max_accounts` accounts per call
+    pub fn new(max_accounts: usize) -> ProxyResult<Self> {
+        if !(1..=MAX_MULTIPLE_ACCOUNTS).contains(&max_accounts) {
+            return Err(ProxyError::Internal(format!(
+                "Coalesced calls must ask for between 1 and {} accounts, not {}",
+                MAX_MULTIPLE_ACCOUNTS, max_accounts
+            )));
+        }
+        Ok(Self { max_accounts })
+    }
+
+    /// Plan the upstream calls of a batch
+    ///
+    /// `routes` holds the provider each query is pinned to, if any; only
+    /// queries with the same route are merged. Queries rejected by `mergeable`, of other
+    /// methods or with an invalid pubkey are sent as is. Calls come in the
+    /// order of their first query.
+    pub fn plan(
+        &self,
+        queries: Vec<Query>,
+        routes: &[Option<usize>],
+        mergeable: impl Fn(&Query) -> bool,
+    ) -> Vec<PlannedCall> {
+        let mut calls: Vec<Option<PlannedCall>> = Vec::new();
+        // Position of each group's pending call in `calls`
+        let mut groups: HashMap<(Option<usize>, CommitmentLevel), (usize, Group)> = HashMap::new();
+
+        for (index, query) in queries.into_iter().enumerate() {
+            let pubkey = match query.method {
+                RpcMethod::GetBalance | RpcMethod::GetAccountInfo if mergeable(&query) => query
+                    .get_primary_param()
+                    .filter(|pubkey| Pubkey::from_str(pubkey).is_ok()),
+                _ => None,
+            };
+            let Some(pubkey) = pubkey else {
+                calls.push(Some(PlannedCall::single(index, query)));
+                continue;
+            };
+
+            let commitment = query.effective_commitment().commitment;
+            let route = routes.get(index).copied().flatten();
+            let (position, group) = groups.entry((route, commitment)).or_insert_with(|| {
+                calls.push(None);
+                (calls.len() - 1, Group::default())
+            });
+            let account = match group.pubkeys.iter().position(|pk| *pk == pubkey) {
+                Some(account) => account,
+                None if group.pubkeys.len() < self.max_accounts => {
+                    group.pubkeys.push(pubkey);
+                    group.pubkeys.len() - 1
+                }
+                // The call is full: seal it and start the next one here
+                None => {
+                    let full = std::mem::take(group);
+                    calls[*position] = Some(merge(full, commitment));
+                    calls.push(None);
+                    *position = calls.len() - 1;
+                    group.pubkeys.push(pubkey);
+                    0
+                }
+            };
+            group.members.push((
+                index,
+                ResultShare::Account {
+                    index: account,
+                    method: query.method,
+                },
+            ));
+            if group.members.len() == 1 {
+                group.first = Some(query);
+            }
+        }
+
+        for ((_, commitment), (position, group)) in groups {
+            calls[position] = Some(merge(group, commitment));
+        }
+        calls.into_iter().flatten().collect()
+    }
+}
+
+/// Turn a group into its getMultipleAccounts call
+///
+/// A group of one query goes out unchanged, so it keeps the shape of a
+/// single-account call.
+fn merge(group: Group, commitment: CommitmentLevel) -> PlannedCall {
+    let Group {
+        first,
+        pubkeys,
+        members,
+    } = group;
+    if let (Some(query), [(index, _)]) = (first, members.as_slice()) {
+        return PlannedCall::single(*index, query);
+    }
+    let query = Query::with_params(
+        format!("coalesced-{}", members[0].0),
+        RpcMethod::GetMultipleAccounts,
+        Value::from(pubkeys),
+    )
+    .with_commitment(commitment.to_string());
+    PlannedCall { query, members }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: &str, method: RpcMethod, seed: u8) -> Query {
+        Query::new(
+            id.to_string(),
+            method,
+            Pubkey::new_from_array([seed; 32]).to_string(),
+        )
+    }
+
+    #[test]
+    fn test_rejects_invalid_chunk_sizes() {
+        assert!(QueryPlanner::new(0).is_err());
+        assert!(QueryPlanner::new(MAX_MULTIPLE_ACCOUNTS + 1).is_err());
+        assert!(QueryPlanner::new(MAX_MULTIPLE_ACCOUNTS).is_ok());
+    }
+
+    #[test]
+    fn test_merges_account_queries_in_chunks() {
+        let planner = QueryPlanner::new(2).unwrap();
+        let queries = vec![
+            query("a", RpcMethod::GetBalance, 1),
+            query("b", RpcMethod::GetAccountInfo, 2),
+            Query::with_params("h".to_string(), RpcMethod::GetBlockHeight, Value::Null),
+            query("c", RpcMethod::GetBalance, 1),
+            query("d", RpcMethod::GetBalance, 3),
+        ];
+        let calls = planner.plan(queries, &[None; 5], |_| true);
+
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].query.method, RpcMethod::GetMultipleAccounts);
+        assert_eq!(calls[0].query.commitment.as_deref(), Some("confirmed"));
+        assert_eq!(
+            calls[0]
+                .query
+                .params
+                .as_ref()
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        // The repeated account is asked for once
+        let indexes: Vec<usize> = calls[0].members.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indexes, [0, 1, 3]);
+        assert_eq!(
+            calls[0].members[2].1,
+            ResultShare::Account {
+                index: 0,
+                method: RpcMethod::GetBalance
+            }
+        );
+        assert_eq!(calls[1].query.method, RpcMethod::GetBlockHeight);
+        assert_eq!(calls[2].first(), 4);
+    }
+
+    #[test]
+    fn test_keeps_routes_and_unmergeable_queries_apart() {
+        let planner = QueryPlanner::new(MAX_MULTIPLE_ACCOUNTS).unwrap();
+        let queries = vec![
+            query("a", RpcMethod::GetBalance, 1),
+            query("b", RpcMethod::GetBalance, 2),
+            query("c", RpcMethod::GetAccountInfo, 3),
+            Query::new(
+                "d".to_string(),
+                RpcMethod::GetBalance,
+                "invalid".to_string(),
+            ),
+        ];
+        let calls = planner.plan(queries, &[Some(0), Some(1), Some(0), Some(0)], |query| {
+            query.method == RpcMethod::GetBalance
+        });
+
+        let members: Vec<Vec<usize>> = calls
+            .iter()
+            .map(|call| call.members.iter().map(|(index, _)| *index).collect())
+            .collect();
+        assert_eq!(members, [vec![0], vec![1], vec![2], vec![3]]);
+        assert_eq!(calls[2].query.method, RpcMethod::GetAccountInfo);
+        assert_eq!(calls[3].query.method, RpcMethod::GetBalance);
+    }
+
+    #[test]
+    fn test_extracts_results_in_method_shape() {
+        let account = json!({
+            "lamports": 5,
+            "owner": "11111111111111111111111111111111",
+            "executable": false,
+            "rentEpoch": 361,
+            "dataLength": 0,
+        });
+        let result = QueryResult::success("coalesced-0".to_string(), json!([account, null]));
+
+        let info = ResultShare::Account {
+            index: 0,
+            method: RpcMethod::GetAccountInfo,
+        };
+        assert_eq!(info.extract("a", &result).data, Some(account));
+
+        let missing_info = ResultShare::Account {
+            index: 1,
+            method: RpcMethod::GetAccountInfo,
+        };
+        assert_eq!(missing_info.extract("b", &result).data, Some(Value::Null));
+
+        let missing_balance = ResultShare::Account {
+            index: 1,
+            method: RpcMethod::GetBalance,
+        };
+        let balance = missing_balance.extract("c", &result);
+        assert_eq!(balance.id, "c");
+        assert_eq!(balance.data, Some(json!({ "lamports": 0 })));
+
+        let failure = QueryResult::failure("coalesced-0".to_string(), "RPC error".to_string());
+        let failed = info.extract("a", &failure);
+        assert_eq!((failed.id.as_str(), failed.success), ("a", false));
+    }
+}
@@ -1,9 +1,14 @@
 //! Execute batch handler
 
-use crate::coordinator::OnChainBatchStatus;
+use crate::coordinator::{
+    verify_query_hashes, CoordinatorReader, OnChainBatch, OnChainBatchStatus,
+};
+use crate::enums::VerificationMode;
 use crate::error::{ProxyError, ProxyResult};
 use crate::handlers::AppState;
-use crate::types::{BatchRequest, BatchResponse};
+use crate::hashing::parse_salt;
+use crate::store::{batch_fingerprint, Admission};
+use crate::types::{BatchRequest, BatchResponse, Query};
 use axum::{extract::State, Json};
 use std::sync::Arc;
 use tracing::info;
@@ -11,39 +16,252 @@ use tracing::info;
 /// Execute a batch of queries
 ///
 /// Receives a batch of queries and executes them in parallel against the RPC.
-/// If batch_id is provided, verifies the batch is finalized on-chain before executing.
+/// If batch_id is provided, verifies the batch is finalized on-chain and that the
+/// submitted queries match the committed query hashes before executing. After a
+/// verified batch executes, `complete_batch` is submitted in the background with
+/// the response's `batch_hash` as the results hash.
+///
+/// Each on-chain batch executes at most once. Re-posting it with the same query
+/// set returns the stored response, kept for an hour, without querying the RPC
+/// again; any other request for it is rejected, including batches already
+/// `Executed` on-chain that this proxy holds no response for.
+///
+/// With ticketed delivery enabled, a coordinated batch's results are withheld
+/// from the response and stored per query under its salt, for retrieval by the
+/// submitter through `GET /results/{ticket}`.
+///
+/// In strict coordination mode, batches that cannot be verified on-chain are
+/// rejected instead of executed.
 pub async fn execute_batch(
     State(state): State<Arc<AppState>>,
     Json(request): Json<BatchRequest>,
 ) -> ProxyResult<Json<BatchResponse>> {
-    // Verify on-chain batch status if batch_id provided
-    if let Some(batch_id_str) = &request.batch_id {
-        if let Some(coordinator) = &state.coordinator {
-            let batch_id: u64 = batch_id_str.parse().map_err(|_| {
-                ProxyError::InvalidQuery(format!("Invalid batch_id: {}", batch_id_str))
-            })?;
+    let mode = verification_mode(
+        request.batch_id.is_some(),
+        state.coordinator.is_some(),
+        state.strict_coordination,
+    )?;
+
+    let batch = match (&request.batch_id, &state.coordinator) {
+        (Some(batch_id), Some(coordinator)) => {
+            Some(verify_on_chain(coordinator, batch_id, &request.queries)?)
+        }
+        _ => None,
+    };
 
-            let batch = coordinator.get_batch(batch_id).ok_or_else(|| {
-                ProxyError::InvalidQuery(format!("Batch {} not found on-chain", batch_id))
+    let Some(batch) = batch else {
+        let response = state.executor.execute_batch(request).await?;
+        return Ok(Json(response.with_verification_mode(mode)));
+    };
+
+    let fingerprint = batch_fingerprint(&request.queries)?;
+    let ticket_hashes = match state.result_store {
+        Some(_) => Some(ticket_hashes(&request.queries)?),
+        None => None,
+    };
+
+    if batch.status == OnChainBatchStatus::Executed {
+        let response = state
+            .executed_batches
+            .replay(batch.id, fingerprint)
+            .await
+            .ok_or_else(|| ProxyError::BatchReplay {
+                batch_id: batch.id,
+                reason: "batch was already executed on-chain".to_string(),
             })?;
+        info!(
+            batch_id = batch.id,
+            "Returning stored response for executed batch"
+        );
+        return Ok(Json(response));
+    }
 
-            if batch.status != OnChainBatchStatus::Finalized {
-                return Err(ProxyError::InvalidQuery(format!(
-                    "Batch {} is not finalized (status: {:?})",
-                    batch_id, batch.status
-                )));
-            }
+    // Released if execution fails or this request is dropped before the
+    // response is stored
+    let claim = match state.executed_batches.admit(batch.id, fingerprint).await? {
+        Admission::Execute(claim) => claim,
+        Admission::Replay(response) => {
+            info!(
+                batch_id = batch.id,
+                "Returning stored response for replayed batch"
+            );
+            return Ok(Json(*response));
+        }
+    };
+
+    // Tickets are matched to results by position, and withheld results have
+    // no order to hide
+    let mut request = request;
+    if ticket_hashes.is_some() {
+        request.shuffle_results = false;
+    }
 
-            info!(batch_id = batch_id, "On-chain batch verified as finalized");
+    let mut response = state
+        .executor
+        .execute_batch(request)
+        .await?
+        .with_verification_mode(mode);
+
+    if let (Some(store), Some(ticket_hashes)) = (&state.result_store, ticket_hashes) {
+        // Executor results are in query order
+        for (ticket_hash, result) in ticket_hashes.into_iter().zip(response.results.drain(..)) {
+            store.insert(ticket_hash, result).await;
         }
+        response = response.into_ticketed();
+    }
+
+    claim.complete(response.clone());
+
+    if let Some(completer) = &state.completer {
+        let results_hash = decode_results_hash(&response.batch_hash)?;
+        completer.spawn_completion(batch.id, results_hash).await;
     }
 
-    let response = state.executor.execute_batch(request).await?;
     Ok(Json(response))
 }
 
+/// Verify that the queries match a finalized (or already executed) on-chain batch
+fn verify_on_chain(
+    coordinator: &CoordinatorReader,
+    batch_id: &str,
+    queries: &[Query],
+) -> ProxyResult<OnChainBatch> {
+    let batch_id: u64 = batch_id
+        .parse()
+        .map_err(|_| ProxyError::InvalidQuery(format!("Invalid batch_id: {}", batch_id)))?;
+
+    let batch = coordinator.get_batch(batch_id).ok_or_else(|| {
+        ProxyError::InvalidQuery(format!("Batch {} not found on-chain", batch_id))
+    })?;
+
+    if batch.status == OnChainBatchStatus::Pending {
+        return Err(ProxyError::InvalidQuery(format!(
+            "Batch {} is not finalized (status: {:?})",
+            batch_id, batch.status
+        )));
+    }
+
+    verify_query_hashes(queries, &batch)?;
+
+    info!(batch_id = batch_id, status = ?batch.status, "On-chain batch verified");
+    Ok(batch)
+}
+
+/// Collect the ticket hash (the revealed salt) of every query
+fn ticket_hashes(queries: &[Query]) -> ProxyResult<Vec<[u8; 32]>> {
+    queries
+        .iter()
+        .map(|query| match query.salt.as_deref() {
+            Some(salt) => parse_salt(salt),
+            None => Err(ProxyError::InvalidQuery(
+                "Ticketed delivery requires a salt on every query".to_string(),
+            )),
+        })
+        .collect()
+}
+
+/// Decide how a batch will be verified, rejecting it if strict mode forbids it
+fn verification_mode(
+    has_batch_id: bool,
+    has_coordinator: bool,
+    strict: bool,
+) -> ProxyResult<VerificationMode> {
+    let mode = match (has_batch_id, has_coordinator) {
+        (true, true) => VerificationMode::Onchain,
+        (true, false) => VerificationMode::Unverified,
+        (false, _) => VerificationMode::Uncoordinated,
+    };
+
+    if strict && !mode.is_verified() {
+        return Err(ProxyError::CoordinationRequired(match mode {
+            VerificationMode::Unverified => "coordinator unavailable".to_string(),
+            _ => "batch_id is required".to_string(),
+        }));
+    }
+
+    Ok(mode)
+}
+
+/// Decode the hex `batch_hash` of a response into an on-chain results hash
+fn decode_results_hash(batch_hash: &str) -> ProxyResult<[u8; 32]> {
+    hex::decode(batch_hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ProxyError::Internal("Malformed batch hash".to_string()))
+}
+
 #[cfg(test)]
 mod tests {
-    // Integration tests would go here, testing the full HTTP flow
-    // These require a running RPC endpoint, so they're typically run separately
+    use super::*;
+    use crate::enums::RpcMethod;
+    use crate::types::QueryResult;
+
+    // Integration tests for the full HTTP flow require a running RPC endpoint,
+    // so they're typically run separately
+
+    #[test]
+    fn test_results_hash_is_response_batch_hash() {
+        let response = BatchResponse::from_results(
+            vec![QueryResult::success("1".to_string(), serde_json::json!(1))],
+            5,
+        );
+
+        let results_hash = decode_results_hash(&response.batch_hash).unwrap();
+        assert_eq!(hex::encode(results_hash), response.batch_hash);
+    }
+
+    #[test]
+    fn test_verification_mode_permissive() {
+        assert_eq!(
+            verification_mode(true, true, false).unwrap(),
+            VerificationMode::Onchain
+        );
+        assert_eq!(
+            verification_mode(true, false, false).unwrap(),
+            VerificationMode::Unverified
+        );
+        assert_eq!(
+            verification_mode(false, true, false).unwrap(),
+            VerificationMode::Uncoordinated
+        );
+    }
+
+    #[test]
+    fn test_verification_mode_strict_fails_closed() {
+        assert_eq!(
+            verification_mode(true, true, true).unwrap(),
+            VerificationMode::Onchain
+        );
+        assert!(matches!(
+            verification_mode(false, true, true),
+            Err(ProxyError::CoordinationRequired(_))
+        ));
+        assert!(matches!(
+            verification_mode(true, false, true),
+            Err(ProxyError::CoordinationRequired(_))
+        ));
+    }
+
+    #[test]
+    fn test_ticket_hashes_require_salt() {
+        let pubkey = "So11111111111111111111111111111111111111112".to_string();
+        let salted = Query::new("1".to_string(), RpcMethod::GetBalance, pubkey.clone())
+            .with_salt(hex::encode([3u8; 32]));
+        let unsalted = Query::new("2".to_string(), RpcMethod::GetBalance, pubkey);
+
+        assert_eq!(
+            ticket_hashes(std::slice::from_ref(&salted)).unwrap(),
+            vec![[3u8; 32]]
+        );
+        assert!(matches!(
+            ticket_hashes(&[salted, unsalted]),
+            Err(ProxyError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_malformed_batch_hash() {
+        assert!(decode_results_hash("abc").is_err());
+        assert!(decode_results_hash(&"00".repeat(16)).is_err());
+    }
 }
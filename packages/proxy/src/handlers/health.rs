@@ -1,23 +1,66 @@
 //! Health check handler
 
-use crate::coordinator::CoordinatorReader;
+use crate::coordinator::{BatchCompleter, CoordinatorReader};
+use crate::cover::CoverTraffic;
+use crate::crypto::KeyManager;
+use crate::decoy::IntersectionGuard;
 use crate::executor::BatchExecutor;
+use crate::mixer::QueryMixer;
+use crate::store::{ExecutedBatchStore, ResultStore};
 use crate::types::HealthResponse;
+use crate::upstream::ResultConsensus;
 use axum::{extract::State, Json};
 use std::sync::Arc;
 
 /// Shared application state
 pub struct AppState {
-    pub executor: BatchExecutor,
+    pub executor: Arc<BatchExecutor>,
     pub coordinator: Option<CoordinatorReader>,
+    pub completer: Option<BatchCompleter>,
+    /// Reject batches that are not verified against the coordinator
+    pub strict_coordination: bool,
+    /// On-chain batches executed by this proxy
+    pub executed_batches: ExecutedBatchStore,
+    /// Results awaiting ticketed retrieval (ticketed delivery enabled)
+    pub result_store: Option<ResultStore>,
+    /// Proxy encryption keys (key management enabled)
+    pub key_manager: Option<Arc<KeyManager>>,
+    /// Server-side query mixer (`POST /query` enabled)
+    pub mixer: Option<Arc<QueryMixer>>,
+    /// Constant-rate upstream emitter (cover traffic enabled)
+    pub cover_traffic: Option<Arc<CoverTraffic>>,
+    /// Persistent decoy cohorts of recurring targets (cohorts enabled)
+    pub intersection_guard: Option<Arc<IntersectionGuard>>,
+    /// Cross-provider result checks (consensus enabled)
+    pub consensus: Option<Arc<ResultConsensus>>,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State running batches on `executor`, with every optional part disabled
+    pub(crate) fn with_executor(executor: BatchExecutor) -> Self {
+        Self {
+            executor: Arc::new(executor),
+            coordinator: None,
+            completer: None,
+            strict_coordination: false,
+            executed_batches: ExecutedBatchStore::new(),
+            result_store: None,
+            key_manager: None,
+            mixer: None,
+            cover_traffic: None,
+            intersection_guard: None,
+            consensus: None,
+        }
+    }
 }
 
 /// Health check endpoint
 ///
-/// Returns service status and RPC connectivity.
+/// Returns service status, RPC connectivity and the state of every upstream.
 pub async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
-    let rpc_healthy = state.executor.check_health().await;
-    Json(HealthResponse::new(rpc_healthy))
+    let upstreams = state.executor.upstream_health().await;
+    Json(HealthResponse::from_upstreams(upstreams))
 }
 
 #[cfg(test)]
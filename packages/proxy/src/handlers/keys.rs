@@ -0,0 +1,71 @@
+//! Encryption key handler
+
+use crate::crypto::{fingerprint_keys, unix_now};
+use crate::error::{ProxyError, ProxyResult};
+use crate::handlers::AppState;
+use crate::types::KeysResponse;
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+/// List the proxy's active encryption keys
+///
+/// Clients should compare `fingerprint` with the one published in the
+/// coordinator state before encrypting to `currentKey`.
+pub async fn get_keys(State(state): State<Arc<AppState>>) -> ProxyResult<Json<KeysResponse>> {
+    let key_manager = state
+        .key_manager
+        .as_ref()
+        .ok_or_else(|| ProxyError::NotConfigured("encryption keys".to_string()))?;
+
+    // Fingerprint the same snapshot that is served, in case a rotation lands
+    let keys = key_manager.keys();
+    Ok(Json(KeysResponse {
+        fingerprint: hex::encode(fingerprint_keys(&keys)?),
+        current_key: key_manager.current_key(unix_now()).public_key,
+        keys,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyManager;
+    use crate::executor::BatchExecutor;
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    fn state(key_manager: Option<Arc<KeyManager>>) -> Arc<AppState> {
+        let mut state = AppState::with_executor(BatchExecutor::new("http://127.0.0.1:1"));
+        state.key_manager = key_manager;
+        Arc::new(state)
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_matches_active_keys() {
+        let dir = std::env::temp_dir().join(format!(
+            "privacy-rpc-keys-handler-{}-{}",
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+        let manager = Arc::new(KeyManager::load_or_generate(&dir, Some(3600)).unwrap());
+
+        let Json(response) = get_keys(State(state(Some(Arc::clone(&manager)))))
+            .await
+            .unwrap();
+
+        assert_eq!(response.fingerprint, hex::encode(manager.fingerprint()));
+        assert_eq!(response.keys, manager.keys());
+        assert_eq!(
+            response.current_key,
+            manager.current_key(unix_now()).public_key
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_not_found_without_key_management() {
+        let error = get_keys(State(state(None))).await.unwrap_err();
+
+        assert!(matches!(error, ProxyError::NotConfigured(_)));
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+}
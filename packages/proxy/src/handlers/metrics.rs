@@ -0,0 +1,32 @@
+//! Upstream and traffic-shaping metrics handler
+
+use crate::handlers::AppState;
+use crate::types::MetricsResponse;
+use axum::{extract::State, Json};
+use std::sync::Arc;
+
+/// Report the upstream call slot usage and the counters of the cover traffic
+/// emitter, intersection guard and result consensus
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> Json<MetricsResponse> {
+    Json(MetricsResponse {
+        upstream: state.executor.upstream_metrics(),
+        cover_traffic: state
+            .cover_traffic
+            .as_ref()
+            .map(|emitter| emitter.metrics()),
+        cohorts: state
+            .intersection_guard
+            .as_ref()
+            .map(|guard| guard.metrics()),
+        consensus: state
+            .consensus
+            .as_ref()
+            .map(|consensus| consensus.metrics()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    // Integration tests would go here, testing the full HTTP flow
+    // These require a running RPC endpoint, so they're typically run separately
+}
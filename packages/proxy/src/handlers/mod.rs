@@ -4,6 +4,14 @@
 
 mod execute_batch;
 mod health;
+mod keys;
+mod metrics;
+mod results;
+mod submit_query;
 
 pub use execute_batch::execute_batch;
 pub use health::{health_check, AppState};
+pub use keys::get_keys;
+pub use metrics::get_metrics;
+pub use results::get_result;
+pub use submit_query::submit_query;
@@ -0,0 +1,90 @@
+//! Ticketed result retrieval handler
+
+use crate::error::{ProxyError, ProxyResult};
+use crate::handlers::AppState;
+use crate::store::parse_ticket;
+use crate::types::QueryResult;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+/// Retrieve the result of a query by its ticket
+///
+/// Each result is returned at most once. Unknown, expired and already
+/// retrieved tickets all yield the same error.
+pub async fn get_result(
+    State(state): State<Arc<AppState>>,
+    Path(ticket): Path<String>,
+) -> ProxyResult<Json<QueryResult>> {
+    let store = state
+        .result_store
+        .as_ref()
+        .ok_or(ProxyError::ResultNotFound)?;
+    let ticket = parse_ticket(&ticket)?;
+
+    store
+        .take(&ticket)
+        .await
+        .map(Json)
+        .ok_or(ProxyError::ResultNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::BatchExecutor;
+    use crate::store::{ticket_hash, ResultStore, TICKET_LEN};
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    fn state(result_store: Option<ResultStore>) -> Arc<AppState> {
+        let mut state = AppState::with_executor(BatchExecutor::new("http://127.0.0.1:1"));
+        state.result_store = result_store;
+        Arc::new(state)
+    }
+
+    #[tokio::test]
+    async fn test_result_is_served_once() {
+        let ticket = [5u8; TICKET_LEN];
+        let store = ResultStore::default();
+        store
+            .insert(
+                ticket_hash(&ticket),
+                QueryResult::success("1".to_string(), serde_json::json!(42)),
+            )
+            .await;
+        let state = state(Some(store));
+
+        let Json(result) = get_result(State(Arc::clone(&state)), Path(hex::encode(ticket)))
+            .await
+            .unwrap();
+        assert_eq!(result.data, Some(serde_json::json!(42)));
+
+        let error = get_result(State(state), Path(hex::encode(ticket)))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProxyError::ResultNotFound));
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_results_are_not_found_without_ticketed_delivery() {
+        let ticket = hex::encode([5u8; TICKET_LEN]);
+
+        let error = get_result(State(state(None)), Path(ticket))
+            .await
+            .unwrap_err();
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_malformed_ticket() {
+        let state = state(Some(ResultStore::default()));
+
+        let error = get_result(State(state), Path("zz".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProxyError::InvalidQuery(_)));
+    }
+}
@@ -0,0 +1,60 @@
+//! Mixed query submission handler
+
+use crate::error::{ProxyError, ProxyResult};
+use crate::handlers::AppState;
+use crate::types::{Query, QueryResult};
+use axum::{
+    extract::{ConnectInfo, State},
+    Json,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Submit a single query for server-side mixing
+///
+/// The query is pooled with other clients' queries and executed once k
+/// distinct client addresses have contributed. The response holds only this
+/// query's result. If the mixer's deadline passes first, its deadline policy
+/// decides whether the batch is padded, held longer or rejected.
+pub async fn submit_query(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(connection): ConnectInfo<SocketAddr>,
+    Json(query): Json<Query>,
+) -> ProxyResult<Json<QueryResult>> {
+    let mixer = state
+        .mixer
+        .as_ref()
+        .ok_or_else(|| ProxyError::NotConfigured("query mixing".to_string()))?;
+
+    mixer.submit(query, connection.ip()).await.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::RpcMethod;
+    use crate::executor::BatchExecutor;
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    #[tokio::test]
+    async fn test_rejects_when_mixer_is_disabled() {
+        let state = Arc::new(AppState::with_executor(BatchExecutor::new(
+            "http://127.0.0.1:1",
+        )));
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "11111111111111111111111111111111".to_string(),
+        );
+
+        let error = submit_query(
+            State(state),
+            ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))),
+            Json(query),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, ProxyError::NotConfigured(_)));
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+}
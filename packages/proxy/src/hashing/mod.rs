@@ -0,0 +1,13 @@
+//! Canonical query hashing
+//!
+//! Defines what a `[u8; 32]` query hash means across the proxy, the SDK and
+//! the on-chain coordinator. The encoding is specified in
+//! `test-vectors/README.md` and pinned by the shared test vectors in
+//! `test-vectors/query-hash-v1.json`.
+
+mod query_hash;
+
+pub use query_hash::{
+    canonical_encoding, commitment_hash, parse_salt, query_hash, salted_query_hash,
+    QUERY_HASH_DOMAIN, QUERY_HASH_VERSION, SALT_LEN,
+};
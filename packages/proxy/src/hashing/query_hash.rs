@@ -0,0 +1,396 @@
+//! Canonical query encoding (version 1)
+//!
+//! ```text
+//! encoding = DOMAIN || VERSION || method_tag || commitment_tag || params
+//! hash     = SHA-256(encoding)
+//! ```
+//!
+//! `params` depends on the method:
+//!
+//! | Method                  | Tag    | Params                                  |
+//! |-------------------------|--------|-----------------------------------------|
+//! | `getBalance`            | `0x01` | 32-byte pubkey                          |
+//! | `getAccountInfo`        | `0x02` | 32-byte pubkey                          |
+//! | `getTransaction`        | `0x03` | 64-byte signature                       |
+//! | `getTokenAccountBalance` | `0x04` | 32-byte pubkey                          |
+//! | `getBlockHeight`        | `0x05` | empty                                   |
+//! | `getMultipleAccounts`   | `0x06` | `u16` LE count, then 32-byte pubkeys    |
+//!
+//! Commitment tags are `processed = 0x00`, `confirmed = 0x01` and
+//! `finalized = 0x02`. A missing commitment encodes as `confirmed`, the
+//! default every executor applies (see `Query::effective_commitment`), so
+//! both spellings hash identically.
+//!
+//! For commit-reveal, clients commit `SHA-256(salt || encoding)` with a
+//! random 32-byte salt and reveal the salt only to the proxy. Without the
+//! salt, a dictionary of popular accounts cannot reverse the on-chain hash,
+//! so unsalted commitments are never accepted.
+//!
+//! A query whose result is encrypted also commits to the client's X25519 key,
+//! `SHA-256(salt || encoding || encryption_key)`, so whoever posts the batch
+//! cannot swap in a key of their own.
+
+use crate::crypto::parse_public_key;
+use crate::enums::{CommitmentLevel, RpcMethod, DEFAULT_COMMITMENT};
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::Query;
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+
+/// Domain separation prefix for query hashes
+pub const QUERY_HASH_DOMAIN: &[u8] = b"privacy-rpc/query-hash";
+
+/// Current version of the canonical encoding
+pub const QUERY_HASH_VERSION: u8 = 1;
+
+/// Length in bytes of a commit-reveal salt
+pub const SALT_LEN: usize = 32;
+
+fn method_tag(method: RpcMethod) -> u8 {
+    match method {
+        RpcMethod::GetBalance => 0x01,
+        RpcMethod::GetAccountInfo => 0x02,
+        RpcMethod::GetTransaction => 0x03,
+        RpcMethod::GetTokenAccountBalance => 0x04,
+        RpcMethod::GetBlockHeight => 0x05,
+        RpcMethod::GetMultipleAccounts => 0x06,
+    }
+}
+
+fn commitment_tag(commitment: CommitmentLevel) -> u8 {
+    match commitment {
+        CommitmentLevel::Processed => 0x00,
+        CommitmentLevel::Confirmed => 0x01,
+        CommitmentLevel::Finalized => 0x02,
+    }
+}
+
+fn parse_commitment(query: &Query) -> ProxyResult<CommitmentLevel> {
+    match query.commitment.as_deref() {
+        None => Ok(DEFAULT_COMMITMENT),
+        Some(s) => CommitmentLevel::from_str(s)
+            .ok_or_else(|| ProxyError::InvalidQuery(format!("Unsupported commitment '{}'", s))),
+    }
+}
+
+fn parse_pubkey(s: &str) -> ProxyResult<Pubkey> {
+    Pubkey::from_str(s).map_err(|e| ProxyError::InvalidPubkey(format!("'{}': {}", s, e)))
+}
+
+fn primary_pubkey(query: &Query) -> ProxyResult<Pubkey> {
+    let pubkey = query
+        .get_primary_param()
+        .ok_or_else(|| ProxyError::InvalidQuery("Missing pubkey parameter".to_string()))?;
+    parse_pubkey(&pubkey)
+}
+
+fn multiple_pubkeys(query: &Query) -> ProxyResult<Vec<Pubkey>> {
+    let params = query
+        .params
+        .as_ref()
+        .ok_or_else(|| ProxyError::InvalidQuery("Missing pubkeys parameter".to_string()))?;
+
+    let pubkeys = if let Some(s) = params.as_str() {
+        vec![parse_pubkey(s)?]
+    } else if let Some(arr) = params.as_array() {
+        arr.iter()
+            .map(|v| {
+                v.as_str()
+                    .ok_or_else(|| {
+                        ProxyError::InvalidQuery("Expected array of pubkey strings".to_string())
+                    })
+                    .and_then(parse_pubkey)
+            })
+            .collect::<ProxyResult<Vec<_>>>()?
+    } else {
+        return Err(ProxyError::InvalidQuery(
+            "Expected array of pubkey strings".to_string(),
+        ));
+    };
+
+    if pubkeys.is_empty() || pubkeys.len() > u16::MAX as usize {
+        return Err(ProxyError::InvalidQuery(format!(
+            "Invalid pubkey count: {}",
+            pubkeys.len()
+        )));
+    }
+    Ok(pubkeys)
+}
+
+/// Encode a query into its canonical byte representation
+///
+/// The query `id` is deliberately excluded: it is a client-side correlation
+/// handle, not part of what is being asked.
+pub fn canonical_encoding(query: &Query) -> ProxyResult<Vec<u8>> {
+    let mut out = Vec::with_capacity(QUERY_HASH_DOMAIN.len() + 3 + 64);
+    out.extend_from_slice(QUERY_HASH_DOMAIN);
+    out.push(QUERY_HASH_VERSION);
+    out.push(method_tag(query.method));
+    out.push(commitment_tag(parse_commitment(query)?));
+
+    match query.method {
+        RpcMethod::GetBalance | RpcMethod::GetAccountInfo | RpcMethod::GetTokenAccountBalance => {
+            out.extend_from_slice(primary_pubkey(query)?.as_ref());
+        }
+        RpcMethod::GetTransaction => {
+            let signature = query.get_primary_param().ok_or_else(|| {
+                ProxyError::InvalidQuery("Missing transaction signature".to_string())
+            })?;
+            let signature = Signature::from_str(&signature)
+                .map_err(|e| ProxyError::InvalidQuery(format!("Invalid signature: {}", e)))?;
+            out.extend_from_slice(signature.as_ref());
+        }
+        RpcMethod::GetBlockHeight => {}
+        RpcMethod::GetMultipleAccounts => {
+            let pubkeys = multiple_pubkeys(query)?;
+            out.extend_from_slice(&(pubkeys.len() as u16).to_le_bytes());
+            for pubkey in &pubkeys {
+                out.extend_from_slice(pubkey.as_ref());
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compute the canonical SHA-256 hash of a query
+pub fn query_hash(query: &Query) -> ProxyResult<[u8; 32]> {
+    let encoding = canonical_encoding(query)?;
+    Ok(Sha256::digest(&encoding).into())
+}
+
+/// Compute the salted commitment `SHA-256(salt || encoding)` of a query,
+/// followed by its encryption key if it has one
+pub fn salted_query_hash(query: &Query, salt: &[u8; SALT_LEN]) -> ProxyResult<[u8; 32]> {
+    let encoding = canonical_encoding(query)?;
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(&encoding);
+    if let Some(key) = query.encryption_key.as_deref() {
+        hasher.update(parse_public_key(key)?.as_bytes());
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Parse a hex-encoded commit-reveal salt
+pub fn parse_salt(salt: &str) -> ProxyResult<[u8; SALT_LEN]> {
+    let bytes = hex::decode(salt)
+        .map_err(|e| ProxyError::InvalidQuery(format!("Invalid salt encoding: {}", e)))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        ProxyError::InvalidQuery(format!(
+            "Invalid salt length: expected {} bytes, got {}",
+            SALT_LEN,
+            bytes.len()
+        ))
+    })
+}
+
+/// Compute the hash a query was committed under on-chain
+///
+/// Every commitment is salted: a query that reveals no salt is rejected,
+/// since its plain hash could be reversed with a dictionary.
+pub fn commitment_hash(query: &Query) -> ProxyResult<[u8; 32]> {
+    let salt = query
+        .salt
+        .as_deref()
+        .ok_or_else(|| ProxyError::InvalidQuery("Missing commit-reveal salt".to_string()))?;
+    salted_query_hash(query, &parse_salt(salt)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const VECTORS: &str = include_str!("../../../../test-vectors/query-hash-v1.json");
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct VectorFile {
+        version: u8,
+        vectors: Vec<Vector>,
+        salted_vectors: Vec<SaltedVector>,
+    }
+
+    #[derive(Deserialize)]
+    struct SaltedVector {
+        description: String,
+        query: Query,
+        hash: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Vector {
+        description: String,
+        query: Query,
+        encoding: String,
+        hash: String,
+    }
+
+    #[test]
+    fn test_published_vectors() {
+        let file: VectorFile = serde_json::from_str(VECTORS).unwrap();
+        assert_eq!(file.version, QUERY_HASH_VERSION);
+
+        for vector in file.vectors {
+            let encoding = canonical_encoding(&vector.query).unwrap();
+            assert_eq!(hex::encode(&encoding), vector.encoding, "{}", vector.description);
+
+            let hash = query_hash(&vector.query).unwrap();
+            assert_eq!(hex::encode(hash), vector.hash, "{}", vector.description);
+        }
+    }
+
+    #[test]
+    fn test_published_salted_vectors() {
+        let file: VectorFile = serde_json::from_str(VECTORS).unwrap();
+        assert!(!file.salted_vectors.is_empty());
+
+        for vector in file.salted_vectors {
+            let hash = commitment_hash(&vector.query).unwrap();
+            assert_eq!(hex::encode(hash), vector.hash, "{}", vector.description);
+        }
+    }
+
+    #[test]
+    fn test_salt_changes_commitment() {
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "So11111111111111111111111111111111111111112".to_string(),
+        );
+
+        let plain = query_hash(&query).unwrap();
+        let salted_a = salted_query_hash(&query, &[1u8; SALT_LEN]).unwrap();
+        let salted_b = salted_query_hash(&query, &[2u8; SALT_LEN]).unwrap();
+
+        assert_ne!(plain, salted_a);
+        assert_ne!(salted_a, salted_b);
+    }
+
+    #[test]
+    fn test_commitment_hash_uses_revealed_salt() {
+        let salt = [7u8; SALT_LEN];
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "So11111111111111111111111111111111111111112".to_string(),
+        );
+        let revealed = query.clone().with_salt(hex::encode(salt));
+
+        assert_eq!(
+            commitment_hash(&revealed).unwrap(),
+            salted_query_hash(&query, &salt).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_commitment_covers_encryption_key() {
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "So11111111111111111111111111111111111111112".to_string(),
+        )
+        .with_salt(hex::encode([7u8; SALT_LEN]));
+        let client = query.clone().with_encryption_key(hex::encode([9u8; 32]));
+        let swapped = query.clone().with_encryption_key(hex::encode([10u8; 32]));
+
+        let committed = commitment_hash(&client).unwrap();
+        assert_ne!(committed, commitment_hash(&query).unwrap());
+        assert_ne!(committed, commitment_hash(&swapped).unwrap());
+        assert!(matches!(
+            commitment_hash(&query.with_encryption_key("zz".to_string())),
+            Err(ProxyError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_commitment_hash_requires_a_salt() {
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "So11111111111111111111111111111111111111112".to_string(),
+        );
+
+        assert!(matches!(
+            commitment_hash(&query),
+            Err(ProxyError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_malformed_salt() {
+        assert!(matches!(parse_salt("zz"), Err(ProxyError::InvalidQuery(_))));
+        assert!(matches!(
+            parse_salt(&hex::encode([0u8; 16])),
+            Err(ProxyError::InvalidQuery(_))
+        ));
+        assert!(parse_salt(&hex::encode([0u8; SALT_LEN])).is_ok());
+    }
+
+    #[test]
+    fn test_vectors_cover_every_method() {
+        let file: VectorFile = serde_json::from_str(VECTORS).unwrap();
+        for method in RpcMethod::all() {
+            assert!(
+                file.vectors.iter().any(|v| v.query.method == *method),
+                "no vector for {}",
+                method
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_commitment_matches_confirmed() {
+        let pubkey = "So11111111111111111111111111111111111111112".to_string();
+        let implicit = Query::new("1".to_string(), RpcMethod::GetBalance, pubkey.clone());
+        let explicit = implicit.clone().with_commitment("confirmed".to_string());
+
+        assert_eq!(query_hash(&implicit).unwrap(), query_hash(&explicit).unwrap());
+    }
+
+    #[test]
+    fn test_methods_are_domain_separated() {
+        let pubkey = "So11111111111111111111111111111111111111112".to_string();
+        let balance = Query::new("1".to_string(), RpcMethod::GetBalance, pubkey.clone());
+        let account = Query::new("1".to_string(), RpcMethod::GetAccountInfo, pubkey);
+
+        assert_ne!(query_hash(&balance).unwrap(), query_hash(&account).unwrap());
+    }
+
+    #[test]
+    fn test_params_are_hashed() {
+        let a = Query::with_params(
+            "1".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            serde_json::json!(["So11111111111111111111111111111111111111112"]),
+        );
+        let b = Query::with_params(
+            "1".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            serde_json::json!(["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"]),
+        );
+
+        assert_ne!(query_hash(&a).unwrap(), query_hash(&b).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_unknown_commitment() {
+        let query = Query::new(
+            "1".to_string(),
+            RpcMethod::GetBalance,
+            "So11111111111111111111111111111111111111112".to_string(),
+        )
+        .with_commitment("max".to_string());
+
+        assert!(matches!(query_hash(&query), Err(ProxyError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_rejects_invalid_pubkey() {
+        let query = Query::new("1".to_string(), RpcMethod::GetBalance, "pubkey1".to_string());
+        assert!(matches!(query_hash(&query), Err(ProxyError::InvalidPubkey(_))));
+    }
+}
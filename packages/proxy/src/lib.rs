@@ -0,0 +1,21 @@
+//! Privacy RPC Proxy
+//!
+//! Library crate for the privacy-preserving RPC proxy. The binary in
+//! `main.rs` only wires configuration and logging; everything else lives here
+//! so it can be exercised by tests and reused by other tools.
+
+pub mod coordinator;
+pub mod cover;
+pub mod crypto;
+pub mod decoy;
+pub mod enums;
+pub mod error;
+pub mod executor;
+pub mod handlers;
+pub mod hashing;
+pub mod mixer;
+pub mod scheduler;
+pub mod server;
+pub mod store;
+pub mod types;
+pub mod upstream;
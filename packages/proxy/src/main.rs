@@ -3,17 +3,27 @@
 //! This is the main entry point for the privacy-preserving RPC proxy.
 //! It initializes logging, loads configuration, and starts the HTTP server.
 
-mod coordinator;
-mod enums;
-mod error;
-mod executor;
-mod handlers;
-mod server;
-mod types;
-
+use privacy_rpc_proxy::crypto::DEFAULT_KEY_ROTATION_SECS;
+use privacy_rpc_proxy::enums::{
+    DeadlinePolicy, QuorumPolicy, RpcMethod, SamplingDistribution, ShardPolicy,
+};
+use privacy_rpc_proxy::executor::MAX_MULTIPLE_ACCOUNTS;
+use privacy_rpc_proxy::server;
+use privacy_rpc_proxy::store::DEFAULT_RESULT_TTL_SECS;
+use privacy_rpc_proxy::types::{
+    CohortConfig, ConsensusConfig, CoverTrafficConfig, DecoyConfig, FailoverConfig, MixerConfig,
+    ProxyConfig, ShardConfig, DEFAULT_BATCH_TIMEOUT_MS, DEFAULT_CIRCUIT_COOLDOWN_MS,
+    DEFAULT_CIRCUIT_FAILURE_THRESHOLD, DEFAULT_COHORT_SIZE, DEFAULT_CONSENSUS_PROVIDERS,
+    DEFAULT_COVER_QUEUE_SIZE, DEFAULT_DECOY_ZIPF_EXPONENT, DEFAULT_FAILOVER_ATTEMPTS,
+    DEFAULT_FAILOVER_BACKOFF_MS, DEFAULT_FAILOVER_MAX_BACKOFF_MS, DEFAULT_K_ANONYMITY,
+    DEFAULT_MAX_COHORT_DECOYS, DEFAULT_MAX_IN_FLIGHT_CALLS, DEFAULT_MAX_QUERY_ACCOUNTS,
+    DEFAULT_MIXER_EXTENSION_MS, DEFAULT_MIXER_MAX_WAIT_MS, DEFAULT_PORT, DEFAULT_QUERY_TIMEOUT_MS,
+    DEFAULT_SHARD_MAX_FRACTION, DEFAULT_SKETCH_DECAY_BATCHES, DEFAULT_UPSTREAM_BATCH_MAX_CALLS,
+    MAX_BATCH_SIZE,
+};
+use privacy_rpc_proxy::upstream::sanitize_rpc_url;
 use std::env;
 use tracing::info;
-use types::{ProxyConfig, DEFAULT_PORT};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -35,6 +45,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .parse()
         .expect("PORT must be a valid number");
 
+    let k_anonymity: usize = env::var("K_ANONYMITY")
+        .unwrap_or_else(|_| DEFAULT_K_ANONYMITY.to_string())
+        .parse()
+        .expect("K_ANONYMITY must be a valid number");
+
+    let max_query_accounts: usize = env::var("MAX_QUERY_ACCOUNTS")
+        .unwrap_or_else(|_| DEFAULT_MAX_QUERY_ACCOUNTS.to_string())
+        .parse()
+        .expect("MAX_QUERY_ACCOUNTS must be a valid number");
+
+    let max_in_flight_calls: usize = env::var("MAX_IN_FLIGHT_CALLS")
+        .unwrap_or_else(|_| DEFAULT_MAX_IN_FLIGHT_CALLS.to_string())
+        .parse()
+        .expect("MAX_IN_FLIGHT_CALLS must be a valid number");
+
+    let count_distinct_targets = env::var("K_ANONYMITY_DISTINCT_TARGETS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+
     let enable_poller = env::var("ENABLE_POLLER")
         .map(|v| v == "true" || v == "1")
         .unwrap_or(false);
@@ -44,10 +73,275 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .parse()
         .unwrap_or(5000);
 
-    let mut config = ProxyConfig::new(rpc_url.clone()).with_port(port);
+    let mut config = ProxyConfig::new(rpc_url.clone())
+        .with_port(port)
+        .with_k_anonymity(k_anonymity)
+        .with_distinct_targets(count_distinct_targets)
+        .with_max_query_accounts(max_query_accounts)
+        .with_max_in_flight_calls(max_in_flight_calls);
     if enable_poller {
         config = config.with_poller(poll_interval_ms);
     }
+    let strict_coordination = env::var("STRICT_COORDINATION")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if strict_coordination {
+        config = config.with_strict_coordination();
+    }
+    let ticketed_results = env::var("TICKETED_RESULTS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if ticketed_results {
+        let result_ttl_secs: u64 = env::var("RESULT_TTL_SECS")
+            .unwrap_or_else(|_| DEFAULT_RESULT_TTL_SECS.to_string())
+            .parse()
+            .expect("RESULT_TTL_SECS must be a valid number");
+        config = config.with_ticketed_results(result_ttl_secs);
+    }
+    if let Ok(path) = env::var("EXECUTOR_KEYPAIR_PATH") {
+        config = config.with_executor_keypair(path);
+    }
+    if let Ok(dir) = env::var("ENCRYPTION_KEY_DIR") {
+        let key_rotation_secs: u64 = env::var("KEY_ROTATION_SECS")
+            .unwrap_or_else(|_| DEFAULT_KEY_ROTATION_SECS.to_string())
+            .parse()
+            .expect("KEY_ROTATION_SECS must be a valid number");
+        config = config.with_encryption_keys(dir, Some(key_rotation_secs).filter(|secs| *secs > 0));
+    }
+
+    let enable_mixer = env::var("ENABLE_MIXER")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if enable_mixer {
+        let max_wait_ms: u64 = env::var("MIXER_MAX_WAIT_MS")
+            .unwrap_or_else(|_| DEFAULT_MIXER_MAX_WAIT_MS.to_string())
+            .parse()
+            .expect("MIXER_MAX_WAIT_MS must be a valid number");
+        let deadline_policy = env::var("MIXER_DEADLINE_POLICY")
+            .map(|v| {
+                DeadlinePolicy::from_str(&v)
+                    .expect("MIXER_DEADLINE_POLICY must be one of pad, wait, reject")
+            })
+            .unwrap_or_default();
+        let max_extension_ms: u64 = env::var("MIXER_MAX_EXTENSION_MS")
+            .unwrap_or_else(|_| DEFAULT_MIXER_EXTENSION_MS.to_string())
+            .parse()
+            .expect("MIXER_MAX_EXTENSION_MS must be a valid number");
+        config = config.with_mixer(
+            MixerConfig::default()
+                .with_max_wait(max_wait_ms)
+                .with_deadline_policy(deadline_policy)
+                .with_max_extension(max_extension_ms),
+        );
+    }
+
+    if let Ok(corpus_path) = env::var("DECOY_CORPUS_PATH") {
+        let target_size: usize = env::var("DECOY_TARGET_SIZE")
+            .unwrap_or_else(|_| k_anonymity.to_string())
+            .parse()
+            .expect("DECOY_TARGET_SIZE must be a valid number");
+        let min_decoys: usize = env::var("DECOY_MIN")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse()
+            .expect("DECOY_MIN must be a valid number");
+        let max_decoys: usize = env::var("DECOY_MAX")
+            .unwrap_or_else(|_| MAX_BATCH_SIZE.to_string())
+            .parse()
+            .expect("DECOY_MAX must be a valid number");
+        let distribution = env::var("DECOY_DISTRIBUTION")
+            .map(|v| {
+                SamplingDistribution::from_str(&v)
+                    .expect("DECOY_DISTRIBUTION must be one of uniform, weighted, zipf")
+            })
+            .unwrap_or_default();
+        let zipf_exponent: f64 = env::var("DECOY_ZIPF_EXPONENT")
+            .unwrap_or_else(|_| DEFAULT_DECOY_ZIPF_EXPONENT.to_string())
+            .parse()
+            .expect("DECOY_ZIPF_EXPONENT must be a valid number");
+        let mut decoys = DecoyConfig::new(corpus_path, target_size)
+            .with_decoy_bounds(min_decoys, max_decoys)
+            .with_distribution(distribution)
+            .with_zipf_exponent(zipf_exponent);
+        if let Ok(mix) = env::var("DECOY_METHOD_MIX") {
+            decoys = decoys.with_method_mix(
+                DecoyConfig::parse_method_mix(&mix)
+                    .expect("DECOY_METHOD_MIX must look like getBalance:2,getAccountInfo:1"),
+            );
+        }
+        config = config.with_decoys(decoys);
+    }
+    if let Ok(threshold) = env::var("DECOY_COHORT_THRESHOLD") {
+        let threshold: u32 = threshold
+            .parse()
+            .expect("DECOY_COHORT_THRESHOLD must be a valid number");
+        let cohort_size: usize = env::var("DECOY_COHORT_SIZE")
+            .unwrap_or_else(|_| DEFAULT_COHORT_SIZE.to_string())
+            .parse()
+            .expect("DECOY_COHORT_SIZE must be a valid number");
+        let max_cohort_decoys: usize = env::var("DECOY_COHORT_MAX")
+            .unwrap_or_else(|_| DEFAULT_MAX_COHORT_DECOYS.to_string())
+            .parse()
+            .expect("DECOY_COHORT_MAX must be a valid number");
+        let decay_batches: u64 = env::var("DECOY_COHORT_DECAY_BATCHES")
+            .unwrap_or_else(|_| DEFAULT_SKETCH_DECAY_BATCHES.to_string())
+            .parse()
+            .expect("DECOY_COHORT_DECAY_BATCHES must be a valid number");
+        let mut cohorts = CohortConfig::new(threshold, cohort_size)
+            .with_max_cohort_decoys(max_cohort_decoys)
+            .with_decay_batches(decay_batches);
+        if let Ok(key_path) = env::var("DECOY_COHORT_KEY_PATH") {
+            cohorts = cohorts.with_key_path(key_path);
+        }
+        config = config.with_cohorts(cohorts);
+    }
+    if let Ok(buckets) = env::var("BATCH_SIZE_BUCKETS") {
+        let buckets = buckets
+            .split(',')
+            .map(|bucket| bucket.trim().parse())
+            .collect::<Result<Vec<usize>, _>>()
+            .expect("BATCH_SIZE_BUCKETS must be a comma-separated list of numbers");
+        config = config.with_batch_buckets(buckets);
+    }
+    let dispatch_window_ms: u64 = env::var("DISPATCH_WINDOW_MS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("DISPATCH_WINDOW_MS must be a valid number");
+    config = config.with_dispatch_window(dispatch_window_ms);
+    let query_timeout_ms: u64 = env::var("QUERY_TIMEOUT_MS")
+        .unwrap_or_else(|_| DEFAULT_QUERY_TIMEOUT_MS.to_string())
+        .parse()
+        .expect("QUERY_TIMEOUT_MS must be a valid number");
+    let batch_timeout_ms: u64 = env::var("BATCH_TIMEOUT_MS")
+        .unwrap_or_else(|_| DEFAULT_BATCH_TIMEOUT_MS.to_string())
+        .parse()
+        .expect("BATCH_TIMEOUT_MS must be a valid number");
+    config = config
+        .with_query_timeout(query_timeout_ms)
+        .with_batch_timeout(batch_timeout_ms);
+    if let Ok(rate) = env::var("COVER_TRAFFIC_RATE") {
+        let calls_per_sec: u32 = rate
+            .parse()
+            .expect("COVER_TRAFFIC_RATE must be a valid number");
+        let max_queue: usize = env::var("COVER_TRAFFIC_MAX_QUEUE")
+            .unwrap_or_else(|_| DEFAULT_COVER_QUEUE_SIZE.to_string())
+            .parse()
+            .expect("COVER_TRAFFIC_MAX_QUEUE must be a valid number");
+        let mut cover_traffic = CoverTrafficConfig::new(calls_per_sec).with_max_queue(max_queue);
+        if let Ok(budget) = env::var("COVER_TRAFFIC_MAX_BYTES_PER_SEC") {
+            cover_traffic = cover_traffic.with_max_bytes_per_sec(
+                budget
+                    .parse()
+                    .expect("COVER_TRAFFIC_MAX_BYTES_PER_SEC must be a valid number"),
+            );
+        }
+        config = config.with_cover_traffic(cover_traffic);
+    }
+    if let Ok(upstreams) = env::var("UPSTREAM_RPC_URLS") {
+        config = config.with_upstreams(
+            upstreams
+                .split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect(),
+        );
+    }
+    let max_attempts: u32 = env::var("FAILOVER_MAX_ATTEMPTS")
+        .unwrap_or_else(|_| DEFAULT_FAILOVER_ATTEMPTS.to_string())
+        .parse()
+        .expect("FAILOVER_MAX_ATTEMPTS must be a valid number");
+    let backoff_ms: u64 = env::var("FAILOVER_BACKOFF_MS")
+        .unwrap_or_else(|_| DEFAULT_FAILOVER_BACKOFF_MS.to_string())
+        .parse()
+        .expect("FAILOVER_BACKOFF_MS must be a valid number");
+    let failure_threshold: u32 = env::var("CIRCUIT_FAILURE_THRESHOLD")
+        .unwrap_or_else(|_| DEFAULT_CIRCUIT_FAILURE_THRESHOLD.to_string())
+        .parse()
+        .expect("CIRCUIT_FAILURE_THRESHOLD must be a valid number");
+    let cooldown_ms: u64 = env::var("CIRCUIT_COOLDOWN_MS")
+        .unwrap_or_else(|_| DEFAULT_CIRCUIT_COOLDOWN_MS.to_string())
+        .parse()
+        .expect("CIRCUIT_COOLDOWN_MS must be a valid number");
+    let mut failover = FailoverConfig::default()
+        .with_max_attempts(max_attempts)
+        .with_backoff(backoff_ms, DEFAULT_FAILOVER_MAX_BACKOFF_MS.max(backoff_ms))
+        .with_circuit_breaker(failure_threshold, cooldown_ms);
+    if let Ok(budgets) = env::var("FAILOVER_METHOD_ATTEMPTS") {
+        let budgets = FailoverConfig::parse_method_attempts(&budgets)
+            .expect("FAILOVER_METHOD_ATTEMPTS must look like getTransaction:5,getBlockHeight:1");
+        for (method, attempts) in budgets {
+            failover = failover.with_method_attempts(method, attempts);
+        }
+    }
+    config = config.with_failover(failover);
+    if let Ok(policy) = env::var("SHARD_POLICY") {
+        let policy = ShardPolicy::from_str(&policy)
+            .expect("SHARD_POLICY must be one of random, round-robin, keyed");
+        let max_fraction: f64 = env::var("SHARD_MAX_FRACTION")
+            .unwrap_or_else(|_| DEFAULT_SHARD_MAX_FRACTION.to_string())
+            .parse()
+            .expect("SHARD_MAX_FRACTION must be a valid number");
+        config = config.with_shards(
+            ShardConfig::default()
+                .with_policy(policy)
+                .with_max_fraction(max_fraction),
+        );
+    }
+    let consensus_methods = env::var("CONSENSUS_METHODS").ok();
+    let consensus_rate = env::var("CONSENSUS_SAMPLE_RATE").ok();
+    if consensus_methods.is_some() || consensus_rate.is_some() {
+        let sample_rate: f64 = consensus_rate
+            .map(|rate| {
+                rate.parse()
+                    .expect("CONSENSUS_SAMPLE_RATE must be a valid number")
+            })
+            .unwrap_or(0.0);
+        let methods = consensus_methods
+            .iter()
+            .flat_map(|methods| methods.split(','))
+            .map(str::trim)
+            .filter(|method| !method.is_empty())
+            .map(|method| {
+                RpcMethod::from_str(method)
+                    .expect("CONSENSUS_METHODS must list RPC methods, e.g. getAccountInfo")
+            })
+            .collect();
+        let providers: usize = env::var("CONSENSUS_PROVIDERS")
+            .unwrap_or_else(|_| DEFAULT_CONSENSUS_PROVIDERS.to_string())
+            .parse()
+            .expect("CONSENSUS_PROVIDERS must be a valid number");
+        let policy = env::var("CONSENSUS_POLICY")
+            .map(|v| {
+                QuorumPolicy::from_str(&v)
+                    .expect("CONSENSUS_POLICY must be one of primary, majority, unanimous")
+            })
+            .unwrap_or_default();
+        config = config.with_consensus(
+            ConsensusConfig::new(sample_rate)
+                .with_providers(providers)
+                .with_methods(methods)
+                .with_policy(policy),
+        );
+    }
+    let coalesce_accounts = env::var("COALESCE_ACCOUNTS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if coalesce_accounts {
+        let max_accounts: usize = env::var("COALESCE_MAX_ACCOUNTS")
+            .unwrap_or_else(|_| MAX_MULTIPLE_ACCOUNTS.to_string())
+            .parse()
+            .expect("COALESCE_MAX_ACCOUNTS must be a valid number");
+        config = config.with_coalescing(max_accounts);
+    }
+    let upstream_batch = env::var("UPSTREAM_BATCH")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if upstream_batch {
+        let max_calls: usize = env::var("UPSTREAM_BATCH_MAX_CALLS")
+            .unwrap_or_else(|_| DEFAULT_UPSTREAM_BATCH_MAX_CALLS.to_string())
+            .parse()
+            .expect("UPSTREAM_BATCH_MAX_CALLS must be a valid number");
+        config = config.with_upstream_batch(max_calls);
+    }
 
     // Log startup info (without exposing full RPC URL credentials)
     let sanitized_url = sanitize_rpc_url(&rpc_url);
@@ -58,15 +352,3 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
-
-/// Sanitize RPC URL for logging (hide API keys)
-fn sanitize_rpc_url(url: &str) -> String {
-    if let Some(idx) = url.find("://") {
-        let after_scheme = &url[idx + 3..];
-        if let Some(path_idx) = after_scheme.find('/') {
-            let host = &after_scheme[..path_idx];
-            return format!("{}://{}/***", &url[..idx], host);
-        }
-    }
-    url.to_string()
-}
@@ -0,0 +1,8 @@
+//! Server-side query mixing
+//!
+//! Builds k-anonymous batches from queries submitted one at a time to
+//! `POST /query`.
+
+mod query_mixer;
+
+pub use query_mixer::QueryMixer;
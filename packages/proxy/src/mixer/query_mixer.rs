@@ -0,0 +1,526 @@
+//! Pool of individual queries flushed as k-anonymous batches
+//!
+//! Queries submitted one at a time are held until at least k distinct
+//! clients contributed, then executed together through the
+//! [`BatchExecutor`]. Each caller receives only the result at its own
+//! position in the batch, so query ids need not be unique across callers.
+//!
+//! A deadline starts with the first pooled query. If it passes before k
+//! clients joined, the [`DeadlinePolicy`] decides:
+//!
+//! - `Pad`: execute anyway, adding one decoy from the corpus for every
+//!   missing client
+//! - `Wait`: keep the pool open for the extension period, then reject
+//! - `Reject`: fail every pooled query
+//!
+//! Clients are identified by their IP address, so one client opening several
+//! sockets still counts once. Behind a reverse proxy all clients share one
+//! address and count as one. Queries of callers that disconnected are dropped
+//! from the pool and never count.
+
+use crate::crypto::parse_public_key;
+use crate::decoy::DecoyGenerator;
+use crate::enums::DeadlinePolicy;
+use crate::error::{ProxyError, ProxyResult};
+use crate::executor::BatchExecutor;
+use crate::types::{BatchRequest, MixerConfig, Query, QueryResult};
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, info, warn};
+
+type Responder = oneshot::Sender<ProxyResult<QueryResult>>;
+
+/// A query waiting in the pool
+struct PooledQuery {
+    query: Query,
+    client: IpAddr,
+    responder: Responder,
+}
+
+/// Queries collected for the next batch
+#[derive(Default)]
+struct Pool {
+    /// Incremented whenever the pool empties, so stale deadline timers can
+    /// be ignored
+    epoch: u64,
+    entries: Vec<PooledQuery>,
+}
+
+impl Pool {
+    /// Drop the queries whose caller stopped waiting for the result
+    fn prune(&mut self) {
+        let before = self.entries.len();
+        self.entries.retain(|entry| !entry.responder.is_closed());
+        if before > 0 && self.entries.is_empty() {
+            self.epoch += 1;
+        }
+    }
+
+    fn distinct_clients(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.client)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn take(&mut self) -> Vec<PooledQuery> {
+        self.epoch += 1;
+        std::mem::take(&mut self.entries)
+    }
+}
+
+/// Builds k-anonymous batches from individually submitted queries
+pub struct QueryMixer {
+    executor: Arc<BatchExecutor>,
+    config: MixerConfig,
+    /// Distinct clients required before a batch is flushed
+    min_clients: usize,
+    pool: Mutex<Pool>,
+    /// Source of padding for the `Pad` deadline policy
+    decoys: Option<Arc<DecoyGenerator>>,
+}
+
+impl QueryMixer {
+    pub fn new(executor: Arc<BatchExecutor>, min_clients: usize, config: MixerConfig) -> Self {
+        Self {
+            executor,
+            config,
+            min_clients,
+            pool: Mutex::new(Pool::default()),
+            decoys: None,
+        }
+    }
+
+    /// Pad batches from this decoy generator under the `Pad` deadline policy
+    pub fn with_decoys(mut self, decoys: Arc<DecoyGenerator>) -> Self {
+        self.decoys = Some(decoys);
+        self
+    }
+
+    /// Number of queries currently waiting
+    pub async fn pending(&self) -> usize {
+        self.pool.lock().await.entries.len()
+    }
+
+    /// Pool a query from `client` and wait for its result
+    pub async fn submit(self: &Arc<Self>, query: Query, client: IpAddr) -> ProxyResult<QueryResult> {
+        // An invalid key or an oversized query would fail the whole batch,
+        // so reject them up front
+        if let Some(key) = query.encryption_key.as_deref() {
+            parse_public_key(key)?;
+        }
+        self.executor.check_account_limit(&query)?;
+
+        let (responder, receiver) = oneshot::channel();
+        {
+            let mut pool = self.pool.lock().await;
+            pool.prune();
+            // Never flush early on size alone: that would let a single
+            // client fill a batch by itself
+            if pool.entries.len() >= self.config.max_pool_size {
+                return Err(ProxyError::MixerFull(self.config.max_pool_size));
+            }
+            pool.entries.push(PooledQuery {
+                query,
+                client,
+                responder,
+            });
+
+            if pool.distinct_clients() >= self.min_clients {
+                let entries = pool.take();
+                self.spawn_flush(entries, Vec::new());
+            } else if pool.entries.len() == 1 {
+                self.spawn_deadline(pool.epoch);
+            }
+        }
+
+        receiver
+            .await
+            .map_err(|_| ProxyError::Internal("Mixed batch was dropped".to_string()))?
+    }
+
+    fn spawn_deadline(self: &Arc<Self>, epoch: u64) {
+        let mixer = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(mixer.config.expiry()).await;
+            mixer.on_deadline(epoch).await;
+        });
+    }
+
+    async fn on_deadline(self: &Arc<Self>, epoch: u64) {
+        let mut pool = self.pool.lock().await;
+        if pool.epoch != epoch {
+            return;
+        }
+        pool.prune();
+        if pool.entries.is_empty() {
+            return;
+        }
+
+        let clients = pool.distinct_clients();
+        let entries = pool.take();
+        drop(pool);
+
+        if let (DeadlinePolicy::Pad, Some(decoys)) = (self.config.deadline_policy, &self.decoys) {
+            // One decoy stands in for each missing client
+            let missing = self.min_clients.saturating_sub(clients);
+            let real: Vec<Query> = entries.iter().map(|entry| entry.query.clone()).collect();
+            let padding = decoys.generate_like(&real, missing);
+            if padding.len() == missing {
+                debug!(
+                    real = entries.len(),
+                    clients = clients,
+                    padding = padding.len(),
+                    "Mixer deadline passed, padding batch"
+                );
+                self.spawn_flush(entries, padding);
+                return;
+            }
+        }
+
+        warn!(
+            clients = clients,
+            min = self.min_clients,
+            policy = %self.config.deadline_policy,
+            "Mixer deadline passed without enough clients"
+        );
+        for entry in entries {
+            let _ = entry
+                .responder
+                .send(Err(ProxyError::AnonymitySetNotReached {
+                    actual: clients,
+                    min: self.min_clients,
+                }));
+        }
+    }
+
+    fn spawn_flush(self: &Arc<Self>, entries: Vec<PooledQuery>, padding: Vec<Query>) {
+        let mixer = Arc::clone(self);
+        tokio::spawn(async move { mixer.flush(entries, padding).await });
+    }
+
+    async fn flush(&self, entries: Vec<PooledQuery>, padding: Vec<Query>) {
+        let (queries, responders): (Vec<Query>, Vec<(Responder, String)>) = entries
+            .into_iter()
+            .map(|entry| {
+                let id = entry.query.id.clone();
+                (entry.query, (entry.responder, id))
+            })
+            .unzip();
+
+        // Shuffle padding in so its position does not reveal it; each slot
+        // records which caller, if any, the query belongs to
+        let real_count = queries.len();
+        let padding_count = padding.len();
+        let mut batch: Vec<(Option<usize>, Query)> = queries
+            .into_iter()
+            .enumerate()
+            .map(|(index, query)| (Some(index), query))
+            .chain(padding.into_iter().map(|query| (None, query)))
+            .collect();
+        batch.shuffle(&mut rand::thread_rng());
+        let (slots, batch): (Vec<Option<usize>>, Vec<Query>) = batch.into_iter().unzip();
+
+        info!(
+            queries = real_count,
+            padding = padding_count,
+            "Flushing mixed batch"
+        );
+
+        match self.executor.execute_batch(BatchRequest::new(batch)).await {
+            Ok(response) => {
+                let mut results: Vec<Option<QueryResult>> = vec![None; real_count];
+                for (slot, result) in slots.into_iter().zip(response.results) {
+                    if let Some(index) = slot {
+                        results[index] = Some(result);
+                    }
+                }
+                for ((responder, id), result) in responders.into_iter().zip(results) {
+                    let result = result.unwrap_or_else(|| {
+                        QueryResult::failure(id, "No result for query".to_string())
+                    });
+                    let _ = responder.send(Ok(result));
+                }
+            }
+            Err(error) => {
+                warn!(error = %error, "Mixed batch failed");
+                for (responder, id) in responders {
+                    let _ = responder.send(Ok(QueryResult::failure(id, error.to_string())));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoy::{CorpusEntry, DecoyCorpus};
+    use crate::enums::RpcMethod;
+    use crate::types::DecoyConfig;
+    use crate::upstream::MockUpstream;
+
+    const TARGET: &str = "11111111111111111111111111111111";
+
+    fn mixer(upstream: &MockUpstream, k: usize, config: MixerConfig) -> QueryMixer {
+        let executor = Arc::new(BatchExecutor::new(&upstream.url()).with_min_batch_size(k));
+        QueryMixer::new(executor, k, config)
+    }
+
+    fn decoys(corpus: &[&str]) -> Arc<DecoyGenerator> {
+        let corpus = DecoyCorpus {
+            accounts: corpus
+                .iter()
+                .map(|pubkey| CorpusEntry {
+                    pubkey: pubkey.to_string(),
+                    weight: 1.0,
+                })
+                .collect(),
+            token_accounts: Vec::new(),
+        };
+        Arc::new(DecoyGenerator::new(corpus, DecoyConfig::new(String::new(), 0)).unwrap())
+    }
+
+    fn query(id: &str) -> Query {
+        Query::new(id.to_string(), RpcMethod::GetBalance, TARGET.to_string())
+    }
+
+    fn client(host: u8) -> IpAddr {
+        IpAddr::from([127, 0, 0, host])
+    }
+
+    #[tokio::test]
+    async fn test_flushes_at_k_clients_and_routes_results() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(&upstream, 2, MixerConfig::default()));
+
+        let first = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+        let second = mixer.submit(query("b"), client(2)).await.unwrap();
+
+        assert_eq!(second.id, "b");
+        assert!(second.success);
+        assert_eq!(first.await.unwrap().unwrap().id, "a");
+        assert_eq!(mixer.pending().await, 0);
+        assert_eq!(upstream.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_same_client_counts_once() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(
+            &upstream,
+            2,
+            MixerConfig::default()
+                .with_max_wait(20)
+                .with_deadline_policy(DeadlinePolicy::Reject),
+        ));
+
+        // Two sockets of the same host are still one client
+        let first = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+        let second = mixer.submit(query("b"), client(1)).await;
+
+        assert!(matches!(
+            second,
+            Err(ProxyError::AnonymitySetNotReached { actual: 1, min: 2 })
+        ));
+        assert!(first.await.unwrap().is_err());
+        assert_eq!(upstream.call_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_clients_do_not_count() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(
+            &upstream,
+            2,
+            MixerConfig::default()
+                .with_max_wait(20)
+                .with_deadline_policy(DeadlinePolicy::Reject),
+        ));
+
+        let gone = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+        while mixer.pending().await == 0 {
+            tokio::task::yield_now().await;
+        }
+        gone.abort();
+        assert!(gone.await.unwrap_err().is_cancelled());
+
+        // The second client alone is short of k
+        let second = mixer.submit(query("b"), client(2)).await;
+        assert!(matches!(
+            second,
+            Err(ProxyError::AnonymitySetNotReached { actual: 1, min: 2 })
+        ));
+        assert_eq!(upstream.call_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pad_policy_pads_missing_clients_with_decoys() {
+        let corpus = [
+            "So11111111111111111111111111111111111111112",
+            "Vote111111111111111111111111111111111111111",
+        ];
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(
+            mixer(
+                &upstream,
+                3,
+                MixerConfig::default()
+                    .with_max_wait(20)
+                    .with_deadline_policy(DeadlinePolicy::Pad),
+            )
+            .with_decoys(decoys(&corpus)),
+        );
+
+        // Two queries from one client: one client is missing beyond the
+        // two counted, whatever the number of queries
+        let first = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+        let second = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("b"), client(1)).await }
+        });
+        let third = mixer.submit(query("c"), client(2)).await.unwrap();
+
+        assert_eq!(third.id, "c");
+        assert!(first.await.unwrap().unwrap().success);
+        assert!(second.await.unwrap().unwrap().success);
+
+        let targets = upstream.targets();
+        assert_eq!(targets.len(), 4);
+        assert_eq!(targets.iter().filter(|t| *t == TARGET).count(), 3);
+        assert!(targets
+            .iter()
+            .filter(|t| *t != TARGET)
+            .all(|t| corpus.contains(&t.as_str())));
+    }
+
+    #[tokio::test]
+    async fn test_pad_policy_never_replays_earlier_queries() {
+        let corpus = ["So11111111111111111111111111111111111111112"];
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(
+            mixer(
+                &upstream,
+                2,
+                MixerConfig::default()
+                    .with_max_wait(20)
+                    .with_deadline_policy(DeadlinePolicy::Pad),
+            )
+            .with_decoys(decoys(&corpus)),
+        );
+
+        mixer.submit(query("a"), client(1)).await.unwrap();
+        let later = Query::new(
+            "b".to_string(),
+            RpcMethod::GetBalance,
+            "Vote111111111111111111111111111111111111111".to_string(),
+        );
+        mixer.submit(later, client(2)).await.unwrap();
+
+        // The first client's target went upstream once, with its own batch
+        let targets = upstream.targets();
+        assert_eq!(targets.len(), 4);
+        assert_eq!(targets.iter().filter(|t| *t == TARGET).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pad_policy_without_decoys_rejects() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(
+            &upstream,
+            2,
+            MixerConfig::default()
+                .with_max_wait(20)
+                .with_deadline_policy(DeadlinePolicy::Pad),
+        ));
+
+        assert!(matches!(
+            mixer.submit(query("a"), client(1)).await,
+            Err(ProxyError::AnonymitySetNotReached { actual: 1, min: 2 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wait_policy_holds_until_extension() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(
+            &upstream,
+            2,
+            MixerConfig::default()
+                .with_max_wait(10)
+                .with_max_extension(200)
+                .with_deadline_policy(DeadlinePolicy::Wait),
+        ));
+
+        let first = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+
+        // Past the deadline but within the extension the pool is still open
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let second = mixer.submit(query("b"), client(2)).await.unwrap();
+
+        assert_eq!(second.id, "b");
+        assert_eq!(first.await.unwrap().unwrap().id, "a");
+    }
+
+    #[tokio::test]
+    async fn test_full_pool_refuses_queries() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(
+            &upstream,
+            2,
+            MixerConfig {
+                max_pool_size: 1,
+                ..MixerConfig::default().with_max_wait(20)
+            },
+        ));
+
+        let first = tokio::spawn({
+            let mixer = Arc::clone(&mixer);
+            async move { mixer.submit(query("a"), client(1)).await }
+        });
+        while mixer.pending().await == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(matches!(
+            mixer.submit(query("b"), client(2)).await,
+            Err(ProxyError::MixerFull(1))
+        ));
+        assert!(first.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_encryption_key_rejected_before_pooling() {
+        let upstream = MockUpstream::start().await;
+        let mixer = Arc::new(mixer(&upstream, 2, MixerConfig::default()));
+
+        let result = mixer
+            .submit(
+                query("a").with_encryption_key("not-a-key".to_string()),
+                client(1),
+            )
+            .await;
+
+        assert!(matches!(result, Err(ProxyError::InvalidQuery(_))));
+        assert_eq!(mixer.pending().await, 0);
+    }
+}
@@ -0,0 +1,73 @@
+//! Dispatch order and timing of upstream calls
+
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::time::Duration;
+
+/// Shuffles the calls of a batch and spreads them across a dispatch window
+///
+/// Calls are permuted with the operating system's CSPRNG. Each call is then
+/// given an offset drawn uniformly from the window; sorting the offsets yields
+/// randomized inter-arrival delays that add up to at most the window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DispatchScheduler {
+    window: Duration,
+}
+
+impl DispatchScheduler {
+    /// Create a scheduler spreading calls across `window` (zero: shuffle only)
+    pub fn new(window: Duration) -> Self {
+        Self { window }
+    }
+
+    /// Window the calls of a batch are spread across
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Shuffle `calls` and pair each with its dispatch offset, in dispatch order
+    pub fn schedule<T>(&self, mut calls: Vec<T>) -> Vec<(Duration, T)> {
+        let mut rng = OsRng;
+        calls.shuffle(&mut rng);
+
+        let window_us = self.window.as_micros() as u64;
+        let mut offsets: Vec<Duration> = (0..calls.len())
+            .map(|_| match window_us {
+                0 => Duration::ZERO,
+                window_us => Duration::from_micros(rng.gen_range(0..=window_us)),
+            })
+            .collect();
+        offsets.sort_unstable();
+
+        offsets.into_iter().zip(calls).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_a_permutation_within_the_window() {
+        let scheduler = DispatchScheduler::new(Duration::from_millis(50));
+        let schedule = scheduler.schedule((0..64).collect());
+
+        assert!(schedule.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!(schedule
+            .iter()
+            .all(|(offset, _)| *offset <= scheduler.window()));
+
+        let mut calls: Vec<i32> = schedule.iter().map(|(_, call)| *call).collect();
+        assert_ne!(calls, (0..64).collect::<Vec<_>>());
+        calls.sort_unstable();
+        assert_eq!(calls, (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_zero_window_dispatches_at_once() {
+        let schedule = DispatchScheduler::default().schedule(vec!["a", "b", "c"]);
+        assert!(schedule.iter().all(|(offset, _)| offset.is_zero()));
+        assert_eq!(schedule.len(), 3);
+    }
+}
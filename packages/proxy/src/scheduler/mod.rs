@@ -0,0 +1,11 @@
+//! Upstream dispatch scheduling
+//!
+//! Decides the order and timing in which the calls of a batch reach the
+//! upstream RPC, so neither reflects how clients submitted them, and which
+//! provider each call goes to when several are configured.
+
+mod dispatch_scheduler;
+mod upstream_shards;
+
+pub use dispatch_scheduler::DispatchScheduler;
+pub use upstream_shards::{ShardRoom, UpstreamShards};
@@ -0,0 +1,272 @@
+//! Split of upstream calls across independent providers
+
+use crate::enums::ShardPolicy;
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::ShardConfig;
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Provider a target is pinned to under [`ShardPolicy::Keyed`]
+fn keyed_shard(target: &str, providers: usize) -> usize {
+    let digest = Sha256::digest(target.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_le_bytes(bytes) % providers as u64) as usize
+}
+
+/// Room a batch has left on each provider under the shard cap
+///
+/// Failover may only move a call to a provider with room left, so a
+/// provider whose share is used up never sees more of the batch.
+#[derive(Debug)]
+pub struct ShardRoom {
+    free: Vec<AtomicUsize>,
+}
+
+impl ShardRoom {
+    /// Take room for one more call on `provider`
+    pub fn take(&self, provider: usize) -> bool {
+        self.free.get(provider).is_some_and(|free| {
+            free.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |free| {
+                free.checked_sub(1)
+            })
+            .is_ok()
+        })
+    }
+
+    /// Give back room taken for a call that was not sent
+    pub fn release(&self, provider: usize) {
+        if let Some(free) = self.free.get(provider) {
+            free.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Policy assigning the calls of a batch to upstream providers
+///
+/// Every provider receives at most `max_fraction` of a batch's calls (rounded
+/// up). A call whose preferred provider is full overflows to the next one
+/// with room, so the cap wins over the policy.
+pub struct UpstreamShards {
+    providers: usize,
+    policy: ShardPolicy,
+    max_fraction: f64,
+    next: AtomicUsize,
+}
+
+impl UpstreamShards {
+    /// Create a planner over `providers` upstreams
+    ///
+    /// There must be at least two providers, and the cap must leave room for
+    /// a whole batch across them.
+    pub fn new(providers: usize, config: &ShardConfig) -> ProxyResult<Self> {
+        if providers < 2 {
+            return Err(ProxyError::Internal(
+                "Upstream sharding needs at least two providers".to_string(),
+            ));
+        }
+        if !(config.max_fraction <= 1.0 && config.max_fraction * providers as f64 >= 1.0) {
+            return Err(ProxyError::Internal(format!(
+                "Shard fraction {} must lie between 1/{} and 1",
+                config.max_fraction, providers
+            )));
+        }
+
+        Ok(Self {
+            providers,
+            policy: config.policy,
+            max_fraction: config.max_fraction,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Number of providers
+    pub fn len(&self) -> usize {
+        self.providers
+    }
+
+    /// Check whether there are no providers (never true once built)
+    pub fn is_empty(&self) -> bool {
+        self.providers == 0
+    }
+
+    /// Largest number of calls of a batch of `calls` one provider may receive
+    pub fn capacity(&self, calls: usize) -> usize {
+        ((self.max_fraction * calls as f64).ceil() as usize).max(1)
+    }
+
+    /// Provider `target` is pinned to, under [`ShardPolicy::Keyed`] only
+    pub fn home(&self, target: Option<&str>) -> Option<usize> {
+        match (self.policy, target) {
+            (ShardPolicy::Keyed, Some(target)) => Some(keyed_shard(target, self.providers)),
+            _ => None,
+        }
+    }
+
+    /// Room left on each provider once the calls of `plan` are placed
+    pub fn room(&self, plan: &[usize]) -> ShardRoom {
+        let capacity = self.capacity(plan.len());
+        let mut load = vec![0usize; self.providers];
+        for shard in plan {
+            load[*shard] += 1;
+        }
+        ShardRoom {
+            free: load
+                .into_iter()
+                .map(|load| AtomicUsize::new(capacity.saturating_sub(load)))
+                .collect(),
+        }
+    }
+
+    /// Assign each call, given by its target, to a provider
+    ///
+    /// Calls without a target (getBlockHeight) are assigned round-robin under
+    /// [`ShardPolicy::Keyed`].
+    pub fn plan(&self, targets: &[Option<String>]) -> Vec<usize> {
+        let providers = self.providers;
+        let capacity = self.capacity(targets.len());
+        let mut load = vec![0usize; providers];
+        let mut rng = OsRng;
+
+        targets
+            .iter()
+            .map(|target| {
+                let preferred = match (self.policy, target) {
+                    (ShardPolicy::Random, _) => {
+                        let open: Vec<usize> = (0..providers)
+                            .filter(|shard| load[*shard] < capacity)
+                            .collect();
+                        open.choose(&mut rng).copied()
+                    }
+                    (ShardPolicy::Keyed, Some(target)) => Some(keyed_shard(target, providers)),
+                    _ => Some(self.next.fetch_add(1, Ordering::Relaxed) % providers),
+                };
+                // Overflow to the next provider with room; rounding can leave
+                // none, then the least loaded one takes the call
+                let shard = preferred
+                    .and_then(|preferred| {
+                        (0..providers)
+                            .map(|step| (preferred + step) % providers)
+                            .find(|shard| load[*shard] < capacity)
+                    })
+                    .unwrap_or_else(|| {
+                        (0..providers).min_by_key(|shard| load[*shard]).unwrap_or(0)
+                    });
+                load[shard] += 1;
+                shard
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shards(providers: usize, policy: ShardPolicy, max_fraction: f64) -> UpstreamShards {
+        UpstreamShards::new(
+            providers,
+            &ShardConfig::default()
+                .with_policy(policy)
+                .with_max_fraction(max_fraction),
+        )
+        .unwrap()
+    }
+
+    fn targets(count: usize) -> Vec<Option<String>> {
+        (0..count).map(|i| Some(format!("target-{}", i))).collect()
+    }
+
+    fn loads(plan: &[usize], providers: usize) -> Vec<usize> {
+        let mut loads = vec![0; providers];
+        for shard in plan {
+            loads[*shard] += 1;
+        }
+        loads
+    }
+
+    #[test]
+    fn test_only_keyed_targets_have_a_home() {
+        let keyed = shards(3, ShardPolicy::Keyed, 1.0);
+        let home = keyed.home(Some("target-0"));
+        assert_eq!(home, Some(keyed.plan(&targets(1))[0]));
+        assert_eq!(keyed.home(None), None);
+
+        let random = shards(3, ShardPolicy::Random, 1.0);
+        assert_eq!(random.home(Some("target-0")), None);
+    }
+
+    #[test]
+    fn test_rejects_invalid_configurations() {
+        assert!(UpstreamShards::new(1, &ShardConfig::default()).is_err());
+
+        let too_small = ShardConfig::default().with_max_fraction(0.3);
+        assert!(UpstreamShards::new(3, &too_small).is_err());
+        let too_large = ShardConfig::default().with_max_fraction(1.5);
+        assert!(UpstreamShards::new(3, &too_large).is_err());
+        let nan = ShardConfig::default().with_max_fraction(f64::NAN);
+        assert!(UpstreamShards::new(3, &nan).is_err());
+    }
+
+    #[test]
+    fn test_no_provider_exceeds_its_fraction() {
+        for policy in [
+            ShardPolicy::Random,
+            ShardPolicy::RoundRobin,
+            ShardPolicy::Keyed,
+        ] {
+            let shards = shards(3, policy, 0.4);
+            for count in [1, 2, 7, 30, 100] {
+                let plan = shards.plan(&targets(count));
+                assert_eq!(plan.len(), count);
+                let capacity = shards.capacity(count);
+                assert!(loads(&plan, 3).iter().all(|load| *load <= capacity));
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_robin_spreads_evenly() {
+        let shards = shards(4, ShardPolicy::RoundRobin, 1.0);
+        assert_eq!(loads(&shards.plan(&targets(20)), 4), [5, 5, 5, 5]);
+    }
+
+    #[test]
+    fn test_keyed_pins_targets_to_providers() {
+        let shards = shards(3, ShardPolicy::Keyed, 1.0);
+        let first = shards.plan(&targets(12));
+        let mut reversed = targets(12);
+        reversed.reverse();
+        let mut second = shards.plan(&reversed);
+        second.reverse();
+        assert_eq!(first, second);
+
+        // Calls without a target still get a provider
+        assert_eq!(shards.plan(&[None, None]).len(), 2);
+    }
+
+    #[test]
+    fn test_room_is_what_the_plan_leaves_under_the_cap() {
+        let shards = shards(3, ShardPolicy::RoundRobin, 0.5);
+        // Four calls: at most two per provider
+        let room = shards.room(&[0, 1, 1, 2]);
+
+        assert!(!room.take(1));
+        assert!(room.take(0));
+        assert!(!room.take(0));
+        room.release(0);
+        assert!(room.take(0));
+        assert!(room.take(2));
+        assert!(!room.take(2));
+        assert!(!room.take(3));
+    }
+
+    #[test]
+    fn test_keyed_overflows_when_provider_is_full() {
+        let shards = shards(2, ShardPolicy::Keyed, 0.5);
+        let same = vec![Some("target".to_string()); 4];
+        assert_eq!(loads(&shards.plan(&same), 2), [2, 2]);
+    }
+}
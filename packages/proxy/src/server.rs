@@ -2,38 +2,303 @@
 //!
 //! This module configures the Axum web server with all routes and middleware.
 
-use crate::coordinator::{BatchPoller, CoordinatorReader};
-use crate::executor::BatchExecutor;
-use crate::handlers::{execute_batch, health_check, AppState};
+use crate::coordinator::{BatchCompleter, BatchPoller, CoordinatorReader, KeyPublisher};
+use crate::cover::CoverTraffic;
+use crate::crypto::{unix_now, KeyManager};
+use crate::decoy::{DecoyGenerator, IntersectionGuard};
+use crate::enums::DeadlinePolicy;
+use crate::executor::{BatchExecutor, QueryPlanner};
+use crate::handlers::{
+    execute_batch, get_keys, get_metrics, get_result, health_check, submit_query, AppState,
+};
+use crate::mixer::QueryMixer;
+use crate::scheduler::UpstreamShards;
+use crate::store::{ExecutedBatchStore, ResultStore};
 use crate::types::ProxyConfig;
+use crate::upstream::{BatchTransport, CallSlots, ResultConsensus, UpstreamPool};
 use axum::{
     routing::{get, post},
     Router,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{info, warn};
 
 const DEFAULT_POLL_INTERVAL_MS: u64 = 5000;
 
+/// How often the key rotation task checks for due rotations
+const KEY_ROTATION_CHECK_SECS: u64 = 60;
+
 /// Start the HTTP server
 pub async fn run(config: ProxyConfig) -> Result<(), Box<dyn std::error::Error>> {
+    // Every request to the RPC endpoints, the coordinator's included, takes
+    // one of the same call slots
+    let call_slots = Arc::new(CallSlots::new(config.max_in_flight_calls));
+
     // Create coordinator reader if on-chain verification is enabled
     let coordinator = if config.enable_poller {
-        Some(CoordinatorReader::new(&config.rpc_url))
+        Some(CoordinatorReader::new(&config.rpc_url).with_call_slots(&call_slots))
     } else {
         None
     };
 
+    if config.strict_coordination && coordinator.is_none() {
+        return Err("Strict coordination requires the on-chain coordinator (ENABLE_POLLER)".into());
+    }
+
+    // Mixed batches are never coordinated on-chain, so strict mode excludes them
+    if config.strict_coordination && config.mixer.is_some() {
+        return Err("Query mixing cannot be combined with strict coordination".into());
+    }
+
+    // Create batch completer if an executor keypair is configured
+    let completer = match (&config.executor_keypair_path, config.enable_poller) {
+        (Some(path), true) => {
+            let completer = BatchCompleter::from_keypair_file(&config.rpc_url, path)?
+                .with_call_slots(&call_slots);
+            info!(executor = %completer.executor_pubkey(), "Batch completion enabled");
+            Some(completer)
+        }
+        (Some(_), false) => {
+            warn!("Executor keypair ignored: on-chain coordination is disabled");
+            None
+        }
+        (None, _) => None,
+    };
+
+    // Load or generate encryption keys if key management is enabled
+    let key_manager = match &config.encryption_key_dir {
+        Some(dir) => {
+            let key_manager =
+                Arc::new(KeyManager::load_or_generate(dir, config.key_rotation_secs)?);
+            info!(
+                fingerprint = %hex::encode(key_manager.fingerprint()),
+                rotation_secs = ?config.key_rotation_secs,
+                "Encryption keys loaded"
+            );
+            let publisher = completer.as_ref().map(BatchCompleter::key_publisher);
+            if publisher.is_none() {
+                warn!("Encryption key fingerprint will not be published: no executor keypair");
+            }
+            spawn_key_rotation(Arc::clone(&key_manager), publisher);
+            Some(key_manager)
+        }
+        None => None,
+    };
+
+    let upstream_urls: Vec<String> = std::iter::once(config.rpc_url.clone())
+        .chain(config.upstream_urls.iter().cloned())
+        .collect();
+    let upstreams = Arc::new(
+        UpstreamPool::new(&upstream_urls, config.failover.clone())?
+            .with_call_slots(Arc::clone(&call_slots)),
+    );
+    if upstreams.len() > 1 {
+        info!(
+            upstreams = upstreams.len(),
+            max_attempts = config.failover.max_attempts,
+            failure_threshold = config.failover.failure_threshold,
+            "Upstream failover enabled"
+        );
+    }
+
+    let mut executor = BatchExecutor::new(&config.rpc_url)
+        .with_upstreams(Arc::clone(&upstreams))
+        .with_min_batch_size(config.k_anonymity)
+        .with_distinct_targets(config.count_distinct_targets)
+        .with_max_query_accounts(config.max_query_accounts)
+        .with_dispatch_window(Duration::from_millis(config.dispatch_window_ms))
+        .with_query_timeout(Duration::from_millis(config.query_timeout_ms))
+        .with_batch_timeout(Duration::from_millis(config.batch_timeout_ms));
+    if config.batch_timeout_ms > 0 && config.dispatch_window_ms >= config.batch_timeout_ms {
+        warn!(
+            dispatch_window_ms = config.dispatch_window_ms,
+            batch_timeout_ms = config.batch_timeout_ms,
+            "Batch deadline within the dispatch window: late calls will time out"
+        );
+    }
+    let decoys = match config.decoys.clone() {
+        Some(decoy_config) => {
+            info!(
+                corpus = %decoy_config.corpus_path,
+                target_size = decoy_config.target_size,
+                distribution = %decoy_config.distribution,
+                "Decoy injection enabled"
+            );
+            let decoys = Arc::new(DecoyGenerator::from_config(decoy_config)?);
+            executor = executor.with_decoys(Arc::clone(&decoys));
+            Some(decoys)
+        }
+        None => None,
+    };
+    let intersection_guard = match (config.cohorts.clone(), &decoys) {
+        (Some(cohort_config), Some(decoys)) => {
+            info!(
+                threshold = cohort_config.threshold,
+                cohort_size = cohort_config.cohort_size,
+                persistent = cohort_config.key_path.is_some(),
+                "Decoy cohorts enabled"
+            );
+            let guard = Arc::new(IntersectionGuard::from_config(
+                Arc::clone(decoys),
+                cohort_config,
+            )?);
+            executor = executor.with_intersection_guard(Arc::clone(&guard));
+            Some(guard)
+        }
+        // Cohorts are drawn from the corpus, so they need one
+        (Some(_), None) => {
+            return Err("Decoy cohorts require a decoy corpus (DECOY_CORPUS_PATH)".into());
+        }
+        (None, _) => None,
+    };
+    if !config.batch_buckets.is_empty() {
+        // Buckets are filled with decoys, so they need a corpus
+        if decoys.is_none() {
+            return Err("Batch size buckets require a decoy corpus (DECOY_CORPUS_PATH)".into());
+        }
+        info!(buckets = ?config.batch_buckets, "Batch size bucketing enabled");
+        executor = executor.with_batch_buckets(config.batch_buckets.clone());
+    }
+    if let Some(shard_config) = &config.shards {
+        // The emitter picks the upstream of every call itself
+        if config.cover_traffic.is_some() {
+            return Err("Upstream sharding cannot be combined with cover traffic".into());
+        }
+        let shards = UpstreamShards::new(upstreams.len(), shard_config)?;
+        info!(
+            providers = shards.len(),
+            policy = %shard_config.policy,
+            max_fraction = shard_config.max_fraction,
+            "Upstream sharding enabled"
+        );
+        executor = executor.with_shards(Arc::new(shards));
+    }
+    let consensus = match &config.consensus {
+        Some(consensus_config) => {
+            // The emitter sends exactly one call per slot
+            if config.cover_traffic.is_some() {
+                return Err("Result consensus cannot be combined with cover traffic".into());
+            }
+            // Verified calls go to several providers at once, past their
+            // shard and its share of the batch
+            if config.shards.is_some() {
+                return Err("Result consensus cannot be combined with upstream sharding".into());
+            }
+            if upstreams.len() < consensus_config.providers {
+                return Err(format!(
+                    "Result consensus needs {} upstreams (UPSTREAM_RPC_URLS), the pool has {}",
+                    consensus_config.providers,
+                    upstreams.len()
+                )
+                .into());
+            }
+            info!(
+                providers = consensus_config.providers,
+                sample_rate = consensus_config.sample_rate,
+                methods = ?consensus_config.methods,
+                policy = %consensus_config.policy,
+                "Result consensus enabled"
+            );
+            let consensus = Arc::new(ResultConsensus::new(consensus_config.clone())?);
+            executor = executor.with_consensus(Arc::clone(&consensus));
+            Some(consensus)
+        }
+        None => None,
+    };
+    if let Some(max_accounts) = config.coalesce_accounts {
+        info!(
+            max_accounts = max_accounts,
+            "Account query coalescing enabled"
+        );
+        executor = executor.with_query_planner(QueryPlanner::new(max_accounts)?);
+    }
+    if let Some(max_calls) = config.upstream_batch {
+        // The emitter sends one call per slot, never a whole batch
+        if config.cover_traffic.is_some() {
+            return Err("Upstream batching cannot be combined with cover traffic".into());
+        }
+        if config.dispatch_window_ms > 0 {
+            warn!("Dispatch window ignored: upstream batching sends each batch at once");
+        }
+        let mut transport = BatchTransport::new(Arc::clone(&upstreams), max_calls)?;
+        if let Some(consensus) = &consensus {
+            transport = transport.with_consensus(Arc::clone(consensus));
+        }
+        info!(max_calls = max_calls, "Upstream JSON-RPC batching enabled");
+        executor = executor.with_batch_transport(Arc::new(transport));
+    }
+    let cover_traffic = match (config.cover_traffic.clone(), &decoys) {
+        (Some(cover_config), Some(decoys)) => {
+            if cover_config.calls_per_sec == 0 {
+                return Err("Cover traffic rate must be at least one call per second".into());
+            }
+            if config.dispatch_window_ms > 0 {
+                warn!("Dispatch window ignored: cover traffic sets the upstream call rate");
+            }
+            let cover_traffic = Arc::new(CoverTraffic::new(
+                Arc::clone(&upstreams),
+                Arc::clone(decoys),
+                cover_config,
+            ));
+            let _handle = cover_traffic.start();
+            executor = executor.with_cover_traffic(Arc::clone(&cover_traffic));
+            Some(cover_traffic)
+        }
+        // Idle slots are filled with decoys, so cover traffic needs a corpus
+        (Some(_), None) => {
+            return Err("Cover traffic requires a decoy corpus (DECOY_CORPUS_PATH)".into());
+        }
+        (None, _) => None,
+    };
+    let executor = Arc::new(executor);
+
+    let mixer = match config.mixer.clone() {
+        Some(mixer_config) => {
+            // Padding is drawn from the corpus, never from other clients'
+            // queries
+            if mixer_config.deadline_policy == DeadlinePolicy::Pad && decoys.is_none() {
+                return Err(
+                    "The mixer pad policy requires a decoy corpus (DECOY_CORPUS_PATH)".into(),
+                );
+            }
+            info!(
+                max_wait_ms = mixer_config.max_wait_ms,
+                deadline_policy = %mixer_config.deadline_policy,
+                "Query mixing enabled"
+            );
+            let mut mixer =
+                QueryMixer::new(Arc::clone(&executor), config.k_anonymity, mixer_config);
+            if let Some(decoys) = &decoys {
+                mixer = mixer.with_decoys(Arc::clone(decoys));
+            }
+            Some(Arc::new(mixer))
+        }
+        None => None,
+    };
+
     let state = Arc::new(AppState {
-        executor: BatchExecutor::new(&config.rpc_url),
+        executor,
         coordinator,
+        completer,
+        strict_coordination: config.strict_coordination,
+        executed_batches: ExecutedBatchStore::new(),
+        result_store: config
+            .result_ttl_secs
+            .map(|ttl| ResultStore::new(Duration::from_secs(ttl))),
+        key_manager,
+        mixer,
+        cover_traffic,
+        intersection_guard,
+        consensus,
     });
 
     // Start batch poller if enabled
     if config.enable_poller {
         let poll_interval = config.poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS);
-        let poller = BatchPoller::new(&config.rpc_url, poll_interval);
+        let poller = BatchPoller::new(&config.rpc_url, poll_interval).with_call_slots(&call_slots);
         let _handle = poller.start();
         info!(interval_ms = poll_interval, "Batch poller started");
     }
@@ -48,19 +313,81 @@ pub async fn run(config: ProxyConfig) -> Result<(), Box<dyn std::error::Error>>
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/execute-batch", post(execute_batch))
+        .route("/results/:ticket", get(get_result))
+        .route("/keys", get(get_keys))
+        .route("/query", post(submit_query))
+        .route("/metrics", get(get_metrics))
         .layer(cors)
         .with_state(state);
 
+    if config.strict_coordination {
+        info!("Strict coordination enabled: uncoordinated batches are rejected");
+    }
+
+    info!(
+        k_anonymity = config.k_anonymity,
+        distinct_targets = config.count_distinct_targets,
+        "K-anonymity threshold enforced"
+    );
+
     let addr = format!("0.0.0.0:{}", config.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
 
     info!(address = %addr, "Server listening");
 
-    axum::serve(listener, app).await?;
+    // Connection addresses let the mixer count distinct clients
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
 
+/// Rotate encryption keys in the background and publish each new fingerprint
+///
+/// Publication is retried on every check until the chain holds the current
+/// fingerprint.
+fn spawn_key_rotation(
+    key_manager: Arc<KeyManager>,
+    publisher: Option<KeyPublisher>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut published = None;
+        loop {
+            match key_manager.rotate(unix_now()) {
+                Ok(true) => info!(
+                    fingerprint = %hex::encode(key_manager.fingerprint()),
+                    "Encryption keys rotated"
+                ),
+                Ok(false) => {}
+                Err(e) => warn!(error = %e, "Encryption key rotation failed"),
+            }
+
+            let fingerprint = key_manager.fingerprint();
+            if let Some(publisher) = publisher
+                .as_ref()
+                .filter(|_| published != Some(fingerprint))
+            {
+                match publisher.publish(fingerprint).await {
+                    Ok(signature) => {
+                        info!(
+                            fingerprint = %hex::encode(fingerprint),
+                            signature = ?signature,
+                            "Encryption key fingerprint published"
+                        );
+                        published = Some(fingerprint);
+                    }
+                    Err(e) => warn!(error = %e, "Encryption key publication failed"),
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(KEY_ROTATION_CHECK_SECS)).await;
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     // Integration tests would go here, testing the full HTTP flow
@@ -0,0 +1,327 @@
+//! Replay protection for coordinated batches
+//!
+//! Every on-chain batch is executed at most once. The first request for a
+//! batch claims it; a re-post with the same query set receives the stored
+//! response without touching the upstream RPC, and any other request for the
+//! batch is rejected. A claim is released if its execution fails or is
+//! abandoned, and stored responses expire after a while.
+
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{BatchResponse, Query};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default time the response of an executed batch is kept for replay
+pub const DEFAULT_EXECUTED_BATCH_TTL_SECS: u64 = 3600;
+
+/// Fingerprint of a submitted query set
+///
+/// Covers every `(id, commitment hash)` pair in order-independent form, so a
+/// re-post matches only if it would map results to the same query ids.
+pub fn batch_fingerprint(queries: &[Query]) -> ProxyResult<[u8; 32]> {
+    let mut entries = queries
+        .iter()
+        .map(|query| Ok((query.id.as_str(), query.commitment_hash()?)))
+        .collect::<ProxyResult<Vec<_>>>()?;
+    entries.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for (id, hash) in entries {
+        hasher.update((id.len() as u64).to_le_bytes());
+        hasher.update(id.as_bytes());
+        hasher.update(hash);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Outcome of claiming a batch for execution
+#[derive(Debug)]
+pub enum Admission<'a> {
+    /// Batch was claimed and must be executed
+    Execute(BatchClaim<'a>),
+    /// Batch was already executed with the same query set
+    Replay(Box<BatchResponse>),
+}
+
+/// Claim on a batch while it executes
+///
+/// Dropping the claim without completing it releases the batch, so a failed
+/// or abandoned execution (such as a client disconnecting mid-batch) can be
+/// retried.
+#[derive(Debug)]
+pub struct BatchClaim<'a> {
+    store: &'a ExecutedBatchStore,
+    batch_id: u64,
+}
+
+impl BatchClaim<'_> {
+    /// Store the response of the claimed batch for replay
+    pub fn complete(self, response: BatchResponse) {
+        self.store.complete(self.batch_id, response);
+        // Completed: nothing left to release
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for BatchClaim<'_> {
+    fn drop(&mut self) {
+        self.store.release(self.batch_id);
+    }
+}
+
+#[derive(Debug)]
+enum Entry {
+    InFlight {
+        fingerprint: [u8; 32],
+    },
+    Done {
+        fingerprint: [u8; 32],
+        response: Box<BatchResponse>,
+        expires_at: Instant,
+    },
+}
+
+/// Record of on-chain batches the proxy has executed
+#[derive(Debug)]
+pub struct ExecutedBatchStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<u64, Entry>>,
+}
+
+impl ExecutedBatchStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long the response of an executed batch is kept for replay
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Claim a batch for execution, or return its stored response
+    pub async fn admit(&self, batch_id: u64, fingerprint: [u8; 32]) -> ProxyResult<Admission<'_>> {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.retain(|_, entry| match entry {
+            Entry::InFlight { .. } => true,
+            Entry::Done { expires_at, .. } => *expires_at > now,
+        });
+
+        match entries.get(&batch_id) {
+            None => {
+                entries.insert(batch_id, Entry::InFlight { fingerprint });
+                Ok(Admission::Execute(BatchClaim {
+                    store: self,
+                    batch_id,
+                }))
+            }
+            Some(Entry::Done {
+                fingerprint: stored,
+                response,
+                ..
+            }) if *stored == fingerprint => Ok(Admission::Replay(response.clone())),
+            Some(Entry::InFlight { .. }) => Err(ProxyError::BatchReplay {
+                batch_id,
+                reason: "batch is already executing".to_string(),
+            }),
+            Some(Entry::Done { .. }) => Err(ProxyError::BatchReplay {
+                batch_id,
+                reason: "batch was already executed with a different query set".to_string(),
+            }),
+        }
+    }
+
+    /// Look up the stored response of an executed batch
+    pub async fn replay(&self, batch_id: u64, fingerprint: [u8; 32]) -> Option<BatchResponse> {
+        match self.lock().get(&batch_id) {
+            Some(Entry::Done {
+                fingerprint: stored,
+                response,
+                expires_at,
+            }) if *stored == fingerprint && *expires_at > Instant::now() => {
+                Some(response.as_ref().clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of batches held (including expired, not yet purged)
+    pub async fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether the store holds no batches
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Store the response of a claimed batch
+    fn complete(&self, batch_id: u64, response: BatchResponse) {
+        let mut entries = self.lock();
+        if let Some(Entry::InFlight { fingerprint }) = entries.get(&batch_id) {
+            let fingerprint = *fingerprint;
+            entries.insert(
+                batch_id,
+                Entry::Done {
+                    fingerprint,
+                    response: Box::new(response),
+                    expires_at: Instant::now() + self.ttl,
+                },
+            );
+        }
+    }
+
+    /// Release a claimed batch that was not completed, so it can be retried
+    fn release(&self, batch_id: u64) {
+        let mut entries = self.lock();
+        if matches!(entries.get(&batch_id), Some(Entry::InFlight { .. })) {
+            entries.remove(&batch_id);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for ExecutedBatchStore {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(DEFAULT_EXECUTED_BATCH_TTL_SECS),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::RpcMethod;
+    use crate::types::QueryResult;
+
+    const KEY_A: &str = "So11111111111111111111111111111111111111112";
+    const KEY_B: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    /// Query salted with its id, which is a hex digit in every test
+    fn query(id: &str, pubkey: &str) -> Query {
+        Query::new(id.to_string(), RpcMethod::GetBalance, pubkey.to_string())
+            .with_salt(id.repeat(64))
+    }
+
+    /// Claim a batch and store its response
+    async fn execute(store: &ExecutedBatchStore, batch_id: u64, fingerprint: [u8; 32]) {
+        match store.admit(batch_id, fingerprint).await {
+            Ok(Admission::Execute(claim)) => claim.complete(response()),
+            other => panic!("expected execution, got {:?}", other),
+        }
+    }
+
+    fn response() -> BatchResponse {
+        BatchResponse::from_results(
+            vec![QueryResult::success("1".to_string(), serde_json::json!(1))],
+            3,
+        )
+    }
+
+    #[test]
+    fn test_fingerprint_is_order_independent() {
+        let a = batch_fingerprint(&[query("1", KEY_A), query("2", KEY_B)]).unwrap();
+        let b = batch_fingerprint(&[query("2", KEY_B), query("1", KEY_A)]).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_fingerprint_binds_ids_to_queries() {
+        let a = batch_fingerprint(&[query("1", KEY_A), query("2", KEY_B)]).unwrap();
+        let swapped = batch_fingerprint(&[query("1", KEY_B), query("2", KEY_A)]).unwrap();
+        assert_ne!(a, swapped);
+    }
+
+    #[tokio::test]
+    async fn test_replays_same_query_set() {
+        let store = ExecutedBatchStore::new();
+        let fingerprint = [1u8; 32];
+
+        match store.admit(7, fingerprint).await {
+            Ok(Admission::Execute(claim)) => claim.complete(response()),
+            other => panic!("expected execution, got {:?}", other),
+        }
+
+        match store.admit(7, fingerprint).await {
+            Ok(Admission::Replay(stored)) => assert_eq!(stored.batch_hash, response().batch_hash),
+            other => panic!("expected replay, got {:?}", other),
+        }
+        assert!(store.replay(7, fingerprint).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_different_query_set() {
+        let store = ExecutedBatchStore::new();
+
+        execute(&store, 7, [1u8; 32]).await;
+
+        assert!(matches!(
+            store.admit(7, [2u8; 32]).await,
+            Err(ProxyError::BatchReplay { batch_id: 7, .. })
+        ));
+        assert!(store.replay(7, [2u8; 32]).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_concurrent_execution() {
+        let store = ExecutedBatchStore::new();
+
+        let _claim = store.admit(7, [1u8; 32]).await.unwrap();
+        assert!(matches!(
+            store.admit(7, [1u8; 32]).await,
+            Err(ProxyError::BatchReplay { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_claim_allows_retry() {
+        let store = ExecutedBatchStore::new();
+
+        drop(store.admit(7, [1u8; 32]).await.unwrap());
+
+        assert!(matches!(
+            store.admit(7, [1u8; 32]).await,
+            Ok(Admission::Execute(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_execution_releases_the_batch() {
+        let store = ExecutedBatchStore::new();
+
+        // A handler dropped mid-execution, as when its client disconnects
+        let execution = async {
+            let _claim = store.admit(7, [1u8; 32]).await.unwrap();
+            std::future::pending::<()>().await;
+        };
+        let abandoned = tokio::time::timeout(Duration::from_millis(10), execution).await;
+        assert!(abandoned.is_err());
+
+        assert!(matches!(
+            store.admit(7, [1u8; 32]).await,
+            Ok(Admission::Execute(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expired_responses_are_dropped() {
+        let store = ExecutedBatchStore::new().with_ttl(Duration::ZERO);
+
+        execute(&store, 7, [1u8; 32]).await;
+        assert!(store.replay(7, [1u8; 32]).await.is_none());
+
+        // The next admission purges the expired response
+        execute(&store, 8, [1u8; 32]).await;
+        assert_eq!(store.len().await, 1);
+    }
+}
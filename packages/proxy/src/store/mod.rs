@@ -0,0 +1,13 @@
+//! In-memory state kept across requests
+//!
+//! Each store is defined in its own file for modularity.
+
+mod executed_batches;
+mod result_store;
+
+pub use executed_batches::{
+    batch_fingerprint, Admission, BatchClaim, ExecutedBatchStore, DEFAULT_EXECUTED_BATCH_TTL_SECS,
+};
+pub use result_store::{
+    parse_ticket, ticket_hash, ResultStore, DEFAULT_RESULT_TTL_SECS, TICKET_LEN,
+};
@@ -0,0 +1,136 @@
+//! Per-submitter result storage for ticketed delivery
+//!
+//! A submitter picks a random 32-byte ticket and uses `SHA-256(ticket)` as the
+//! commit-reveal salt of its query. The salt is bound to the on-chain
+//! commitment, so whoever posts the batch cannot swap it, and it reveals
+//! nothing that allows retrieval: results are stored under the salt and only
+//! released to a caller presenting the ticket. Each result can be retrieved
+//! once, before it expires.
+
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::QueryResult;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Length in bytes of a result ticket
+pub const TICKET_LEN: usize = 32;
+
+/// Default time a result stays retrievable
+pub const DEFAULT_RESULT_TTL_SECS: u64 = 300;
+
+/// Parse a hex-encoded result ticket
+pub fn parse_ticket(ticket: &str) -> ProxyResult<[u8; TICKET_LEN]> {
+    hex::decode(ticket)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ProxyError::InvalidQuery("Malformed result ticket".to_string()))
+}
+
+/// Compute the salt a ticket commits to
+pub fn ticket_hash(ticket: &[u8; TICKET_LEN]) -> [u8; 32] {
+    Sha256::digest(ticket).into()
+}
+
+/// Results of executed batches, keyed by ticket hash
+#[derive(Debug)]
+pub struct ResultStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<[u8; 32], (QueryResult, Instant)>>,
+}
+
+impl ResultStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store a result for retrieval with the ticket hashing to `ticket_hash`
+    pub async fn insert(&self, ticket_hash: [u8; 32], result: QueryResult) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(ticket_hash, (result, now + self.ttl));
+    }
+
+    /// Retrieve and remove the result for a ticket
+    ///
+    /// Unknown, expired and already retrieved tickets are indistinguishable.
+    pub async fn take(&self, ticket: &[u8; TICKET_LEN]) -> Option<QueryResult> {
+        let (result, expires_at) = self.entries.lock().await.remove(&ticket_hash(ticket))?;
+        (expires_at > Instant::now()).then_some(result)
+    }
+
+    /// Number of results currently held (including expired, not yet purged)
+    pub async fn len(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+
+    /// Whether the store holds no results
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+impl Default for ResultStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_RESULT_TTL_SECS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> QueryResult {
+        QueryResult::success("1".to_string(), serde_json::json!(42))
+    }
+
+    #[tokio::test]
+    async fn test_result_retrieved_once() {
+        let store = ResultStore::default();
+        let ticket = [5u8; TICKET_LEN];
+
+        store.insert(ticket_hash(&ticket), result()).await;
+
+        let retrieved = store.take(&ticket).await.unwrap();
+        assert_eq!(retrieved.data, Some(serde_json::json!(42)));
+        assert!(store.take(&ticket).await.is_none());
+        assert!(store.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_salt_does_not_retrieve_result() {
+        let store = ResultStore::default();
+        let ticket = [5u8; TICKET_LEN];
+        let salt = ticket_hash(&ticket);
+
+        store.insert(salt, result()).await;
+
+        assert!(store.take(&salt).await.is_none());
+        assert!(store.take(&ticket).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_expired_result_not_returned() {
+        let store = ResultStore::new(Duration::ZERO);
+        let ticket = [5u8; TICKET_LEN];
+
+        store.insert(ticket_hash(&ticket), result()).await;
+
+        assert!(store.take(&ticket).await.is_none());
+    }
+
+    #[test]
+    fn test_parse_ticket() {
+        assert_eq!(
+            parse_ticket(&hex::encode([9u8; TICKET_LEN])).unwrap(),
+            [9u8; TICKET_LEN]
+        );
+        assert!(parse_ticket("zz").is_err());
+        assert!(parse_ticket(&hex::encode([9u8; 16])).is_err());
+    }
+}
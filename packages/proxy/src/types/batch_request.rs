@@ -17,6 +17,19 @@ pub struct BatchRequest {
     /// On-chain batch ID (for coordinated batches)
     #[serde(default)]
     pub batch_id: Option<String>,
+
+    /// Return results in random order instead of request order
+    #[serde(default)]
+    pub shuffle_results: bool,
+
+    /// Per-query timeout in milliseconds, if shorter than the proxy's
+    #[serde(default)]
+    pub query_timeout_ms: Option<u64>,
+
+    /// Deadline of the whole batch in milliseconds, if shorter than the
+    /// proxy's
+    #[serde(default)]
+    pub batch_timeout_ms: Option<u64>,
 }
 
 impl BatchRequest {
@@ -26,6 +39,9 @@ impl BatchRequest {
             queries,
             batch_hash: None,
             batch_id: None,
+            shuffle_results: false,
+            query_timeout_ms: None,
+            batch_timeout_ms: None,
         }
     }
 
@@ -35,6 +51,24 @@ impl BatchRequest {
         self
     }
 
+    /// Ask for the results in random order
+    pub fn with_shuffled_results(mut self) -> Self {
+        self.shuffle_results = true;
+        self
+    }
+
+    /// Give every query at most `timeout_ms` to complete
+    pub fn with_query_timeout(mut self, timeout_ms: u64) -> Self {
+        self.query_timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Return the batch after at most `timeout_ms`, partial if need be
+    pub fn with_batch_timeout(mut self, timeout_ms: u64) -> Self {
+        self.batch_timeout_ms = Some(timeout_ms);
+        self
+    }
+
     /// Get the number of queries in this batch
     pub fn len(&self) -> usize {
         self.queries.len()
@@ -71,6 +105,30 @@ mod tests {
         assert_eq!(request.batch_hash, Some("abc123".to_string()));
     }
 
+    #[test]
+    fn test_batch_request_shuffle_results_defaults_off() {
+        let request: BatchRequest = serde_json::from_str(r#"{ "queries": [] }"#).unwrap();
+        assert!(!request.shuffle_results);
+
+        let request: BatchRequest =
+            serde_json::from_str(r#"{ "queries": [], "shuffleResults": true }"#).unwrap();
+        assert!(request.shuffle_results);
+
+        let request = BatchRequest::new(vec![]).with_shuffled_results();
+        assert!(request.shuffle_results);
+    }
+
+    #[test]
+    fn test_batch_request_timeouts() {
+        let request: BatchRequest =
+            serde_json::from_str(r#"{ "queries": [], "batchTimeoutMs": 500 }"#).unwrap();
+        assert_eq!(request.query_timeout_ms, None);
+        assert_eq!(request.batch_timeout_ms, Some(500));
+
+        let request = BatchRequest::new(vec![]).with_query_timeout(100);
+        assert_eq!(request.query_timeout_ms, Some(100));
+    }
+
     #[test]
     fn test_batch_request_empty() {
         let request = BatchRequest::new(vec![]);
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 use super::QueryResult;
+use crate::crypto::EncryptedPayload;
+use crate::enums::{ResultDelivery, VerificationMode};
 
 /// Response from batch execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +26,17 @@ pub struct BatchResponse {
 
     /// Hash of the results batch
     pub batch_hash: String,
+
+    /// How the batch was verified against the coordinator before execution
+    pub verification_mode: VerificationMode,
+
+    /// How query results are delivered to their submitters
+    pub result_delivery: ResultDelivery,
+
+    /// Whether some queries missed their deadline and failed with a
+    /// timeout
+    #[serde(default)]
+    pub partial: bool,
 }
 
 impl BatchResponse {
@@ -34,13 +47,17 @@ impl BatchResponse {
         let succeeded_count = results.iter().filter(|r| r.success).count();
         let failed_count = results.len() - succeeded_count;
 
-        // Compute hash of results
+        // Compute hash of results; encrypted data is hashed by its decoded
+        // envelope so the digest does not depend on JSON formatting
         let mut hasher = Sha256::new();
         for result in &results {
             hasher.update(result.id.as_bytes());
             hasher.update(if result.success { b"1" } else { b"0" });
             if let Some(data) = &result.data {
-                hasher.update(data.to_string().as_bytes());
+                match EncryptedPayload::from_value(data) {
+                    Some(payload) => hasher.update(payload.canonical_bytes()),
+                    None => hasher.update(data.to_string().as_bytes()),
+                }
             }
         }
         let batch_hash = hex::encode(hasher.finalize());
@@ -52,8 +69,32 @@ impl BatchResponse {
             succeeded_count,
             failed_count,
             batch_hash,
+            verification_mode: VerificationMode::Uncoordinated,
+            result_delivery: ResultDelivery::Inline,
+            partial: false,
         }
     }
+
+    /// Withhold results for ticketed delivery
+    ///
+    /// Counts and `batch_hash` still describe the full batch.
+    pub fn into_ticketed(mut self) -> Self {
+        self.results.clear();
+        self.result_delivery = ResultDelivery::Ticket;
+        self
+    }
+
+    /// Mark the batch as returned with queries that timed out
+    pub fn with_partial(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    /// Set the verification mode used for this batch
+    pub fn with_verification_mode(mut self, mode: VerificationMode) -> Self {
+        self.verification_mode = mode;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -89,6 +130,56 @@ mod tests {
         assert_eq!(response.succeeded_count, 1);
         assert_eq!(response.failed_count, 1);
         assert!(!response.batch_hash.is_empty());
+        assert!(!response.partial);
+
+        let json = serde_json::to_value(response.with_partial(true)).unwrap();
+        assert_eq!(json["partial"], true);
+    }
+
+    #[test]
+    fn test_batch_response_verification_mode() {
+        let response = BatchResponse::from_results(vec![], 0);
+        assert_eq!(response.verification_mode, VerificationMode::Uncoordinated);
+
+        let response = response.with_verification_mode(VerificationMode::Onchain);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["verificationMode"], "onchain");
+    }
+
+    #[test]
+    fn test_batch_response_ticketed() {
+        let results = vec![QueryResult::success("1".to_string(), serde_json::json!({}))];
+        let inline = BatchResponse::from_results(results, 10);
+        assert_eq!(inline.result_delivery, ResultDelivery::Inline);
+
+        let ticketed = inline.clone().into_ticketed();
+        assert!(ticketed.results.is_empty());
+        assert_eq!(ticketed.result_delivery, ResultDelivery::Ticket);
+        assert_eq!(ticketed.succeeded_count, 1);
+        assert_eq!(ticketed.batch_hash, inline.batch_hash);
+    }
+
+    #[test]
+    fn test_batch_response_hashes_ciphertexts_deterministically() {
+        use crate::crypto::encrypt_result;
+        use x25519_dalek::{PublicKey, StaticSecret};
+
+        let public = PublicKey::from(&StaticSecret::from([1u8; 32]));
+        let payload = encrypt_result(&public, "1", &serde_json::json!(42)).unwrap();
+        let sealed = |payload: &EncryptedPayload| {
+            vec![QueryResult::success(
+                "1".to_string(),
+                serde_json::to_value(payload).unwrap(),
+            )]
+        };
+
+        let a = BatchResponse::from_results(sealed(&payload), 1);
+        let b = BatchResponse::from_results(sealed(&payload), 2);
+        assert_eq!(a.batch_hash, b.batch_hash);
+
+        let other = encrypt_result(&public, "1", &serde_json::json!(42)).unwrap();
+        let c = BatchResponse::from_results(sealed(&other), 1);
+        assert_ne!(a.batch_hash, c.batch_hash);
     }
 
     #[test]
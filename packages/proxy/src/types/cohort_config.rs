@@ -0,0 +1,98 @@
+//! Decoy cohort configuration
+
+/// Default number of batches a target must appear in to get a cohort
+pub const DEFAULT_COHORT_THRESHOLD: u32 = 3;
+
+/// Default number of decoys in the cohort of a recurring target
+pub const DEFAULT_COHORT_SIZE: usize = 8;
+
+/// Default upper bound on cohort decoys added to a single batch
+pub const DEFAULT_MAX_COHORT_DECOYS: usize = 32;
+
+/// Default counters per row of the recurrence sketch
+pub const DEFAULT_SKETCH_WIDTH: usize = 2048;
+
+/// Default rows of the recurrence sketch
+pub const DEFAULT_SKETCH_DEPTH: usize = 4;
+
+/// Default number of batches after which recurrence counts are halved
+pub const DEFAULT_SKETCH_DECAY_BATCHES: u64 = 10_000;
+
+/// Configuration of persistent decoy cohorts
+///
+/// Fresh random decoys do not survive an intersection attack: an observer
+/// intersecting the upstream batches that contain a recurring target is left
+/// with that target alone. A target seen in `threshold` batches or more is
+/// therefore always accompanied by the same cohort of decoys, which survives
+/// the intersection with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CohortConfig {
+    /// Batches a target must appear in before it gets a cohort
+    pub threshold: u32,
+
+    /// Decoys in the cohort of each recurring target
+    pub cohort_size: usize,
+
+    /// Upper bound on the cohort decoys added to a single batch
+    pub max_cohort_decoys: usize,
+
+    /// File holding the secret cohorts are derived from (None: a fresh secret
+    /// per process, so cohorts change across restarts)
+    pub key_path: Option<String>,
+
+    /// Counters per row of the recurrence sketch
+    pub sketch_width: usize,
+
+    /// Rows of the recurrence sketch
+    pub sketch_depth: usize,
+
+    /// Batches after which every recurrence count is halved (0: never)
+    pub decay_batches: u64,
+}
+
+impl CohortConfig {
+    /// Create a configuration giving targets seen in `threshold` batches a
+    /// cohort of `cohort_size` decoys
+    pub fn new(threshold: u32, cohort_size: usize) -> Self {
+        Self {
+            threshold,
+            cohort_size,
+            max_cohort_decoys: DEFAULT_MAX_COHORT_DECOYS,
+            key_path: None,
+            sketch_width: DEFAULT_SKETCH_WIDTH,
+            sketch_depth: DEFAULT_SKETCH_DEPTH,
+            decay_batches: DEFAULT_SKETCH_DECAY_BATCHES,
+        }
+    }
+
+    /// Set the upper bound on cohort decoys per batch
+    pub fn with_max_cohort_decoys(mut self, max_cohort_decoys: usize) -> Self {
+        self.max_cohort_decoys = max_cohort_decoys;
+        self
+    }
+
+    /// Derive cohorts from a secret persisted at `path`
+    pub fn with_key_path(mut self, path: String) -> Self {
+        self.key_path = Some(path);
+        self
+    }
+
+    /// Set the dimensions of the recurrence sketch
+    pub fn with_sketch_size(mut self, width: usize, depth: usize) -> Self {
+        self.sketch_width = width;
+        self.sketch_depth = depth;
+        self
+    }
+
+    /// Set the number of batches after which recurrence counts are halved
+    pub fn with_decay_batches(mut self, batches: u64) -> Self {
+        self.decay_batches = batches;
+        self
+    }
+}
+
+impl Default for CohortConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_COHORT_THRESHOLD, DEFAULT_COHORT_SIZE)
+    }
+}
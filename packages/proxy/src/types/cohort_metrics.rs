@@ -0,0 +1,29 @@
+//! Intersection guard metrics
+
+use serde::{Deserialize, Serialize};
+
+/// Counters of the intersection guard since startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CohortMetrics {
+    /// Batches a target must appear in before it gets a cohort
+    pub threshold: u32,
+
+    /// Batches inspected
+    pub batches: u64,
+
+    /// Recurring targets seen, counted once per batch
+    pub recurring_targets: u64,
+
+    /// Cohort decoys dispatched
+    pub cohort_decoys: u64,
+
+    /// Recurring targets sent without their full cohort
+    pub unprotected_targets: u64,
+
+    /// Batches in which at least one recurring target lacked its full cohort
+    pub degraded_batches: u64,
+
+    /// Whether the most recent batch was degraded
+    pub degraded: bool,
+}
@@ -1,5 +1,10 @@
 //! Configuration types
 
+use super::{
+    CohortConfig, ConsensusConfig, CoverTrafficConfig, DecoyConfig, FailoverConfig, MixerConfig,
+    ShardConfig,
+};
+
 /// Maximum number of queries allowed in a single batch
 pub const MAX_BATCH_SIZE: usize = 100;
 
@@ -9,26 +14,110 @@ pub const DEFAULT_K_ANONYMITY: usize = 10;
 /// Default server port
 pub const DEFAULT_PORT: u16 = 3000;
 
+/// Default maximum number of accounts a getMultipleAccounts query may ask for
+pub const DEFAULT_MAX_QUERY_ACCOUNTS: usize = 1000;
+
+/// Default maximum number of calls per upstream JSON-RPC batch request
+pub const DEFAULT_UPSTREAM_BATCH_MAX_CALLS: usize = 100;
+
+/// Default maximum number of upstream calls in flight across every batch
+pub const DEFAULT_MAX_IN_FLIGHT_CALLS: usize = 128;
+
+/// Default time a query may take once sent, in milliseconds
+pub const DEFAULT_QUERY_TIMEOUT_MS: u64 = 10_000;
+
+/// Default deadline of a whole batch, in milliseconds
+pub const DEFAULT_BATCH_TIMEOUT_MS: u64 = 30_000;
+
 /// Proxy server configuration
 #[derive(Debug, Clone)]
 pub struct ProxyConfig {
     /// Solana RPC endpoint URL
     pub rpc_url: String,
 
+    /// Further RPC endpoints queries fail over to
+    pub upstream_urls: Vec<String>,
+
+    /// Retry and circuit breaker settings of the upstream pool
+    pub failover: FailoverConfig,
+
     /// Server port
     pub port: u16,
 
     /// Minimum batch size for k-anonymity
     pub k_anonymity: usize,
 
+    /// Count distinct query targets instead of raw queries towards k
+    pub count_distinct_targets: bool,
+
     /// Maximum batch size
     pub max_batch_size: usize,
 
+    /// Maximum accounts per getMultipleAccounts query
+    pub max_query_accounts: usize,
+
+    /// Maximum HTTP requests to the RPC endpoints in flight at once, across
+    /// every batch
+    pub max_in_flight_calls: usize,
+
     /// Enable on-chain batch poller
     pub enable_poller: bool,
 
     /// Poll interval in milliseconds
     pub poll_interval_ms: Option<u64>,
+
+    /// Require every batch to reference a finalized on-chain batch
+    pub strict_coordination: bool,
+
+    /// Serve coordinated batch results per submitter via tickets, with this TTL
+    pub result_ttl_secs: Option<u64>,
+
+    /// Path to the executor keypair used to sign `complete_batch`
+    pub executor_keypair_path: Option<String>,
+
+    /// Directory holding the proxy's encryption keys
+    pub encryption_key_dir: Option<String>,
+
+    /// Rotation interval of the encryption keys in seconds (None: long-term key only)
+    pub key_rotation_secs: Option<u64>,
+
+    /// Server-side query mixing via `POST /query`
+    pub mixer: Option<MixerConfig>,
+
+    /// Decoy queries dispatched upstream alongside each batch
+    pub decoys: Option<DecoyConfig>,
+
+    /// Persistent decoy cohorts for targets recurring across batches
+    pub cohorts: Option<CohortConfig>,
+
+    /// Upstream batch sizes every batch is padded up to with decoys (empty: off)
+    pub batch_buckets: Vec<usize>,
+
+    /// Window the upstream calls of a batch are spread across, in milliseconds
+    pub dispatch_window_ms: u64,
+
+    /// Time a query may take once sent, in milliseconds (0: no limit)
+    pub query_timeout_ms: u64,
+
+    /// Time after which a batch is returned with the results it has, in
+    /// milliseconds (0: no limit)
+    pub batch_timeout_ms: u64,
+
+    /// Constant-rate cover traffic toward the upstream
+    pub cover_traffic: Option<CoverTrafficConfig>,
+
+    /// Split of the calls of each batch across the upstream pool
+    pub shards: Option<ShardConfig>,
+
+    /// Cross-provider check of selected queries
+    pub consensus: Option<ConsensusConfig>,
+
+    /// Send batches upstream as JSON-RPC batch arrays of at most this many calls
+    pub upstream_batch: Option<usize>,
+
+    /// Merge single-account queries into getMultipleAccounts calls of at most
+    /// this many accounts
+    pub coalesce_accounts: Option<usize>,
 }
 
 impl ProxyConfig {
@@ -36,11 +125,33 @@ impl ProxyConfig {
     pub fn new(rpc_url: String) -> Self {
         Self {
             rpc_url,
+            upstream_urls: Vec::new(),
+            failover: FailoverConfig::default(),
             port: DEFAULT_PORT,
             k_anonymity: DEFAULT_K_ANONYMITY,
+            count_distinct_targets: false,
             max_batch_size: MAX_BATCH_SIZE,
+            max_query_accounts: DEFAULT_MAX_QUERY_ACCOUNTS,
+            max_in_flight_calls: DEFAULT_MAX_IN_FLIGHT_CALLS,
             enable_poller: false,
             poll_interval_ms: None,
+            strict_coordination: false,
+            result_ttl_secs: None,
+            executor_keypair_path: None,
+            encryption_key_dir: None,
+            key_rotation_secs: None,
+            mixer: None,
+            decoys: None,
+            cohorts: None,
+            batch_buckets: Vec::new(),
+            dispatch_window_ms: 0,
+            query_timeout_ms: DEFAULT_QUERY_TIMEOUT_MS,
+            batch_timeout_ms: DEFAULT_BATCH_TIMEOUT_MS,
+            cover_traffic: None,
+            shards: None,
+            consensus: None,
+            upstream_batch: None,
+            coalesce_accounts: None,
         }
     }
 
@@ -56,23 +167,175 @@ impl ProxyConfig {
         self
     }
 
+    /// Count distinct query targets towards k-anonymity
+    pub fn with_distinct_targets(mut self, enabled: bool) -> Self {
+        self.count_distinct_targets = enabled;
+        self
+    }
+
+    /// Set the most accounts a getMultipleAccounts query may ask for
+    pub fn with_max_query_accounts(mut self, limit: usize) -> Self {
+        self.max_query_accounts = limit;
+        self
+    }
+
+    /// Set the most upstream requests in flight at once; further ones queue
+    pub fn with_max_in_flight_calls(mut self, limit: usize) -> Self {
+        self.max_in_flight_calls = limit;
+        self
+    }
+
     /// Enable the on-chain batch poller
     pub fn with_poller(mut self, interval_ms: u64) -> Self {
         self.enable_poller = true;
         self.poll_interval_ms = Some(interval_ms);
         self
     }
+
+    /// Require on-chain coordination for every batch (fail closed)
+    pub fn with_strict_coordination(mut self) -> Self {
+        self.strict_coordination = true;
+        self
+    }
+
+    /// Deliver coordinated batch results per submitter via tickets
+    pub fn with_ticketed_results(mut self, ttl_secs: u64) -> Self {
+        self.result_ttl_secs = Some(ttl_secs);
+        self
+    }
+
+    /// Set the executor keypair used to complete batches on-chain
+    pub fn with_executor_keypair(mut self, path: String) -> Self {
+        self.executor_keypair_path = Some(path);
+        self
+    }
+
+    /// Manage encryption keys in the given directory, rotating them every
+    /// `rotation_secs` if set
+    pub fn with_encryption_keys(mut self, dir: String, rotation_secs: Option<u64>) -> Self {
+        self.encryption_key_dir = Some(dir);
+        self.key_rotation_secs = rotation_secs;
+        self
+    }
+
+    /// Accept individual queries at `POST /query` and mix them server-side
+    pub fn with_mixer(mut self, mixer: MixerConfig) -> Self {
+        self.mixer = Some(mixer);
+        self
+    }
+
+    /// Pad batches with decoy queries before dispatching them upstream
+    pub fn with_decoys(mut self, decoys: DecoyConfig) -> Self {
+        self.decoys = Some(decoys);
+        self
+    }
+
+    /// Give targets recurring across batches a persistent decoy cohort
+    pub fn with_cohorts(mut self, cohorts: CohortConfig) -> Self {
+        self.cohorts = Some(cohorts);
+        self
+    }
+
+    /// Round every batch up to the next of these upstream batch sizes
+    pub fn with_batch_buckets(mut self, buckets: Vec<usize>) -> Self {
+        self.batch_buckets = buckets;
+        self
+    }
+
+    /// Spread the upstream calls of each batch across a window
+    pub fn with_dispatch_window(mut self, window_ms: u64) -> Self {
+        self.dispatch_window_ms = window_ms;
+        self
+    }
+
+    /// Fail queries still running `timeout_ms` after they are sent (0: never)
+    pub fn with_query_timeout(mut self, timeout_ms: u64) -> Self {
+        self.query_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Return batches after at most `timeout_ms`, partial if need be (0: never)
+    pub fn with_batch_timeout(mut self, timeout_ms: u64) -> Self {
+        self.batch_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Send upstream calls at a constant rate, filling idle slots with decoys
+    pub fn with_cover_traffic(mut self, cover_traffic: CoverTrafficConfig) -> Self {
+        self.cover_traffic = Some(cover_traffic);
+        self
+    }
+
+    /// Add RPC endpoints to the upstream pool
+    pub fn with_upstreams(mut self, upstream_urls: Vec<String>) -> Self {
+        self.upstream_urls = upstream_urls;
+        self
+    }
+
+    /// Set the retry and circuit breaker settings of the upstream pool
+    pub fn with_failover(mut self, failover: FailoverConfig) -> Self {
+        self.failover = failover;
+        self
+    }
+
+    /// Split the upstream calls of each batch across the upstream pool
+    pub fn with_shards(mut self, shards: ShardConfig) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    /// Check selected queries against several upstream providers
+    pub fn with_consensus(mut self, consensus: ConsensusConfig) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    /// Send each batch upstream as JSON-RPC batch arrays of at most
+    /// `max_calls` calls
+    pub fn with_upstream_batch(mut self, max_calls: usize) -> Self {
+        self.upstream_batch = Some(max_calls);
+        self
+    }
+
+    /// Merge single-account queries into getMultipleAccounts calls of at
+    /// most `max_accounts` accounts
+    pub fn with_coalescing(mut self, max_accounts: usize) -> Self {
+        self.coalesce_accounts = Some(max_accounts);
+        self
+    }
 }
 
 impl Default for ProxyConfig {
     fn default() -> Self {
         Self {
             rpc_url: String::new(),
+            upstream_urls: Vec::new(),
+            failover: FailoverConfig::default(),
             port: DEFAULT_PORT,
             k_anonymity: DEFAULT_K_ANONYMITY,
+            count_distinct_targets: false,
             max_batch_size: MAX_BATCH_SIZE,
+            max_query_accounts: DEFAULT_MAX_QUERY_ACCOUNTS,
+            max_in_flight_calls: DEFAULT_MAX_IN_FLIGHT_CALLS,
             enable_poller: false,
             poll_interval_ms: None,
+            strict_coordination: false,
+            result_ttl_secs: None,
+            executor_keypair_path: None,
+            encryption_key_dir: None,
+            key_rotation_secs: None,
+            mixer: None,
+            decoys: None,
+            cohorts: None,
+            batch_buckets: Vec::new(),
+            dispatch_window_ms: 0,
+            query_timeout_ms: DEFAULT_QUERY_TIMEOUT_MS,
+            batch_timeout_ms: DEFAULT_BATCH_TIMEOUT_MS,
+            cover_traffic: None,
+            shards: None,
+            consensus: None,
+            upstream_batch: None,
+            coalesce_accounts: None,
         }
     }
 }
@@ -95,10 +358,199 @@ mod tests {
     fn test_proxy_config_builder() {
         let config = ProxyConfig::new("http://localhost:8899".to_string())
             .with_port(8080)
-            .with_k_anonymity(5);
+            .with_k_anonymity(5)
+            .with_distinct_targets(true);
 
         assert_eq!(config.port, 8080);
         assert_eq!(config.k_anonymity, 5);
+        assert!(config.count_distinct_targets);
+    }
+
+    #[test]
+    fn test_proxy_config_max_query_accounts() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert_eq!(config.max_query_accounts, DEFAULT_MAX_QUERY_ACCOUNTS);
+
+        let config = config.with_max_query_accounts(250);
+        assert_eq!(config.max_query_accounts, 250);
+    }
+
+    #[test]
+    fn test_proxy_config_max_in_flight_calls() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert_eq!(config.max_in_flight_calls, DEFAULT_MAX_IN_FLIGHT_CALLS);
+
+        let config = config.with_max_in_flight_calls(16);
+        assert_eq!(config.max_in_flight_calls, 16);
+    }
+
+    #[test]
+    fn test_proxy_config_strict_coordination() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(!config.strict_coordination);
+
+        let config = config.with_poller(1000).with_strict_coordination();
+        assert!(config.strict_coordination);
+        assert!(config.enable_poller);
+    }
+
+    #[test]
+    fn test_proxy_config_ticketed_results() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.result_ttl_secs.is_none());
+
+        let config = config.with_ticketed_results(60);
+        assert_eq!(config.result_ttl_secs, Some(60));
+    }
+
+    #[test]
+    fn test_proxy_config_executor_keypair() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.executor_keypair_path.is_none());
+
+        let config = config.with_executor_keypair("/keys/executor.json".to_string());
+        assert_eq!(
+            config.executor_keypair_path.as_deref(),
+            Some("/keys/executor.json")
+        );
+    }
+
+    #[test]
+    fn test_proxy_config_encryption_keys() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.encryption_key_dir.is_none());
+
+        let config = config.with_encryption_keys("/keys/proxy".to_string(), Some(3600));
+        assert_eq!(config.encryption_key_dir.as_deref(), Some("/keys/proxy"));
+        assert_eq!(config.key_rotation_secs, Some(3600));
+    }
+
+    #[test]
+    fn test_proxy_config_mixer() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.mixer.is_none());
+
+        let config = config.with_mixer(MixerConfig::default().with_max_wait(500));
+        assert_eq!(config.mixer.map(|mixer| mixer.max_wait_ms), Some(500));
+    }
+
+    #[test]
+    fn test_proxy_config_decoys() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.decoys.is_none());
+
+        let config = config.with_decoys(DecoyConfig::new("corpus.json".to_string(), 16));
+        assert_eq!(config.decoys.map(|decoys| decoys.target_size), Some(16));
+    }
+
+    #[test]
+    fn test_proxy_config_cohorts() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.cohorts.is_none());
+
+        let config = config.with_cohorts(CohortConfig::new(5, 4));
+        assert_eq!(
+            config
+                .cohorts
+                .map(|cohorts| (cohorts.threshold, cohorts.cohort_size)),
+            Some((5, 4))
+        );
+    }
+
+    #[test]
+    fn test_proxy_config_batch_buckets() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.batch_buckets.is_empty());
+
+        let config = config.with_batch_buckets(vec![8, 16, 32, 64]);
+        assert_eq!(config.batch_buckets, [8, 16, 32, 64]);
+    }
+
+    #[test]
+    fn test_proxy_config_dispatch_window() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert_eq!(config.dispatch_window_ms, 0);
+
+        let config = config.with_dispatch_window(250);
+        assert_eq!(config.dispatch_window_ms, 250);
+    }
+
+    #[test]
+    fn test_proxy_config_timeouts() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert_eq!(config.query_timeout_ms, DEFAULT_QUERY_TIMEOUT_MS);
+        assert_eq!(config.batch_timeout_ms, DEFAULT_BATCH_TIMEOUT_MS);
+
+        let config = config.with_query_timeout(0).with_batch_timeout(5_000);
+        assert_eq!(config.query_timeout_ms, 0);
+        assert_eq!(config.batch_timeout_ms, 5_000);
+    }
+
+    #[test]
+    fn test_proxy_config_cover_traffic() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.cover_traffic.is_none());
+
+        let config = config.with_cover_traffic(CoverTrafficConfig::new(20));
+        assert_eq!(
+            config.cover_traffic.map(|cover| cover.calls_per_sec),
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn test_proxy_config_shards() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.shards.is_none());
+
+        let config = config.with_shards(ShardConfig::default().with_max_fraction(0.75));
+        assert_eq!(config.shards.map(|shards| shards.max_fraction), Some(0.75));
+    }
+
+    #[test]
+    fn test_proxy_config_consensus() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.consensus.is_none());
+
+        let config = config.with_consensus(ConsensusConfig::new(0.1).with_providers(3));
+        assert_eq!(
+            config
+                .consensus
+                .map(|consensus| (consensus.sample_rate, consensus.providers)),
+            Some((0.1, 3))
+        );
+    }
+
+    #[test]
+    fn test_proxy_config_upstream_batch() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.upstream_batch.is_none());
+
+        let config = config.with_upstream_batch(DEFAULT_UPSTREAM_BATCH_MAX_CALLS);
+        assert_eq!(config.upstream_batch, Some(100));
+    }
+
+    #[test]
+    fn test_proxy_config_coalescing() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.coalesce_accounts.is_none());
+
+        let config = config.with_coalescing(50);
+        assert_eq!(config.coalesce_accounts, Some(50));
+    }
+
+    #[test]
+    fn test_proxy_config_upstreams() {
+        let config = ProxyConfig::new("http://localhost:8899".to_string());
+        assert!(config.upstream_urls.is_empty());
+        assert_eq!(config.failover, FailoverConfig::default());
+
+        let urls = vec!["http://a".to_string(), "http://b".to_string()];
+        let config = config
+            .with_upstreams(urls.clone())
+            .with_failover(FailoverConfig::default().with_max_attempts(5));
+        assert_eq!(config.upstream_urls, urls);
+        assert_eq!(config.failover.max_attempts, 5);
     }
 
     #[test]
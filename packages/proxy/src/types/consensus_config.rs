@@ -0,0 +1,56 @@
+//! Result consensus configuration
+
+use crate::enums::{QuorumPolicy, RpcMethod};
+
+/// Default number of providers a verified query is sent to
+pub const DEFAULT_CONSENSUS_PROVIDERS: usize = 2;
+
+/// Configuration of cross-provider result consensus
+///
+/// Verified queries are sent to several upstreams of the pool and their
+/// results compared. Queries of the listed methods are always verified;
+/// others are verified with probability `sample_rate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsensusConfig {
+    /// Providers each verified query is sent to
+    pub providers: usize,
+
+    /// Fraction of the other queries that are verified
+    pub sample_rate: f64,
+
+    /// Methods whose queries are always verified
+    pub methods: Vec<RpcMethod>,
+
+    /// Which result is returned
+    pub policy: QuorumPolicy,
+}
+
+impl ConsensusConfig {
+    /// Create a configuration verifying a `sample_rate` fraction of queries
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            providers: DEFAULT_CONSENSUS_PROVIDERS,
+            sample_rate,
+            methods: Vec::new(),
+            policy: QuorumPolicy::default(),
+        }
+    }
+
+    /// Set the number of providers each verified query is sent to
+    pub fn with_providers(mut self, providers: usize) -> Self {
+        self.providers = providers;
+        self
+    }
+
+    /// Always verify queries of these methods
+    pub fn with_methods(mut self, methods: Vec<RpcMethod>) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Set the quorum policy
+    pub fn with_policy(mut self, policy: QuorumPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
@@ -0,0 +1,23 @@
+//! Result consensus metrics
+
+use serde::{Deserialize, Serialize};
+
+/// Counters of cross-provider result consensus since startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusMetrics {
+    /// Queries sent to several providers
+    pub checked: u64,
+
+    /// Checked queries on which every answering provider agreed
+    pub agreed: u64,
+
+    /// Checked queries on which providers disagreed
+    pub disagreed: u64,
+
+    /// Checked queries fewer than two providers answered
+    pub unverified: u64,
+
+    /// Disagreements the quorum policy could not resolve, failing the query
+    pub quorum_failures: u64,
+}
@@ -0,0 +1,64 @@
+//! Cover traffic configuration
+
+use std::time::Duration;
+
+/// Default maximum number of real calls waiting for a cover traffic slot
+pub const DEFAULT_COVER_QUEUE_SIZE: usize = 1000;
+
+/// Configuration of the constant-rate cover traffic emitter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverTrafficConfig {
+    /// Upstream calls emitted per second, real or decoy
+    pub calls_per_sec: u32,
+
+    /// Approximate upstream bytes per second decoys may use (None: unlimited)
+    pub max_bytes_per_sec: Option<u64>,
+
+    /// Maximum number of real calls waiting for a slot
+    pub max_queue: usize,
+}
+
+impl CoverTrafficConfig {
+    /// Create a configuration emitting `calls_per_sec` upstream calls
+    pub fn new(calls_per_sec: u32) -> Self {
+        Self {
+            calls_per_sec,
+            max_bytes_per_sec: None,
+            max_queue: DEFAULT_COVER_QUEUE_SIZE,
+        }
+    }
+
+    /// Set the bandwidth budget
+    pub fn with_max_bytes_per_sec(mut self, max_bytes_per_sec: u64) -> Self {
+        self.max_bytes_per_sec = Some(max_bytes_per_sec);
+        self
+    }
+
+    /// Set the queue capacity
+    pub fn with_max_queue(mut self, max_queue: usize) -> Self {
+        self.max_queue = max_queue;
+        self
+    }
+
+    /// Time between two slots
+    pub fn slot_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.calls_per_sec.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_interval() {
+        assert_eq!(
+            CoverTrafficConfig::new(4).slot_interval(),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            CoverTrafficConfig::new(0).slot_interval(),
+            Duration::from_secs(1)
+        );
+    }
+}
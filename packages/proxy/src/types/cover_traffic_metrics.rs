@@ -0,0 +1,32 @@
+//! Cover traffic metrics
+
+use serde::{Deserialize, Serialize};
+
+/// Counters of the cover traffic emitter since startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverTrafficMetrics {
+    /// Configured upstream calls per second
+    pub calls_per_sec: u32,
+
+    /// Slots elapsed
+    pub slots: u64,
+
+    /// Slots used by real queries
+    pub real_calls: u64,
+
+    /// Slots used by decoys
+    pub decoy_calls: u64,
+
+    /// Empty slots left unused because the bandwidth budget was exhausted
+    pub skipped_slots: u64,
+
+    /// Approximate upstream bytes of completed calls (JSON request and result)
+    pub bytes: u64,
+
+    /// Real calls currently waiting for a slot
+    pub queue_depth: usize,
+
+    /// Longest time a real call waited for its slot, in milliseconds
+    pub max_queue_wait_ms: u64,
+}
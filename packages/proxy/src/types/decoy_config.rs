@@ -0,0 +1,134 @@
+//! Decoy query configuration
+
+use super::MAX_BATCH_SIZE;
+use crate::enums::{RpcMethod, SamplingDistribution};
+
+/// Default Zipf exponent for [`SamplingDistribution::Zipf`]
+pub const DEFAULT_DECOY_ZIPF_EXPONENT: f64 = 1.0;
+
+/// Methods decoys can be generated for, each paired with its default share
+/// of the decoy mix
+pub const DEFAULT_DECOY_METHOD_MIX: &[(RpcMethod, f64)] = &[
+    (RpcMethod::GetBalance, 2.0),
+    (RpcMethod::GetAccountInfo, 2.0),
+    (RpcMethod::GetTokenAccountBalance, 1.0),
+];
+
+/// Configuration of decoy query injection
+///
+/// Decoys are dispatched upstream alongside the real queries of a batch and
+/// their results are discarded. They add to what the upstream sees, never to
+/// the anonymity set k is checked against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecoyConfig {
+    /// Path to the JSON corpus of decoy accounts and token accounts
+    pub corpus_path: String,
+
+    /// Number of upstream calls (real plus decoy) each batch is padded up to
+    pub target_size: usize,
+
+    /// Decoys added to every batch, even one already at the target size
+    pub min_decoys: usize,
+
+    /// Upper bound on the decoys added to a single batch
+    pub max_decoys: usize,
+
+    /// How decoy targets are drawn from the corpus
+    pub distribution: SamplingDistribution,
+
+    /// Exponent of the rank distribution under [`SamplingDistribution::Zipf`]
+    pub zipf_exponent: f64,
+
+    /// Relative share of each method among decoys
+    pub method_mix: Vec<(RpcMethod, f64)>,
+}
+
+impl DecoyConfig {
+    /// Create a configuration padding batches up to `target_size` calls with
+    /// decoys from the corpus at `corpus_path`
+    pub fn new(corpus_path: String, target_size: usize) -> Self {
+        Self {
+            corpus_path,
+            target_size,
+            min_decoys: 0,
+            max_decoys: MAX_BATCH_SIZE,
+            distribution: SamplingDistribution::default(),
+            zipf_exponent: DEFAULT_DECOY_ZIPF_EXPONENT,
+            method_mix: DEFAULT_DECOY_METHOD_MIX.to_vec(),
+        }
+    }
+
+    /// Set the bounds on the number of decoys per batch
+    pub fn with_decoy_bounds(mut self, min_decoys: usize, max_decoys: usize) -> Self {
+        self.min_decoys = min_decoys;
+        self.max_decoys = max_decoys;
+        self
+    }
+
+    /// Set the sampling distribution
+    pub fn with_distribution(mut self, distribution: SamplingDistribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+
+    /// Set the Zipf exponent
+    pub fn with_zipf_exponent(mut self, exponent: f64) -> Self {
+        self.zipf_exponent = exponent;
+        self
+    }
+
+    /// Set the per-method mix
+    pub fn with_method_mix(mut self, method_mix: Vec<(RpcMethod, f64)>) -> Self {
+        self.method_mix = method_mix;
+        self
+    }
+
+    /// Number of decoys to add to a batch of `real_count` queries
+    pub fn decoy_count(&self, real_count: usize) -> usize {
+        self.target_size
+            .saturating_sub(real_count)
+            .max(self.min_decoys)
+            .min(self.max_decoys)
+    }
+
+    /// Parse a method mix such as `getBalance:2,getAccountInfo:1`
+    pub fn parse_method_mix(s: &str) -> Option<Vec<(RpcMethod, f64)>> {
+        s.split(',')
+            .map(|entry| {
+                let (method, weight) = entry.trim().split_once(':')?;
+                let method = RpcMethod::from_str(method.trim())?;
+                let weight = weight.trim().parse::<f64>().ok()?;
+                Some((method, weight))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoy_count_pads_to_target_within_bounds() {
+        let config = DecoyConfig::new("corpus.json".to_string(), 10);
+        assert_eq!(config.decoy_count(3), 7);
+        assert_eq!(config.decoy_count(12), 0);
+
+        let config = config.with_decoy_bounds(2, 5);
+        assert_eq!(config.decoy_count(3), 5);
+        assert_eq!(config.decoy_count(12), 2);
+    }
+
+    #[test]
+    fn test_parse_method_mix() {
+        assert_eq!(
+            DecoyConfig::parse_method_mix("getBalance:3, getAccountInfo:0.5"),
+            Some(vec![
+                (RpcMethod::GetBalance, 3.0),
+                (RpcMethod::GetAccountInfo, 0.5),
+            ])
+        );
+        assert_eq!(DecoyConfig::parse_method_mix("getBalance"), None);
+        assert_eq!(DecoyConfig::parse_method_mix("getSlot:1"), None);
+    }
+}
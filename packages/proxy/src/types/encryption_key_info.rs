@@ -0,0 +1,70 @@
+//! Public description of a proxy encryption key
+
+use crate::enums::KeyKind;
+use serde::{Deserialize, Serialize};
+
+/// A proxy encryption key as served at `GET /keys`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionKeyInfo {
+    /// Long-term or rotating key
+    pub kind: KeyKind,
+
+    /// Hex-encoded X25519 public key
+    pub public_key: String,
+
+    /// Unix timestamp (seconds) from which the key is valid
+    pub not_before: i64,
+
+    /// Unix timestamp (seconds) at which the key expires, if it does
+    pub not_after: Option<i64>,
+}
+
+impl EncryptionKeyInfo {
+    /// Whether the key is valid at the given Unix timestamp
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        self.not_before <= now && self.not_after.is_none_or(|not_after| now < not_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validity_window() {
+        let key = EncryptionKeyInfo {
+            kind: KeyKind::Rotating,
+            public_key: "00".repeat(32),
+            not_before: 100,
+            not_after: Some(200),
+        };
+
+        assert!(!key.is_valid_at(99));
+        assert!(key.is_valid_at(100));
+        assert!(key.is_valid_at(199));
+        assert!(!key.is_valid_at(200));
+
+        let long_term = EncryptionKeyInfo {
+            kind: KeyKind::LongTerm,
+            not_after: None,
+            ..key
+        };
+        assert!(long_term.is_valid_at(i64::MAX));
+    }
+
+    #[test]
+    fn test_serialization_uses_camel_case() {
+        let key = EncryptionKeyInfo {
+            kind: KeyKind::LongTerm,
+            public_key: "ab".repeat(32),
+            not_before: 1,
+            not_after: None,
+        };
+
+        let json = serde_json::to_value(&key).unwrap();
+        assert_eq!(json["kind"], "longTerm");
+        assert_eq!(json["notBefore"], 1);
+        assert!(json["notAfter"].is_null());
+    }
+}
@@ -0,0 +1,139 @@
+//! Upstream failover configuration
+
+use crate::enums::RpcMethod;
+use std::collections::HashMap;
+
+/// Default upstream attempts per query
+pub const DEFAULT_FAILOVER_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry, in milliseconds
+pub const DEFAULT_FAILOVER_BACKOFF_MS: u64 = 50;
+
+/// Default cap on the delay between retries, in milliseconds
+pub const DEFAULT_FAILOVER_MAX_BACKOFF_MS: u64 = 1000;
+
+/// Default consecutive failures that open an upstream's circuit
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default time an opened circuit stays open, in milliseconds
+pub const DEFAULT_CIRCUIT_COOLDOWN_MS: u64 = 1000;
+
+/// Default cap on the cooldown of a circuit that keeps reopening, in milliseconds
+pub const DEFAULT_CIRCUIT_MAX_COOLDOWN_MS: u64 = 60_000;
+
+/// Configuration of per-query failover across the upstream pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailoverConfig {
+    /// Upstream attempts per query, the first one included
+    pub max_attempts: u32,
+
+    /// Attempts for methods that do not use `max_attempts`
+    pub method_attempts: HashMap<RpcMethod, u32>,
+
+    /// Delay before the first retry, doubled for every further one
+    pub backoff_ms: u64,
+
+    /// Cap on the delay between retries
+    pub max_backoff_ms: u64,
+
+    /// Consecutive failures after which an upstream's circuit opens
+    pub failure_threshold: u32,
+
+    /// Time an opened circuit stays open, doubled every time it reopens
+    pub cooldown_ms: u64,
+
+    /// Cap on the cooldown
+    pub max_cooldown_ms: u64,
+}
+
+impl FailoverConfig {
+    /// Set the attempts per query
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the attempts per query of `method`
+    pub fn with_method_attempts(mut self, method: RpcMethod, attempts: u32) -> Self {
+        self.method_attempts.insert(method, attempts);
+        self
+    }
+
+    /// Upstream attempts a query of `method` gets
+    pub fn attempts_for(&self, method: RpcMethod) -> u32 {
+        self.method_attempts
+            .get(&method)
+            .copied()
+            .unwrap_or(self.max_attempts)
+    }
+
+    /// Parse retry budgets such as `getTransaction:5,getBlockHeight:1`
+    pub fn parse_method_attempts(s: &str) -> Option<Vec<(RpcMethod, u32)>> {
+        s.split(',')
+            .map(|entry| {
+                let (method, attempts) = entry.trim().split_once(':')?;
+                let method = RpcMethod::from_str(method.trim())?;
+                let attempts = attempts.trim().parse::<u32>().ok()?;
+                Some((method, attempts))
+            })
+            .collect()
+    }
+
+    /// Set the retry backoff
+    pub fn with_backoff(mut self, backoff_ms: u64, max_backoff_ms: u64) -> Self {
+        self.backoff_ms = backoff_ms;
+        self.max_backoff_ms = max_backoff_ms;
+        self
+    }
+
+    /// Set the circuit breaker thresholds
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown_ms: u64) -> Self {
+        self.failure_threshold = failure_threshold;
+        self.cooldown_ms = cooldown_ms;
+        self
+    }
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_FAILOVER_ATTEMPTS,
+            method_attempts: HashMap::new(),
+            backoff_ms: DEFAULT_FAILOVER_BACKOFF_MS,
+            max_backoff_ms: DEFAULT_FAILOVER_MAX_BACKOFF_MS,
+            failure_threshold: DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+            cooldown_ms: DEFAULT_CIRCUIT_COOLDOWN_MS,
+            max_cooldown_ms: DEFAULT_CIRCUIT_MAX_COOLDOWN_MS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_attempts_override_default() {
+        let config = FailoverConfig::default()
+            .with_max_attempts(2)
+            .with_method_attempts(RpcMethod::GetTransaction, 5);
+        assert_eq!(config.attempts_for(RpcMethod::GetTransaction), 5);
+        assert_eq!(config.attempts_for(RpcMethod::GetBalance), 2);
+    }
+
+    #[test]
+    fn test_parse_method_attempts() {
+        assert_eq!(
+            FailoverConfig::parse_method_attempts("getTransaction:5, getBlockHeight:1"),
+            Some(vec![
+                (RpcMethod::GetTransaction, 5),
+                (RpcMethod::GetBlockHeight, 1),
+            ])
+        );
+        assert_eq!(
+            FailoverConfig::parse_method_attempts("getTransaction:-1"),
+            None
+        );
+        assert_eq!(FailoverConfig::parse_method_attempts("getSlot:2"), None);
+    }
+}
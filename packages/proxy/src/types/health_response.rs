@@ -1,5 +1,6 @@
 //! Health check response type
 
+use super::UpstreamStatus;
 use serde::{Deserialize, Serialize};
 
 /// Health check response
@@ -14,6 +15,10 @@ pub struct HealthResponse {
 
     /// Whether the RPC connection is healthy
     pub rpc_healthy: bool,
+
+    /// State of every upstream RPC endpoint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upstreams: Vec<UpstreamStatus>,
 }
 
 impl HealthResponse {
@@ -32,7 +37,22 @@ impl HealthResponse {
             },
             version: env!("CARGO_PKG_VERSION").to_string(),
             rpc_healthy,
+            upstreams: Vec::new(),
+        }
+    }
+
+    /// Create a health response from the state of every upstream
+    ///
+    /// Queries fail over, so the RPC connection is healthy while any upstream
+    /// is; the service is degraded as soon as one is not.
+    pub fn from_upstreams(upstreams: Vec<UpstreamStatus>) -> Self {
+        let rpc_healthy = upstreams.iter().any(|upstream| upstream.healthy);
+        let mut response = Self::new(rpc_healthy);
+        if !upstreams.iter().all(|upstream| upstream.healthy) {
+            response.status = Self::STATUS_DEGRADED.to_string();
         }
+        response.upstreams = upstreams;
+        response
     }
 }
 
@@ -57,6 +77,36 @@ mod tests {
         assert!(!response.rpc_healthy);
     }
 
+    #[test]
+    fn test_health_response_from_upstreams() {
+        let upstream = |url: &str, healthy: bool| UpstreamStatus {
+            url: url.to_string(),
+            healthy,
+            circuit: Default::default(),
+            latency_ms: None,
+            error_rate: 0.0,
+            consecutive_failures: 0,
+            calls: 0,
+            failures: 0,
+        };
+
+        let response = HealthResponse::from_upstreams(vec![upstream("http://a", true)]);
+        assert_eq!(response.status, "healthy");
+        assert!(response.rpc_healthy);
+
+        let response = HealthResponse::from_upstreams(vec![
+            upstream("http://a", true),
+            upstream("http://b", false),
+        ]);
+        assert_eq!(response.status, "degraded");
+        assert!(response.rpc_healthy);
+        assert_eq!(response.upstreams.len(), 2);
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["upstreams"][1]["url"], "http://b");
+        assert_eq!(json["upstreams"][1]["circuit"], "closed");
+    }
+
     #[test]
     fn test_health_response_constants() {
         assert_eq!(HealthResponse::STATUS_HEALTHY, "healthy");
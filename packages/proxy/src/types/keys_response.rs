@@ -0,0 +1,18 @@
+//! Encryption keys response type
+
+use super::EncryptionKeyInfo;
+use serde::{Deserialize, Serialize};
+
+/// Response of `GET /keys`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeysResponse {
+    /// Hex-encoded fingerprint of `keys`, as published on-chain
+    pub fingerprint: String,
+
+    /// Hex-encoded X25519 public key clients should encrypt to
+    pub current_key: String,
+
+    /// Active keys: long-term first, then rotating keys oldest first
+    pub keys: Vec<EncryptionKeyInfo>,
+}
@@ -0,0 +1,25 @@
+//! Metrics response type
+
+use super::{CohortMetrics, ConsensusMetrics, CoverTrafficMetrics, UpstreamMetrics};
+use serde::{Deserialize, Serialize};
+
+/// Upstream call slot usage and counters of the enabled traffic-shaping
+/// features
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsResponse {
+    /// Global upstream call slots and their queue
+    pub upstream: UpstreamMetrics,
+
+    /// Constant-rate emitter counters (cover traffic enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_traffic: Option<CoverTrafficMetrics>,
+
+    /// Intersection guard counters (cohorts enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohorts: Option<CohortMetrics>,
+
+    /// Cross-provider result consensus counters (consensus enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<ConsensusMetrics>,
+}
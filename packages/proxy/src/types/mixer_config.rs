@@ -0,0 +1,84 @@
+//! Query mixer configuration
+
+use super::MAX_BATCH_SIZE;
+use crate::enums::DeadlinePolicy;
+use std::time::Duration;
+
+/// Default time the mixer waits for k clients
+pub const DEFAULT_MIXER_MAX_WAIT_MS: u64 = 2000;
+
+/// Default extra time granted by [`DeadlinePolicy::Wait`]
+pub const DEFAULT_MIXER_EXTENSION_MS: u64 = 10_000;
+
+/// Configuration of server-side query mixing (`POST /query`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixerConfig {
+    /// Time after the first pooled query at which the deadline policy applies
+    pub max_wait_ms: u64,
+
+    /// Behaviour when the deadline passes with fewer than k clients
+    pub deadline_policy: DeadlinePolicy,
+
+    /// Extra time the pool stays open under [`DeadlinePolicy::Wait`]
+    pub max_extension_ms: u64,
+
+    /// Maximum number of queries held in the pool; further queries are refused
+    pub max_pool_size: usize,
+}
+
+impl MixerConfig {
+    /// Set the deadline
+    pub fn with_max_wait(mut self, max_wait_ms: u64) -> Self {
+        self.max_wait_ms = max_wait_ms;
+        self
+    }
+
+    /// Set the deadline policy
+    pub fn with_deadline_policy(mut self, policy: DeadlinePolicy) -> Self {
+        self.deadline_policy = policy;
+        self
+    }
+
+    /// Set the extension granted by [`DeadlinePolicy::Wait`]
+    pub fn with_max_extension(mut self, max_extension_ms: u64) -> Self {
+        self.max_extension_ms = max_extension_ms;
+        self
+    }
+
+    /// Time after the first pooled query at which the pool is resolved
+    pub fn expiry(&self) -> Duration {
+        match self.deadline_policy {
+            DeadlinePolicy::Wait => {
+                Duration::from_millis(self.max_wait_ms.saturating_add(self.max_extension_ms))
+            }
+            DeadlinePolicy::Pad | DeadlinePolicy::Reject => Duration::from_millis(self.max_wait_ms),
+        }
+    }
+}
+
+impl Default for MixerConfig {
+    fn default() -> Self {
+        Self {
+            max_wait_ms: DEFAULT_MIXER_MAX_WAIT_MS,
+            deadline_policy: DeadlinePolicy::default(),
+            max_extension_ms: DEFAULT_MIXER_EXTENSION_MS,
+            max_pool_size: MAX_BATCH_SIZE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_includes_extension_only_when_waiting() {
+        let config = MixerConfig::default()
+            .with_max_wait(100)
+            .with_max_extension(400);
+        assert_eq!(config.expiry(), Duration::from_millis(100));
+
+        let config = config.with_deadline_policy(DeadlinePolicy::Wait);
+        assert_eq!(config.expiry(), Duration::from_millis(500));
+    }
+}
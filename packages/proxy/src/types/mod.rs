@@ -4,14 +4,55 @@
 
 mod batch_request;
 mod batch_response;
+mod cohort_config;
+mod cohort_metrics;
 mod config;
+mod consensus_config;
+mod consensus_metrics;
+mod cover_traffic_config;
+mod cover_traffic_metrics;
+mod decoy_config;
+mod encryption_key_info;
+mod failover_config;
 mod health_response;
+mod keys_response;
+mod metrics_response;
+mod mixer_config;
 mod query;
 mod query_result;
+mod shard_config;
+mod upstream_metrics;
+mod upstream_status;
 
 pub use batch_request::BatchRequest;
 pub use batch_response::BatchResponse;
-pub use config::{ProxyConfig, DEFAULT_K_ANONYMITY, DEFAULT_PORT, MAX_BATCH_SIZE};
+pub use cohort_config::{
+    CohortConfig, DEFAULT_COHORT_SIZE, DEFAULT_COHORT_THRESHOLD, DEFAULT_MAX_COHORT_DECOYS,
+    DEFAULT_SKETCH_DECAY_BATCHES, DEFAULT_SKETCH_DEPTH, DEFAULT_SKETCH_WIDTH,
+};
+pub use cohort_metrics::CohortMetrics;
+pub use config::{
+    ProxyConfig, DEFAULT_BATCH_TIMEOUT_MS, DEFAULT_K_ANONYMITY, DEFAULT_MAX_IN_FLIGHT_CALLS,
+    DEFAULT_MAX_QUERY_ACCOUNTS, DEFAULT_PORT, DEFAULT_QUERY_TIMEOUT_MS,
+    DEFAULT_UPSTREAM_BATCH_MAX_CALLS, MAX_BATCH_SIZE,
+};
+pub use consensus_config::{ConsensusConfig, DEFAULT_CONSENSUS_PROVIDERS};
+pub use consensus_metrics::ConsensusMetrics;
+pub use cover_traffic_config::{CoverTrafficConfig, DEFAULT_COVER_QUEUE_SIZE};
+pub use cover_traffic_metrics::CoverTrafficMetrics;
+pub use decoy_config::{DecoyConfig, DEFAULT_DECOY_METHOD_MIX, DEFAULT_DECOY_ZIPF_EXPONENT};
+pub use encryption_key_info::EncryptionKeyInfo;
+pub use failover_config::{
+    FailoverConfig, DEFAULT_CIRCUIT_COOLDOWN_MS, DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+    DEFAULT_CIRCUIT_MAX_COOLDOWN_MS, DEFAULT_FAILOVER_ATTEMPTS, DEFAULT_FAILOVER_BACKOFF_MS,
+    DEFAULT_FAILOVER_MAX_BACKOFF_MS,
+};
 pub use health_response::HealthResponse;
+pub use keys_response::KeysResponse;
+pub use metrics_response::MetricsResponse;
+pub use mixer_config::{MixerConfig, DEFAULT_MIXER_EXTENSION_MS, DEFAULT_MIXER_MAX_WAIT_MS};
 pub use query::Query;
 pub use query_result::QueryResult;
+pub use shard_config::{ShardConfig, DEFAULT_SHARD_MAX_FRACTION};
+pub use upstream_metrics::UpstreamMetrics;
+pub use upstream_status::UpstreamStatus;
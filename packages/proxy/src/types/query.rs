@@ -2,9 +2,14 @@
 
 use serde::{Deserialize, Serialize};
 use crate::enums::RpcMethod;
+use crate::error::ProxyResult;
+use crate::hashing::{commitment_hash, query_hash};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::str::FromStr;
 
 /// A single query in a batch request
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Query {
     /// Unique identifier for this query (for result mapping)
     pub id: String,
@@ -23,6 +28,14 @@ pub struct Query {
     /// Optional commitment level (defaults to "confirmed")
     #[serde(default)]
     pub commitment: Option<String>,
+
+    /// Hex-encoded commit-reveal salt (for salted on-chain commitments)
+    #[serde(default)]
+    pub salt: Option<String>,
+
+    /// Hex-encoded X25519 public key to encrypt this query's result to
+    #[serde(default)]
+    pub encryption_key: Option<String>,
 }
 
 impl Query {
@@ -34,6 +47,8 @@ impl Query {
             pubkey: Some(pubkey),
             params: None,
             commitment: None,
+            salt: None,
+            encryption_key: None,
         }
     }
 
@@ -45,6 +60,8 @@ impl Query {
             pubkey: None,
             params: Some(params),
             commitment: None,
+            salt: None,
+            encryption_key: None,
         }
     }
 
@@ -54,6 +71,18 @@ impl Query {
         self
     }
 
+    /// Set the commit-reveal salt (hex-encoded)
+    pub fn with_salt(mut self, salt: String) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Set the X25519 public key (hex-encoded) to encrypt the result to
+    pub fn with_encryption_key(mut self, key: String) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
     /// Get the primary parameter (pubkey or first param)
     pub fn get_primary_param(&self) -> Option<String> {
         if let Some(ref pubkey) = self.pubkey {
@@ -66,12 +95,72 @@ impl Query {
         }
         None
     }
+
+    /// Get the account or transaction this query targets
+    ///
+    /// Two queries with the same target reveal interest in the same object,
+    /// whatever the method. Queries without a target (e.g. getBlockHeight)
+    /// return `None`.
+    pub fn target(&self) -> Option<String> {
+        self.get_primary_param().or_else(|| {
+            self.params
+                .as_ref()
+                .filter(|params| !params.is_null())
+                .map(|params| params.to_string())
+        })
+    }
+
+    /// Commitment the query is executed at
+    ///
+    /// The requested commitment, or confirmed when none is set. This is also
+    /// the commitment the query hash encodes.
+    pub fn effective_commitment(&self) -> CommitmentConfig {
+        self.commitment
+            .as_ref()
+            .and_then(|c| CommitmentConfig::from_str(c).ok())
+            .unwrap_or(CommitmentConfig::confirmed())
+    }
+
+    /// Compute the canonical hash that commits this query on-chain
+    ///
+    /// See [`crate::hashing`] for the encoding.
+    pub fn hash(&self) -> ProxyResult<[u8; 32]> {
+        query_hash(self)
+    }
+
+    /// Compute the hash this query was committed under on-chain
+    ///
+    /// Queries commit `SHA-256(salt || encoding)` and must reveal their salt;
+    /// see [`crate::hashing::commitment_hash`].
+    pub fn commitment_hash(&self) -> ProxyResult<[u8; 32]> {
+        commitment_hash(self)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_query_target_ignores_method() {
+        let pubkey = "11111111111111111111111111111111".to_string();
+        let balance = Query::new("1".to_string(), RpcMethod::GetBalance, pubkey.clone());
+        let account = Query::with_params(
+            "2".to_string(),
+            RpcMethod::GetAccountInfo,
+            serde_json::json!(pubkey),
+        );
+        let height = Query::with_params(
+            "3".to_string(),
+            RpcMethod::GetBlockHeight,
+            serde_json::Value::Null,
+        );
+
+        assert_eq!(balance.target(), account.target());
+        assert_eq!(balance.target().as_deref(), Some(pubkey.as_str()));
+        assert_eq!(height.target(), None);
+    }
+
     #[test]
     fn test_query_new() {
         let query = Query::new(
@@ -82,7 +171,7 @@ mod tests {
 
         assert_eq!(query.id, "test-1");
         assert_eq!(query.method, RpcMethod::GetBalance);
-        assert_eq!(query.pubkey, "11111111111111111111111111111111");
+        assert_eq!(query.pubkey.as_deref(), Some("11111111111111111111111111111111"));
         assert!(query.commitment.is_none());
     }
 
@@ -98,6 +187,35 @@ mod tests {
         assert_eq!(query.commitment, Some("finalized".to_string()));
     }
 
+    #[test]
+    fn test_query_effective_commitment() {
+        let balance = Query::new(
+            "test-1".to_string(),
+            RpcMethod::GetBalance,
+            "11111111111111111111111111111111".to_string(),
+        )
+        .with_commitment("processed".to_string());
+        assert_eq!(
+            balance.effective_commitment(),
+            CommitmentConfig::processed()
+        );
+
+        let accounts = Query::with_params(
+            "test-2".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            serde_json::json!(["11111111111111111111111111111111"]),
+        );
+        assert_eq!(
+            accounts.effective_commitment(),
+            CommitmentConfig::confirmed()
+        );
+        let accounts = accounts.with_commitment("processed".to_string());
+        assert_eq!(
+            accounts.effective_commitment(),
+            CommitmentConfig::processed()
+        );
+    }
+
     #[test]
     fn test_query_serialization() {
         let query = Query::new(
@@ -114,4 +232,12 @@ mod tests {
         assert_eq!(parsed.id, query.id);
         assert_eq!(parsed.method, query.method);
     }
+
+    #[test]
+    fn test_query_hash_ignores_id() {
+        let pubkey = "So11111111111111111111111111111111111111112".to_string();
+        let a = Query::new("a".to_string(), RpcMethod::GetBalance, pubkey.clone());
+        let b = Query::new("b".to_string(), RpcMethod::GetBalance, pubkey);
+        assert_eq!(a.hash().unwrap(), b.hash().unwrap());
+    }
 }
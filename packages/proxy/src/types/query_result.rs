@@ -1,9 +1,12 @@
 //! Query result type
 
+use crate::enums::{ConsensusStatus, QueryErrorCode};
+use crate::error::ProxyError;
 use serde::{Deserialize, Serialize};
 
 /// Result of a single query execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct QueryResult {
     /// The query ID this result corresponds to
     pub id: String,
@@ -11,13 +14,26 @@ pub struct QueryResult {
     /// Whether the query succeeded
     pub success: bool,
 
-    /// The result data (if successful)
+    /// The result data (if successful), or the sealed error message of a
+    /// failure whose query carried an encryption key
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
 
     /// Error message (if failed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+
+    /// Machine-readable failure reason (some failures only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<QueryErrorCode>,
+
+    /// Outcome of the cross-provider check (verified queries only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<ConsensusStatus>,
+
+    /// Number of upstream calls made for the result, retries included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
 }
 
 impl QueryResult {
@@ -28,6 +44,9 @@ impl QueryResult {
             success: true,
             data: Some(data),
             error: None,
+            error_code: None,
+            consensus: None,
+            attempts: None,
         }
     }
 
@@ -38,8 +57,36 @@ impl QueryResult {
             success: false,
             data: None,
             error: Some(error),
+            error_code: None,
+            consensus: None,
+            attempts: None,
         }
     }
+
+    /// Create the result of a query that missed its deadline after
+    /// `timeout_ms`
+    pub fn timeout(id: String, timeout_ms: u64) -> Self {
+        Self::failure(id, ProxyError::Timeout(timeout_ms).to_string())
+            .with_error_code(QueryErrorCode::Timeout)
+    }
+
+    /// Set the machine-readable failure reason
+    pub fn with_error_code(mut self, code: QueryErrorCode) -> Self {
+        self.error_code = Some(code);
+        self
+    }
+
+    /// Record the number of upstream calls made for the result
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    /// Flag the outcome of checking the result against several providers
+    pub fn with_consensus(mut self, status: ConsensusStatus) -> Self {
+        self.consensus = Some(status);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -73,5 +120,27 @@ mod tests {
         assert!(json.contains("\"success\":true"));
         assert!(json.contains("\"id\":\"test\""));
         assert!(!json.contains("\"error\"")); // Should be skipped when None
+        assert!(!json.contains("\"consensus\""));
+        assert!(!json.contains("\"errorCode\""));
+        assert!(!json.contains("\"attempts\""));
+    }
+
+    #[test]
+    fn test_query_result_timeout() {
+        let result = QueryResult::timeout("id1".to_string(), 250);
+        let json = serde_json::to_value(&result).unwrap();
+
+        assert!(!result.success);
+        assert_eq!(json["error"], "Query execution timed out after 250ms");
+        assert_eq!(json["errorCode"], "timeout");
+    }
+
+    #[test]
+    fn test_query_result_consensus_flag() {
+        let result = QueryResult::success("test".to_string(), serde_json::json!(1))
+            .with_consensus(ConsensusStatus::Disagreed);
+        let json = serde_json::to_value(&result).unwrap();
+
+        assert_eq!(json["consensus"], "disagreed");
     }
 }
@@ -0,0 +1,42 @@
+//! Upstream sharding configuration
+
+use crate::enums::ShardPolicy;
+
+/// Default largest share of a batch a single provider may receive
+pub const DEFAULT_SHARD_MAX_FRACTION: f64 = 0.5;
+
+/// Configuration of upstream sharding
+///
+/// The calls of every batch, decoys included, are split across the
+/// upstreams of the pool so no single provider sees the whole batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShardConfig {
+    /// How calls are assigned to providers
+    pub policy: ShardPolicy,
+
+    /// Largest fraction of a batch's calls a single provider may receive
+    pub max_fraction: f64,
+}
+
+impl ShardConfig {
+    /// Set the shard policy
+    pub fn with_policy(mut self, policy: ShardPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the largest fraction of a batch a single provider may receive
+    pub fn with_max_fraction(mut self, max_fraction: f64) -> Self {
+        self.max_fraction = max_fraction;
+        self
+    }
+}
+
+impl Default for ShardConfig {
+    fn default() -> Self {
+        Self {
+            policy: ShardPolicy::default(),
+            max_fraction: DEFAULT_SHARD_MAX_FRACTION,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Upstream call slot metrics
+
+use serde::{Deserialize, Serialize};
+
+/// Usage of the upstream pool's global call slots
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamMetrics {
+    /// Most upstream requests in flight at once
+    pub max_in_flight: usize,
+
+    /// Upstream requests in flight now
+    pub in_flight: usize,
+
+    /// Requests waiting for a slot now
+    pub queue_depth: usize,
+
+    /// Requests that had to wait for a slot since startup
+    pub queued_calls: u64,
+}
@@ -0,0 +1,34 @@
+//! Upstream status type
+
+use crate::enums::CircuitState;
+use serde::{Deserialize, Serialize};
+
+/// Health of one upstream of the pool, as reported by `GET /health`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStatus {
+    /// Endpoint, without path or credentials
+    pub url: String,
+
+    /// Whether the upstream answered the health probe
+    pub healthy: bool,
+
+    /// State of its circuit breaker
+    pub circuit: CircuitState,
+
+    /// Rolling average latency of its calls, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+
+    /// Rolling share of its calls that failed
+    pub error_rate: f64,
+
+    /// Failures since its last success
+    pub consecutive_failures: u32,
+
+    /// Calls sent to it since startup
+    pub calls: u64,
+
+    /// Calls that failed since startup
+    pub failures: u64,
+}
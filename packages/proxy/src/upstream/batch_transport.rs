@@ -0,0 +1,471 @@
+//! Upstream transport sending each batch as one JSON-RPC batch array
+
+use super::json_rpc::{decode_response, encode_request};
+use super::{CallSlots, Departure, ResultConsensus, UpstreamFault, UpstreamPool};
+use crate::error::{ProxyError, ProxyResult};
+use crate::scheduler::ShardRoom;
+use crate::types::{Query, QueryResult};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+/// Timeout of a batch request, as `RpcClient` uses for single calls
+const BATCH_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long calls of an upstream that refused a batch go one by one before
+/// batches are tried again
+const BATCH_RECHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// JSON-RPC code of a request the upstream cannot parse as such
+const INVALID_REQUEST_CODE: i64 = -32600;
+
+/// Why a batch array did not go through
+enum BatchFailure {
+    /// The upstream said it does not accept batch arrays
+    Unsupported(String),
+    /// The upstream is unreachable, overloaded or rate-limits the proxy
+    Fault(String),
+}
+
+/// A call waiting for its result
+struct PendingCall {
+    query: Query,
+    /// Marked when the call leaves, unless it already left in a batch array
+    departure: Option<Departure>,
+    responder: oneshot::Sender<QueryResult>,
+}
+
+/// Sends the calls of a batch upstream as JSON-RPC 2.0 batch arrays
+///
+/// Calls are grouped by the upstream they prefer, or the best scored one,
+/// and each group goes out as a single HTTP request of at most `max_calls`
+/// calls. The upstream thus sees neither the number of calls as separate
+/// requests nor their timing. Calls the transport cannot encode, calls
+/// checked by result consensus and calls of upstreams that refused a batch
+/// array in the last `BATCH_RECHECK_INTERVAL` are sent one by one through the
+/// pool instead.
+pub struct BatchTransport {
+    upstreams: Arc<UpstreamPool>,
+    consensus: Option<Arc<ResultConsensus>>,
+    http: reqwest::Client,
+    max_calls: usize,
+    recheck_interval: Duration,
+    refused_until: Vec<Mutex<Option<Instant>>>,
+}
+
+impl BatchTransport {
+    /// Create a transport over `upstreams` sending at most `max_calls` calls
+    /// per request
+    pub fn new(upstreams: Arc<UpstreamPool>, max_calls: usize) -> ProxyResult<Self> {
+        if max_calls == 0 {
+            return Err(ProxyError::Internal(
+                "Upstream batches need room for at least one call".to_string(),
+            ));
+        }
+        let http = reqwest::Client::builder()
+            .timeout(BATCH_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| ProxyError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(Self {
+            refused_until: (0..upstreams.len()).map(|_| Mutex::new(None)).collect(),
+            upstreams,
+            consensus: None,
+            http,
+            max_calls,
+            recheck_interval: BATCH_RECHECK_INTERVAL,
+        })
+    }
+
+    /// Check selected calls against several providers, one by one
+    pub fn with_consensus(mut self, consensus: Arc<ResultConsensus>) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    /// Whether `upstream` refused a batch array recently
+    fn refuses_batches(&self, upstream: usize) -> bool {
+        lock(&self.refused_until[upstream]).is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Send calls, each with its preferred upstream and the departure to
+    /// mark when it leaves, and return a receiver per call for its result
+    ///
+    /// Calls of a sharded batch carry the batch's `room`, which bounds where
+    /// calls sent one by one may fail over to. Calls whose receiver is
+    /// dropped before they leave are not sent.
+    pub fn submit(
+        self: &Arc<Self>,
+        calls: Vec<(Query, Option<usize>, Departure)>,
+        room: Option<Arc<ShardRoom>>,
+    ) -> Vec<oneshot::Receiver<QueryResult>> {
+        let best = self.upstreams.best();
+        let mut groups: BTreeMap<usize, Vec<PendingCall>> = BTreeMap::new();
+        let mut receivers = Vec::with_capacity(calls.len());
+
+        for (query, preferred, departure) in calls {
+            let (responder, receiver) = oneshot::channel();
+            receivers.push(receiver);
+            let call = PendingCall {
+                query,
+                departure: Some(departure),
+                responder,
+            };
+
+            let verified = self
+                .consensus
+                .as_ref()
+                .is_some_and(|consensus| consensus.selects(&call.query));
+            if verified || encode_request(0, &call.query).is_none() {
+                self.execute_single(call, preferred, verified, room.clone());
+                continue;
+            }
+            let upstream = preferred
+                .filter(|index| *index < self.upstreams.len())
+                .unwrap_or(best);
+            groups.entry(upstream).or_default().push(call);
+        }
+
+        for (upstream, calls) in groups {
+            let mut calls = calls.into_iter().peekable();
+            while calls.peek().is_some() {
+                let chunk: Vec<PendingCall> = calls.by_ref().take(self.max_calls).collect();
+                if self.refuses_batches(upstream) {
+                    self.execute_each(upstream, chunk, room.clone());
+                    continue;
+                }
+                let transport = Arc::clone(self);
+                let room = room.clone();
+                tokio::spawn(async move { transport.send_batch(upstream, chunk, room).await });
+            }
+        }
+
+        receivers
+    }
+
+    /// Send a chunk of calls to `upstream` as one batch array
+    async fn send_batch(
+        self: Arc<Self>,
+        upstream: usize,
+        mut calls: Vec<PendingCall>,
+        room: Option<Arc<ShardRoom>>,
+    ) {
+        // The whole array holds a single call slot of the pool. Calls given
+        // up on while it waited are left out.
+        let slot = self.upstreams.acquire_slot().await;
+        calls.retain(|call| !call.responder.is_closed());
+        if calls.is_empty() {
+            return;
+        }
+        if !self.upstreams.try_acquire(upstream) {
+            drop(slot);
+            self.execute_each(upstream, calls, room);
+            return;
+        }
+
+        let body: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .filter_map(|(id, call)| encode_request(id, &call.query))
+            .collect();
+        for call in &mut calls {
+            if let Some(departure) = call.departure.take() {
+                departure.mark();
+            }
+        }
+        let started = Instant::now();
+        let outcome = self.post(upstream, &body).await;
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        drop(slot);
+
+        let responses = match outcome {
+            Ok(responses) => {
+                self.upstreams.record(upstream, latency_ms, None);
+                responses
+            }
+            Err(BatchFailure::Unsupported(reason)) => {
+                // The upstream answered, it just wants calls one by one
+                self.upstreams.record(upstream, latency_ms, None);
+                *lock(&self.refused_until[upstream]) = Some(Instant::now() + self.recheck_interval);
+                warn!(
+                    upstream = upstream,
+                    reason = %reason,
+                    "Upstream refuses JSON-RPC batches, sending calls one by one"
+                );
+                self.execute_each(upstream, calls, room);
+                return;
+            }
+            Err(BatchFailure::Fault(reason)) => {
+                let fault = UpstreamFault::classify(&reason).unwrap_or(UpstreamFault::Unavailable);
+                self.upstreams.record(upstream, latency_ms, Some(fault));
+                debug!(upstream = upstream, reason = %reason, "Upstream batch failed");
+                self.execute_each(upstream, calls, room);
+                return;
+            }
+        };
+
+        let mut by_id: HashMap<u64, Value> = responses
+            .into_iter()
+            .filter_map(|response| Some((response.get("id")?.as_u64()?, response)))
+            .collect();
+        for (id, call) in calls.into_iter().enumerate() {
+            match by_id.remove(&(id as u64)) {
+                Some(response) => {
+                    let result = decode_response(&call.query, &response).with_attempts(1);
+                    let _ = call.responder.send(result);
+                }
+                // Left out of the response: retry it on its own
+                None => self.execute_single(call, Some(upstream), false, room.clone()),
+            }
+        }
+    }
+
+    /// POST a batch array to `upstream` and return its responses
+    async fn post(&self, upstream: usize, body: &[Value]) -> Result<Vec<Value>, BatchFailure> {
+        let response = self
+            .http
+            .post(self.upstreams.endpoint(upstream))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| BatchFailure::Fault(e.without_url().to_string()))?;
+
+        // Errors leave out the URL, which may carry an API key
+        let status = response
+            .error_for_status_ref()
+            .map(|_| ())
+            .map_err(|e| e.without_url().to_string());
+        let reply = response
+            .json::<Value>()
+            .await
+            .map_err(|e| e.without_url().to_string());
+
+        match (status, reply) {
+            (Ok(()), Ok(Value::Array(responses))) => Ok(responses),
+            // A single error object instead of an array, with or without an
+            // error status
+            (_, Ok(reply)) if is_batch_refusal(&reply["error"]) => {
+                Err(BatchFailure::Unsupported(reply["error"].to_string()))
+            }
+            (Err(e), _) | (Ok(()), Err(e)) => Err(BatchFailure::Fault(e)),
+            (Ok(()), Ok(reply)) => Err(BatchFailure::Fault(reply["error"].to_string())),
+        }
+    }
+
+    /// Send calls one by one, preferring `upstream`
+    fn execute_each(
+        self: &Arc<Self>,
+        upstream: usize,
+        calls: Vec<PendingCall>,
+        room: Option<Arc<ShardRoom>>,
+    ) {
+        for call in calls {
+            self.execute_single(call, Some(upstream), false, room.clone());
+        }
+    }
+
+    /// Send a single call through the pool, or through result consensus
+    ///
+    /// The call is dropped if its receiver goes away before its result.
+    fn execute_single(
+        self: &Arc<Self>,
+        call: PendingCall,
+        preferred: Option<usize>,
+        verified: bool,
+        room: Option<Arc<ShardRoom>>,
+    ) {
+        let transport = Arc::clone(self);
+        let PendingCall {
+            query,
+            departure,
+            mut responder,
+        } = call;
+        tokio::spawn(async move {
+            let execute = async {
+                match transport.consensus.as_ref().filter(|_| verified) {
+                    Some(consensus) => {
+                        consensus
+                            .execute(&transport.upstreams, query, preferred)
+                            .await
+                    }
+                    None => {
+                        transport
+                            .upstreams
+                            .execute(query, preferred, room.as_deref())
+                            .await
+                    }
+                }
+            };
+            let result = tokio::select! {
+                result = async {
+                    match departure {
+                        Some(departure) => CallSlots::departing(departure, execute).await,
+                        None => execute.await,
+                    }
+                } => result,
+                () = responder.closed() => return,
+            };
+            let _ = responder.send(result);
+        });
+    }
+}
+
+/// Whether a JSON-RPC `error` says batch arrays are not accepted
+fn is_batch_refusal(error: &Value) -> bool {
+    error["code"].as_i64() == Some(INVALID_REQUEST_CODE)
+        || error["message"]
+            .as_str()
+            .is_some_and(|message| message.to_lowercase().contains("batch"))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::RpcMethod;
+    use crate::types::FailoverConfig;
+    use crate::upstream::{MockUpstream, MOCK_LAMPORTS};
+    use serde_json::json;
+
+    fn calls(count: usize) -> Vec<(Query, Option<usize>, Departure)> {
+        (0..count)
+            .map(|i| {
+                let query = Query::new(
+                    format!("q{}", i),
+                    RpcMethod::GetBalance,
+                    "So11111111111111111111111111111111111111112".to_string(),
+                );
+                (query, None, Departure::channel().0)
+            })
+            .collect()
+    }
+
+    async fn collect_results(receivers: Vec<oneshot::Receiver<QueryResult>>) -> Vec<QueryResult> {
+        let mut results = Vec::new();
+        for receiver in receivers {
+            results.push(receiver.await.unwrap());
+        }
+        results
+    }
+
+    fn single_calls(upstream: &MockUpstream) -> usize {
+        upstream.calls().iter().filter(|call| !call.batched).count()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_goes_out_as_one_request() {
+        let upstream = MockUpstream::start().await;
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()));
+        let transport = Arc::new(BatchTransport::new(pool, 4).unwrap());
+
+        let mut queries = calls(6);
+        queries.push((
+            Query::new("bad".to_string(), RpcMethod::GetBalance, "x".to_string()),
+            None,
+            Departure::channel().0,
+        ));
+        let results = collect_results(transport.submit(queries, None)).await;
+
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["q0", "q1", "q2", "q3", "q4", "q5", "bad"]);
+        assert!(results[..6]
+            .iter()
+            .all(|r| r.data == Some(json!({ "lamports": MOCK_LAMPORTS }))));
+        assert!(!results[6].success);
+
+        // Six calls in chunks of four; the invalid one never left the proxy
+        assert_eq!(upstream.batches(), 2);
+        assert_eq!(single_calls(&upstream), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_falls_back_when_batches_are_refused() {
+        let upstream = MockUpstream::start().await;
+        upstream.refuse_batches(json!({
+            "code": -32600,
+            "message": "Batch requests are not supported",
+        }));
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()));
+        let transport = Arc::new(BatchTransport::new(pool, 10).unwrap());
+
+        let results = collect_results(transport.submit(calls(3), None)).await;
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(single_calls(&upstream), 3);
+
+        // Later batches go one by one straight away
+        let results = collect_results(transport.submit(calls(2), None)).await;
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(single_calls(&upstream), 5);
+        assert_eq!(upstream.batches(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batches_are_tried_again_after_a_refusal() {
+        let upstream = MockUpstream::start().await;
+        upstream.refuse_batches(json!({
+            "code": -32600,
+            "message": "Batch requests are not supported",
+        }));
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()));
+        let mut transport = BatchTransport::new(pool, 10).unwrap();
+        transport.recheck_interval = Duration::ZERO;
+        let transport = Arc::new(transport);
+
+        collect_results(transport.submit(calls(2), None)).await;
+        assert_eq!(upstream.batches(), 1);
+
+        upstream.recover();
+        let results = collect_results(transport.submit(calls(2), None)).await;
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(upstream.batches(), 2);
+        assert_eq!(single_calls(&upstream), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_other_error_replies_do_not_disable_batches() {
+        let upstream = MockUpstream::start().await;
+        upstream.refuse_batches(json!({ "code": -32603, "message": "Internal error" }));
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()));
+        let transport = Arc::new(BatchTransport::new(pool, 10).unwrap());
+
+        // The calls still get through one by one
+        let results = collect_results(transport.submit(calls(2), None)).await;
+        assert!(results.iter().all(|r| r.success));
+        assert!(!transport.refuses_batches(0));
+
+        upstream.recover();
+        collect_results(transport.submit(calls(2), None)).await;
+        assert_eq!(upstream.batches(), 2);
+    }
+
+    #[test]
+    fn test_batch_refusals() {
+        assert!(is_batch_refusal(&json!({ "code": -32600, "message": "Invalid request" })));
+        assert!(is_batch_refusal(&json!({ "code": -32000, "message": "Batch calls disabled" })));
+        assert!(!is_batch_refusal(&json!({ "code": -32005, "message": "Too many requests" })));
+        assert!(!is_batch_refusal(&Value::Null));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unreachable_upstream_fails_each_call() {
+        let pool = Arc::new(
+            UpstreamPool::new(
+                &["http://127.0.0.1:1".to_string()],
+                FailoverConfig::default().with_backoff(1, 1),
+            )
+            .unwrap(),
+        );
+        let transport = Arc::new(BatchTransport::new(pool, 10).unwrap());
+
+        let results = collect_results(transport.submit(calls(3), None)).await;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| !r.success));
+    }
+}
@@ -0,0 +1,241 @@
+//! Bound on the HTTP requests in flight to the upstreams
+
+use crate::types::UpstreamMetrics;
+use async_trait::async_trait;
+use serde_json::Value;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::{RpcClient as BlockingRpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+tokio::task_local! {
+    /// Set while a task's requests go out under a slot it already holds
+    static SLOT_HELD: ();
+
+    /// Departure of the call a task runs, until its first request leaves
+    static DEPARTURE: RefCell<Option<Departure>>;
+}
+
+/// Signal of the moment a call leaves for the upstream
+///
+/// A call leaves when its first request has a call slot, not when it is
+/// queued, so its timeout can start then.
+#[derive(Debug)]
+pub struct Departure(oneshot::Sender<Instant>);
+
+impl Departure {
+    /// Create a signal and the receiver of its moment
+    pub fn channel() -> (Self, oneshot::Receiver<Instant>) {
+        let (sender, receiver) = oneshot::channel();
+        (Self(sender), receiver)
+    }
+
+    /// Record that the call leaves now
+    pub fn mark(self) {
+        let _ = self.0.send(Instant::now());
+    }
+}
+
+/// Bound on the HTTP requests in flight to the upstreams, across every batch
+///
+/// Every request of a client built here holds a slot while in flight: each
+/// attempt of a call, each chunk of a large getMultipleAccounts call and the
+/// client's own probes alike. Past the limit requests queue until one in
+/// flight completes.
+pub struct CallSlots {
+    permits: Arc<Semaphore>,
+    limit: usize,
+    /// Requests waiting for a slot
+    queued: AtomicUsize,
+    /// Requests that had to wait for a slot, ever
+    waited: AtomicU64,
+}
+
+impl CallSlots {
+    /// Create `limit` slots (at least 1)
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(limit)),
+            limit,
+            queued: AtomicUsize::new(0),
+            waited: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait for a free slot, held until the permit is dropped
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        if let Ok(permit) = self.permits.try_acquire() {
+            return permit;
+        }
+        self.waited.fetch_add(1, Ordering::Relaxed);
+        let _queued = QueuedRequest::new(&self.queued);
+        self.permits
+            .acquire()
+            .await
+            .expect("the call slot semaphore is never closed")
+    }
+
+    /// Take a slot if one is free, without waiting
+    pub fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        Arc::clone(&self.permits).try_acquire_owned().ok()
+    }
+
+    /// Run `future` with its requests going out under `slot`, which they
+    /// would otherwise wait for in turn
+    pub async fn with_slot<F: Future>(slot: OwnedSemaphorePermit, future: F) -> F::Output {
+        let output = SLOT_HELD.scope((), future).await;
+        drop(slot);
+        output
+    }
+
+    /// Run the call `future`, marking `departure` when its first request
+    /// leaves
+    pub async fn departing<F: Future>(departure: Departure, future: F) -> F::Output {
+        DEPARTURE.scope(RefCell::new(Some(departure)), future).await
+    }
+
+    /// Client of the RPC endpoint at `url` whose requests take a slot
+    pub fn client(self: &Arc<Self>, url: &str) -> RpcClient {
+        RpcClient::new_sender(self.sender(url), RpcClientConfig::default())
+    }
+
+    /// Blocking client of the RPC endpoint at `url` whose requests take a slot
+    pub fn blocking_client(self: &Arc<Self>, url: &str) -> BlockingRpcClient {
+        BlockingRpcClient::new_sender(self.sender(url), RpcClientConfig::default())
+    }
+
+    fn sender(self: &Arc<Self>, url: &str) -> SlottedSender {
+        SlottedSender {
+            http: HttpSender::new(url.to_string()),
+            slots: Arc::clone(self),
+        }
+    }
+
+    /// Report the slot usage
+    pub fn metrics(&self) -> UpstreamMetrics {
+        UpstreamMetrics {
+            max_in_flight: self.limit,
+            in_flight: self.limit - self.permits.available_permits(),
+            queue_depth: self.queued.load(Ordering::Relaxed),
+            queued_calls: self.waited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts a request in the queue for as long as it waits, cancelled or not
+struct QueuedRequest<'a>(&'a AtomicUsize);
+
+impl<'a> QueuedRequest<'a> {
+    fn new(queued: &'a AtomicUsize) -> Self {
+        queued.fetch_add(1, Ordering::Relaxed);
+        Self(queued)
+    }
+}
+
+impl Drop for QueuedRequest<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// HTTP transport of an RPC client holding a slot per request
+struct SlottedSender {
+    http: HttpSender,
+    slots: Arc<CallSlots>,
+}
+
+#[async_trait]
+impl RpcSender for SlottedSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let _slot = match SLOT_HELD.try_with(|_| ()) {
+            Ok(()) => None,
+            Err(_) => Some(self.slots.acquire().await),
+        };
+        if let Ok(Some(departure)) = DEPARTURE.try_with(|departure| departure.borrow_mut().take()) {
+            departure.mark();
+        }
+        self.http.send(request, params).await
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.http.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.http.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upstream::MockUpstream;
+
+    #[tokio::test]
+    async fn test_every_request_takes_a_slot() {
+        let upstream = MockUpstream::start().await;
+        upstream.hang();
+        let slots = Arc::new(CallSlots::new(2));
+        let client = Arc::new(slots.client(&upstream.url()));
+
+        let requests: Vec<_> = (0..3)
+            .map(|_| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move { client.get_health().await })
+            })
+            .collect();
+        while slots.metrics().queue_depth == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(slots.metrics().in_flight, 2);
+
+        for request in requests {
+            request.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_run_under_a_held_slot() {
+        let upstream = MockUpstream::start().await;
+        let slots = Arc::new(CallSlots::new(1));
+        let client = slots.client(&upstream.url());
+
+        let slot = slots.try_acquire().unwrap();
+        let height = CallSlots::with_slot(slot, client.get_block_height()).await;
+        assert!(height.is_ok());
+        assert_eq!(slots.metrics().queued_calls, 0);
+        assert_eq!(slots.metrics().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_departure_is_marked_once_a_slot_is_free() {
+        let upstream = MockUpstream::start().await;
+        let slots = Arc::new(CallSlots::new(1));
+        let client = Arc::new(slots.client(&upstream.url()));
+
+        let taken = slots.try_acquire().unwrap();
+        let (departure, mut departed) = Departure::channel();
+        let call = tokio::spawn({
+            let client = Arc::clone(&client);
+            CallSlots::departing(departure, async move { client.get_health().await })
+        });
+        while slots.metrics().queue_depth == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(departed.try_recv().is_err());
+
+        let released = Instant::now();
+        drop(taken);
+        assert!(call.await.unwrap().is_ok());
+        assert!(departed.try_recv().unwrap() >= released);
+    }
+}
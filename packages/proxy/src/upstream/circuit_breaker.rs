@@ -0,0 +1,171 @@
+//! Per-upstream circuit breaker
+
+use crate::enums::CircuitState;
+use std::time::{Duration, Instant};
+
+/// Keeps calls away from an upstream that keeps failing
+///
+/// The circuit opens after `failure_threshold` consecutive failures, or at
+/// once when the upstream rate-limits the proxy. Once the cooldown is over a
+/// single probe call is let through: success closes the circuit, failure
+/// reopens it for twice the previous cooldown, up to `max_cooldown`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    max_cooldown: Duration,
+    consecutive_failures: u32,
+    trips: u32,
+    open_until: Option<Instant>,
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker
+    pub fn new(failure_threshold: u32, cooldown: Duration, max_cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            max_cooldown,
+            consecutive_failures: 0,
+            trips: 0,
+            open_until: None,
+            probing: false,
+        }
+    }
+
+    /// State of the breaker at `now`
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(until) if now < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Failures since the last success
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Check whether a call may be sent at `now`
+    ///
+    /// In the half-open state only the first caller gets through, as the probe.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.probing => false,
+            CircuitState::HalfOpen => {
+                self.probing = true;
+                true
+            }
+        }
+    }
+
+    /// Record a successful call, closing the circuit
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.trips = 0;
+        self.open_until = None;
+        self.probing = false;
+    }
+
+    /// Record a failed call; `rate_limited` opens the circuit at once
+    pub fn record_failure(&mut self, now: Instant, rate_limited: bool) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let half_open = self.probing;
+        self.probing = false;
+
+        // Failures of calls sent before the circuit opened do not extend it
+        let closed = self.open_until.is_none();
+        let tripped = rate_limited || self.consecutive_failures >= self.failure_threshold;
+        if half_open || (closed && tripped) {
+            let cooldown = self
+                .cooldown
+                .saturating_mul(2u32.saturating_pow(self.trips))
+                .min(self.max_cooldown);
+            self.open_until = Some(now + cooldown);
+            self.trips = self.trips.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(3, Duration::from_secs(1), Duration::from_secs(3))
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let now = Instant::now();
+        let mut breaker = breaker();
+
+        breaker.record_failure(now, false);
+        breaker.record_failure(now, false);
+        breaker.record_success();
+        breaker.record_failure(now, false);
+        breaker.record_failure(now, false);
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+        assert!(breaker.try_acquire(now));
+
+        breaker.record_failure(now, false);
+        assert_eq!(breaker.state(now), CircuitState::Open);
+        assert!(!breaker.try_acquire(now));
+    }
+
+    #[test]
+    fn test_rate_limit_opens_at_once() {
+        let now = Instant::now();
+        let mut breaker = breaker();
+
+        breaker.record_failure(now, true);
+        assert_eq!(breaker.state(now), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_half_open_lets_one_probe_through() {
+        let now = Instant::now();
+        let mut breaker = breaker();
+        breaker.record_failure(now, true);
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(later));
+        assert!(!breaker.try_acquire(later));
+
+        breaker.record_success();
+        assert_eq!(breaker.state(later), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_failed_probe_doubles_cooldown() {
+        let now = Instant::now();
+        let mut breaker = breaker();
+        breaker.record_failure(now, true);
+
+        let probe = now + Duration::from_secs(1);
+        assert!(breaker.try_acquire(probe));
+        breaker.record_failure(probe, false);
+        assert_eq!(
+            breaker.state(probe + Duration::from_millis(1999)),
+            CircuitState::Open
+        );
+        assert_eq!(
+            breaker.state(probe + Duration::from_secs(2)),
+            CircuitState::HalfOpen
+        );
+
+        // Capped at the maximum cooldown
+        let probe = probe + Duration::from_secs(2);
+        assert!(breaker.try_acquire(probe));
+        breaker.record_failure(probe, false);
+        assert_eq!(
+            breaker.state(probe + Duration::from_secs(3)),
+            CircuitState::HalfOpen
+        );
+    }
+}
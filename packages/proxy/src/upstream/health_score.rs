@@ -0,0 +1,90 @@
+//! Rolling latency and error score of an upstream
+
+/// Weight of the newest sample in the rolling averages
+const SCORE_ALPHA: f64 = 0.2;
+
+/// How much a fully failing upstream's latency is inflated when ranking
+const ERROR_PENALTY: f64 = 10.0;
+
+/// Exponentially weighted latency and error rate of an upstream's calls
+#[derive(Debug, Clone, Default)]
+pub struct HealthScore {
+    latency_ms: Option<f64>,
+    error_rate: f64,
+    calls: u64,
+    failures: u64,
+}
+
+impl HealthScore {
+    /// Record a call that took `latency_ms` and failed or not
+    pub fn record(&mut self, latency_ms: f64, failed: bool) {
+        self.latency_ms = Some(match self.latency_ms {
+            Some(average) => average + SCORE_ALPHA * (latency_ms - average),
+            None => latency_ms,
+        });
+        let sample = if failed { 1.0 } else { 0.0 };
+        self.error_rate += SCORE_ALPHA * (sample - self.error_rate);
+        self.calls += 1;
+        if failed {
+            self.failures += 1;
+        }
+    }
+
+    /// Rolling average latency, if any call was recorded
+    pub fn latency_ms(&self) -> Option<f64> {
+        self.latency_ms
+    }
+
+    /// Rolling share of failed calls
+    pub fn error_rate(&self) -> f64 {
+        self.error_rate
+    }
+
+    /// Calls recorded
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// Failed calls recorded
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// Ranking cost of the upstream: lower is better
+    ///
+    /// Upstreams without samples rank first, so new ones get tried.
+    pub fn cost(&self) -> f64 {
+        self.latency_ms.unwrap_or(0.0) * (1.0 + ERROR_PENALTY * self.error_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_averages() {
+        let mut score = HealthScore::default();
+        assert_eq!(score.latency_ms(), None);
+
+        score.record(100.0, false);
+        assert_eq!(score.latency_ms(), Some(100.0));
+        assert_eq!(score.error_rate(), 0.0);
+
+        score.record(200.0, true);
+        assert_eq!(score.latency_ms(), Some(120.0));
+        assert!((score.error_rate() - 0.2).abs() < 1e-9);
+        assert_eq!((score.calls(), score.failures()), (2, 1));
+    }
+
+    #[test]
+    fn test_errors_raise_cost() {
+        let mut fast_failing = HealthScore::default();
+        let mut slow_healthy = HealthScore::default();
+        for _ in 0..10 {
+            fast_failing.record(50.0, true);
+            slow_healthy.record(200.0, false);
+        }
+        assert!(fast_failing.cost() > slow_healthy.cost());
+    }
+}
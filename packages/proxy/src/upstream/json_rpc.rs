@@ -0,0 +1,274 @@
+//! JSON-RPC 2.0 encoding of queries for batch arrays
+//!
+//! Requests mirror what `RpcClient` sends for each method, and responses are
+//! decoded into the same result data the per-call executors return, so a
+//! client cannot tell which transport carried its query.
+
+use crate::enums::RpcMethod;
+use crate::executor::{ErrorClass, MAX_MULTIPLE_ACCOUNTS};
+use crate::types::{Query, QueryResult};
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Pubkey of a single-account query, if valid
+fn pubkey(query: &Query) -> Option<String> {
+    query
+        .get_primary_param()
+        .filter(|pubkey| Pubkey::from_str(pubkey).is_ok())
+}
+
+/// Pubkeys of a getMultipleAccounts query, if all valid
+///
+/// Queries with more accounts than one call may carry are left to the
+/// executor, which splits them into chunks.
+fn pubkeys(query: &Query) -> Option<Vec<String>> {
+    let pubkeys: Vec<String> = match query.params.as_ref()? {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(String::from))
+            .collect(),
+        Value::String(pubkey) => vec![pubkey.clone()],
+        _ => return None,
+    };
+    let valid = (1..=MAX_MULTIPLE_ACCOUNTS).contains(&pubkeys.len())
+        && pubkeys.iter().all(|pk| Pubkey::from_str(pk).is_ok());
+    valid.then_some(pubkeys)
+}
+
+/// Encode a query as a JSON-RPC request with the given id
+///
+/// Returns `None` for queries whose parameters are invalid; they are left to
+/// the per-call executors, which report the error.
+pub fn encode_request(id: usize, query: &Query) -> Option<Value> {
+    let commitment = serde_json::to_value(query.effective_commitment()).ok()?;
+    let params = match query.method {
+        RpcMethod::GetBalance | RpcMethod::GetTokenAccountBalance => {
+            json!([pubkey(query)?, commitment])
+        }
+        RpcMethod::GetAccountInfo => {
+            let mut config = commitment;
+            config["encoding"] = json!("base64");
+            json!([pubkey(query)?, config])
+        }
+        RpcMethod::GetMultipleAccounts => {
+            let mut config = commitment;
+            config["encoding"] = json!("base64");
+            json!([pubkeys(query)?, config])
+        }
+        RpcMethod::GetBlockHeight => json!([commitment]),
+        RpcMethod::GetTransaction => {
+            let signature = query.get_primary_param()?;
+            solana_sdk::signature::Signature::from_str(&signature).ok()?;
+            let mut config = commitment;
+            config["encoding"] = json!("json");
+            config["maxSupportedTransactionVersion"] = json!(0);
+            json!([signature, config])
+        }
+    };
+    Some(json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": query.method.as_str(),
+        "params": params,
+    }))
+}
+
+/// Account data in the shape the account executors return
+fn account_json(account: &Value) -> Option<Value> {
+    if account.is_null() {
+        return Some(Value::Null);
+    }
+    let data = account["data"][0].as_str()?;
+    let data_length = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .ok()?
+        .len();
+    Some(json!({
+        "lamports": account["lamports"].as_u64()?,
+        "owner": account["owner"].as_str()?,
+        "executable": account["executable"].as_bool()?,
+        "rentEpoch": account["rentEpoch"].as_u64()?,
+        "dataLength": data_length,
+    }))
+}
+
+/// Decode the JSON-RPC response to a query into its result
+///
+/// Error objects are classified like the executors' client errors: "not
+/// found" becomes a `null` result, anything else a failure with its code.
+pub fn decode_response(query: &Query, response: &Value) -> QueryResult {
+    let id = query.id.clone();
+    if let Some(error) = response.get("error") {
+        let message = error["message"].as_str().unwrap_or_default();
+        return ErrorClass::of_rpc_error(error["code"].as_i64().unwrap_or_default(), message)
+            .result(
+                id,
+                format!("RPC response error {}: {}", error["code"], message),
+            );
+    }
+    let result = &response["result"];
+
+    let data = match query.method {
+        RpcMethod::GetBalance => result["value"]
+            .as_u64()
+            .map(|lamports| json!({ "lamports": lamports })),
+        RpcMethod::GetAccountInfo => account_json(&result["value"]),
+        RpcMethod::GetMultipleAccounts => result["value"]
+            .as_array()
+            .and_then(|accounts| {
+                accounts
+                    .iter()
+                    .map(account_json)
+                    .collect::<Option<Vec<_>>>()
+            })
+            .map(Value::Array),
+        RpcMethod::GetTokenAccountBalance => {
+            Some(result["value"].clone()).filter(|value| value.is_object())
+        }
+        RpcMethod::GetBlockHeight => result.as_u64().map(|height| json!(height)),
+        // A transaction that does not exist comes back as `null`
+        RpcMethod::GetTransaction => Some(result.clone()),
+    };
+
+    match data {
+        Some(data) => QueryResult::success(id, data),
+        None => QueryResult::failure(
+            id,
+            format!("RPC error: unexpected {} response", query.method),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::QueryErrorCode;
+
+    const PUBKEY: &str = "So11111111111111111111111111111111111111112";
+
+    #[test]
+    fn test_encode_requests() {
+        let balance = Query::new("b".to_string(), RpcMethod::GetBalance, PUBKEY.to_string());
+        assert_eq!(
+            encode_request(3, &balance).unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "getBalance",
+                "params": [PUBKEY, { "commitment": "confirmed" }],
+            })
+        );
+
+        let accounts = Query::with_params(
+            "m".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            json!([PUBKEY]),
+        )
+        .with_commitment("processed".to_string());
+        assert_eq!(
+            encode_request(0, &accounts).unwrap()["params"],
+            json!([[PUBKEY], { "commitment": "processed", "encoding": "base64" }])
+        );
+
+        let height = Query::with_params("h".to_string(), RpcMethod::GetBlockHeight, Value::Null);
+        assert_eq!(
+            encode_request(0, &height).unwrap()["params"],
+            json!([{ "commitment": "confirmed" }])
+        );
+    }
+
+    #[test]
+    fn test_invalid_queries_are_not_encoded() {
+        let invalid = Query::new(
+            "b".to_string(),
+            RpcMethod::GetBalance,
+            "not-a-pubkey".to_string(),
+        );
+        assert!(encode_request(0, &invalid).is_none());
+
+        let empty = Query::with_params("m".to_string(), RpcMethod::GetMultipleAccounts, json!([]));
+        assert!(encode_request(0, &empty).is_none());
+
+        let oversized = Query::with_params(
+            "m".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            json!(vec![PUBKEY; MAX_MULTIPLE_ACCOUNTS + 1]),
+        );
+        assert!(encode_request(0, &oversized).is_none());
+    }
+
+    #[test]
+    fn test_decode_matches_executor_shapes() {
+        let info = Query::new(
+            "a".to_string(),
+            RpcMethod::GetAccountInfo,
+            PUBKEY.to_string(),
+        );
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {
+                "context": { "slot": 5 },
+                "value": {
+                    "lamports": 10,
+                    "owner": PUBKEY,
+                    "executable": false,
+                    "rentEpoch": 361,
+                    "data": ["AQID", "base64"],
+                },
+            },
+        });
+        let result = decode_response(&info, &response);
+        assert_eq!(
+            result.data.unwrap(),
+            json!({
+                "lamports": 10,
+                "owner": PUBKEY,
+                "executable": false,
+                "rentEpoch": 361,
+                "dataLength": 3,
+            })
+        );
+
+        let missing = json!({ "result": { "context": { "slot": 5 }, "value": null } });
+        let result = decode_response(&info, &missing);
+        assert!(result.success);
+        assert_eq!(result.data, Some(Value::Null));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let balance = Query::new("b".to_string(), RpcMethod::GetBalance, PUBKEY.to_string());
+        let error = json!({ "error": { "code": -32602, "message": "Invalid param" } });
+        let result = decode_response(&balance, &error);
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("RPC response error -32602: Invalid param")
+        );
+        assert_eq!(result.error_code, Some(QueryErrorCode::Invalid));
+
+        let unhealthy = json!({ "error": { "code": -32005, "message": "Node is unhealthy" } });
+        assert_eq!(
+            decode_response(&balance, &unhealthy).error_code,
+            Some(QueryErrorCode::Unavailable)
+        );
+
+        let token = Query::new(
+            "t".to_string(),
+            RpcMethod::GetTokenAccountBalance,
+            PUBKEY.to_string(),
+        );
+        let missing = json!({
+            "error": { "code": -32602, "message": "Invalid param: could not find account" }
+        });
+        let result = decode_response(&token, &missing);
+        assert!(result.success);
+        assert_eq!(result.data, Some(Value::Null));
+
+        let malformed = json!({ "result": { "value": "lots" } });
+        assert!(!decode_response(&balance, &malformed).success);
+    }
+}
@@ -0,0 +1,235 @@
+//! Mock upstream RPC endpoint for tests
+//!
+//! Serves JSON-RPC calls and batch arrays over HTTP the way a Solana RPC
+//! node does, and records every call it receives so tests can check what
+//! the upstream saw. It can be told to answer with an HTTP error status, to
+//! never answer, or to reply to batch arrays with a single error object.
+
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Slot every answer is reported at
+pub const MOCK_SLOT: u64 = 1000;
+
+/// Lamports every account holds unless told otherwise
+pub const MOCK_LAMPORTS: u64 = 42;
+
+/// A call as the upstream received it
+#[derive(Debug, Clone)]
+pub struct ReceivedCall {
+    pub method: String,
+    pub params: Value,
+    /// Whether the call came in a batch array
+    pub batched: bool,
+    pub at: Instant,
+}
+
+impl ReceivedCall {
+    /// Accounts or transaction the call asks for, in order
+    pub fn targets(&self) -> Vec<String> {
+        match &self.params[0] {
+            Value::String(target) => vec![target.clone()],
+            Value::Array(items) => items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// How the mock answers
+#[derive(Debug, Clone, Default)]
+enum Behaviour {
+    #[default]
+    Answer,
+    /// Every request fails with this HTTP status
+    Status(StatusCode),
+    /// Requests are accepted and never answered
+    Hang,
+    /// Batch arrays get this error object; single calls are answered
+    BatchError(Value),
+}
+
+#[derive(Default)]
+struct MockState {
+    behaviour: Mutex<Behaviour>,
+    lamports: AtomicU64,
+    calls: Mutex<Vec<ReceivedCall>>,
+    requests: AtomicUsize,
+    batches: AtomicUsize,
+}
+
+impl MockState {
+    fn behaviour(&self) -> Behaviour {
+        lock(&self.behaviour).clone()
+    }
+
+    fn record(&self, request: &Value, batched: bool) {
+        let method = request["method"].as_str().unwrap_or_default();
+        // Probes of the RPC client itself, not calls of the proxy
+        if matches!(method, "getVersion" | "getHealth") {
+            return;
+        }
+        lock(&self.calls).push(ReceivedCall {
+            method: method.to_string(),
+            params: request["params"].clone(),
+            batched,
+            at: Instant::now(),
+        });
+    }
+
+    fn answer(&self, request: &Value) -> Value {
+        let lamports = self.lamports.load(Ordering::Relaxed);
+        let account = json!({
+            "lamports": lamports,
+            "owner": "11111111111111111111111111111111",
+            "executable": false,
+            "rentEpoch": 361,
+            "data": ["AQID", "base64"],
+            "space": 3,
+        });
+        let context = json!({ "slot": MOCK_SLOT });
+        let result = match request["method"].as_str().unwrap_or_default() {
+            "getVersion" => json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getHealth" => json!("ok"),
+            "getBalance" => json!({ "context": context, "value": lamports }),
+            "getAccountInfo" => json!({ "context": context, "value": account }),
+            "getMultipleAccounts" => {
+                let count = request["params"][0].as_array().map_or(0, Vec::len);
+                json!({ "context": context, "value": vec![account; count] })
+            }
+            "getTokenAccountBalance" => json!({
+                "context": context,
+                "value": {
+                    "amount": lamports.to_string(),
+                    "decimals": 0,
+                    "uiAmount": lamports as f64,
+                    "uiAmountString": lamports.to_string(),
+                },
+            }),
+            "getBlockHeight" => json!(MOCK_SLOT),
+            _ => Value::Null,
+        };
+        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn serve(
+    State(state): State<Arc<MockState>>,
+    Json(body): Json<Value>,
+) -> Result<Json<Value>, StatusCode> {
+    let behaviour = state.behaviour();
+    state.requests.fetch_add(1, Ordering::Relaxed);
+
+    match (behaviour, body) {
+        (Behaviour::Hang, _) => std::future::pending().await,
+        (Behaviour::Status(status), _) => Err(status),
+        (Behaviour::BatchError(error), Value::Array(_)) => {
+            state.batches.fetch_add(1, Ordering::Relaxed);
+            Ok(Json(json!({ "jsonrpc": "2.0", "id": null, "error": error })))
+        }
+        (Behaviour::Answer, Value::Array(requests)) => {
+            state.batches.fetch_add(1, Ordering::Relaxed);
+            for request in &requests {
+                state.record(request, true);
+            }
+            // Answer out of order, as upstreams may
+            Ok(Json(Value::Array(
+                requests.iter().rev().map(|r| state.answer(r)).collect(),
+            )))
+        }
+        (_, request) => {
+            state.record(&request, false);
+            Ok(Json(state.answer(&request)))
+        }
+    }
+}
+
+/// Solana RPC endpoint on a local port, recording the calls it serves
+pub struct MockUpstream {
+    url: String,
+    state: Arc<MockState>,
+}
+
+impl MockUpstream {
+    /// Start an upstream that answers every call
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState {
+            lamports: AtomicU64::new(MOCK_LAMPORTS),
+            ..MockState::default()
+        });
+        let app = Router::new()
+            .route("/", post(serve))
+            .with_state(Arc::clone(&state));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Self { url, state }
+    }
+
+    /// URL of the endpoint
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Report every account holding `lamports`
+    pub fn set_lamports(&self, lamports: u64) {
+        self.state.lamports.store(lamports, Ordering::Relaxed);
+    }
+
+    /// Fail every request with the HTTP `status`
+    pub fn fail_with(&self, status: u16) {
+        *lock(&self.state.behaviour) = Behaviour::Status(StatusCode::from_u16(status).unwrap());
+    }
+
+    /// Accept requests and never answer them
+    pub fn hang(&self) {
+        *lock(&self.state.behaviour) = Behaviour::Hang;
+    }
+
+    /// Reply to batch arrays with the JSON-RPC `error` object
+    pub fn refuse_batches(&self, error: Value) {
+        *lock(&self.state.behaviour) = Behaviour::BatchError(error);
+    }
+
+    /// Answer every call again
+    pub fn recover(&self) {
+        *lock(&self.state.behaviour) = Behaviour::Answer;
+    }
+
+    /// Calls received so far, in order of arrival
+    pub fn calls(&self) -> Vec<ReceivedCall> {
+        lock(&self.state.calls).clone()
+    }
+
+    /// Number of calls received so far
+    pub fn call_count(&self) -> usize {
+        lock(&self.state.calls).len()
+    }
+
+    /// Targets of every call received so far, in order of arrival
+    pub fn targets(&self) -> Vec<String> {
+        self.calls().iter().flat_map(ReceivedCall::targets).collect()
+    }
+
+    /// Number of HTTP requests received, batch arrays and the RPC client's
+    /// own probes included
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of batch arrays received
+    pub fn batches(&self) -> usize {
+        self.state.batches.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,28 @@
+//! Upstream RPC endpoints
+//!
+//! Tracks the health of every configured endpoint and fails queries over
+//! between them when one is unreachable or rate-limits the proxy. Selected
+//! queries can be checked against several endpoints at once, and whole
+//! batches can be sent as a single JSON-RPC batch request.
+
+mod batch_transport;
+mod call_slots;
+mod circuit_breaker;
+mod health_score;
+mod json_rpc;
+#[cfg(test)]
+mod mock_upstream;
+mod result_consensus;
+mod upstream_fault;
+mod upstream_pool;
+
+pub use batch_transport::BatchTransport;
+pub use call_slots::{CallSlots, Departure};
+pub use circuit_breaker::CircuitBreaker;
+pub use health_score::HealthScore;
+pub use json_rpc::{decode_response, encode_request};
+#[cfg(test)]
+pub use mock_upstream::{MockUpstream, ReceivedCall, MOCK_LAMPORTS, MOCK_SLOT};
+pub use result_consensus::{normalize_result, ResultConsensus};
+pub use upstream_fault::UpstreamFault;
+pub use upstream_pool::{sanitize_rpc_url, UpstreamPool};
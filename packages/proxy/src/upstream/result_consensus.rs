@@ -0,0 +1,358 @@
+//! Cross-provider result consensus
+
+use super::{UpstreamFault, UpstreamPool};
+use crate::enums::{ConsensusStatus, QuorumPolicy, RpcMethod};
+use crate::error::{ProxyError, ProxyResult};
+use crate::types::{ConsensusConfig, ConsensusMetrics, Query, QueryResult};
+use rand::Rng;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+/// Copy of a `method` result without the fields that legitimately differ
+/// between providers reading at different slots
+///
+/// Only the result's own `context` and the `rentEpoch` of account objects
+/// are dropped. Other fields, such as the `slot` of a transaction, are part
+/// of the answer and compared.
+pub fn normalize_result(method: RpcMethod, value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Value::Object(fields) = &mut value {
+        fields.remove("context");
+    }
+    match (method, &mut value) {
+        (RpcMethod::GetAccountInfo, account) => drop_rent_epoch(account),
+        (RpcMethod::GetMultipleAccounts, Value::Array(accounts)) => {
+            accounts.iter_mut().for_each(drop_rent_epoch)
+        }
+        _ => {}
+    }
+    value
+}
+
+fn drop_rent_epoch(account: &mut Value) {
+    if let Value::Object(fields) = account {
+        fields.remove("rentEpoch");
+    }
+}
+
+/// What two answers to a `method` query must share to agree
+///
+/// Failures agree with each other whatever their message, since providers
+/// word errors differently.
+fn answer_key(method: RpcMethod, result: &QueryResult) -> (bool, Value) {
+    if result.success {
+        let data = result
+            .data
+            .as_ref()
+            .map_or(Value::Null, |data| normalize_result(method, data));
+        (true, data)
+    } else {
+        (false, Value::Null)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    checked: AtomicU64,
+    agreed: AtomicU64,
+    disagreed: AtomicU64,
+    unverified: AtomicU64,
+    quorum_failures: AtomicU64,
+}
+
+/// Checks results against several upstream providers
+///
+/// Catches providers that lie or lag behind: a verified query is sent to
+/// several upstreams of the pool at once, their results compared once
+/// slot-dependent fields are removed, and the quorum policy decides what is
+/// returned. The result is flagged with the outcome.
+pub struct ResultConsensus {
+    config: ConsensusConfig,
+    counters: Counters,
+}
+
+impl ResultConsensus {
+    /// Create a checker
+    ///
+    /// Verified queries must go to at least two providers, and the sample
+    /// rate must be a fraction.
+    pub fn new(config: ConsensusConfig) -> ProxyResult<Self> {
+        if config.providers < 2 {
+            return Err(ProxyError::Internal(
+                "Result consensus needs at least two providers per query".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&config.sample_rate) {
+            return Err(ProxyError::Internal(format!(
+                "Consensus sample rate {} must lie between 0 and 1",
+                config.sample_rate
+            )));
+        }
+        Ok(Self {
+            config,
+            counters: Counters::default(),
+        })
+    }
+
+    /// Providers each verified query is sent to
+    pub fn providers(&self) -> usize {
+        self.config.providers
+    }
+
+    /// Decide whether a query is verified
+    ///
+    /// getBlockHeight moves with every slot, so it is never verified.
+    pub fn selects(&self, query: &Query) -> bool {
+        if query.method == RpcMethod::GetBlockHeight {
+            return false;
+        }
+        self.always_selects(query.method)
+            || (self.config.sample_rate > 0.0
+                && rand::thread_rng().gen_bool(self.config.sample_rate))
+    }
+
+    /// Whether every query of `method` is verified, whatever the sample rate
+    pub fn always_selects(&self, method: RpcMethod) -> bool {
+        method != RpcMethod::GetBlockHeight && self.config.methods.contains(&method)
+    }
+
+    /// Send a query to several providers of `pool` and resolve their results
+    pub async fn execute(
+        &self,
+        pool: &UpstreamPool,
+        query: Query,
+        preferred: Option<usize>,
+    ) -> QueryResult {
+        let (id, method) = (query.id.clone(), query.method);
+        let results = pool
+            .execute_distinct(query, preferred, self.config.providers)
+            .await;
+        self.resolve(id, method, results)
+    }
+
+    /// Compare the results of one `method` query from several providers
+    ///
+    /// Results lost to an upstream fault are not answers. The first answer
+    /// is the primary one.
+    pub fn resolve(
+        &self,
+        id: String,
+        method: RpcMethod,
+        results: Vec<QueryResult>,
+    ) -> QueryResult {
+        self.counters.checked.fetch_add(1, Ordering::Relaxed);
+        let (answers, faults): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .partition(|result| UpstreamFault::of(result).is_none());
+
+        if answers.len() < 2 {
+            self.counters.unverified.fetch_add(1, Ordering::Relaxed);
+            let result = answers.into_iter().chain(faults).next().unwrap_or_else(|| {
+                QueryResult::failure(
+                    id,
+                    "No upstream available: every circuit is open".to_string(),
+                )
+            });
+            return result.with_consensus(ConsensusStatus::Unverified);
+        }
+
+        // Group identical answers as (first answer, count)
+        let keys: Vec<_> = answers
+            .iter()
+            .map(|answer| answer_key(method, answer))
+            .collect();
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for (index, key) in keys.iter().enumerate() {
+            match groups.iter_mut().find(|(first, _)| keys[*first] == *key) {
+                Some((_, count)) => *count += 1,
+                None => groups.push((index, 1)),
+            }
+        }
+
+        if groups.len() == 1 {
+            self.counters.agreed.fetch_add(1, Ordering::Relaxed);
+            return answers[0].clone().with_consensus(ConsensusStatus::Agreed);
+        }
+
+        self.counters.disagreed.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            query_id = %id,
+            answers = answers.len(),
+            distinct = groups.len(),
+            "Upstream providers disagree"
+        );
+        let chosen = match self.config.policy {
+            QuorumPolicy::Primary => Some(0),
+            QuorumPolicy::Majority => groups
+                .iter()
+                .find(|(_, count)| count * 2 > answers.len())
+                .map(|(first, _)| *first),
+            QuorumPolicy::Unanimous => None,
+        };
+        match chosen {
+            Some(index) => answers[index]
+                .clone()
+                .with_consensus(ConsensusStatus::Disagreed),
+            None => {
+                self.counters
+                    .quorum_failures
+                    .fetch_add(1, Ordering::Relaxed);
+                QueryResult::failure(
+                    id,
+                    "No quorum: upstream providers returned different results".to_string(),
+                )
+                .with_consensus(ConsensusStatus::Disagreed)
+            }
+        }
+    }
+
+    /// Snapshot of the checker's counters
+    pub fn metrics(&self) -> ConsensusMetrics {
+        ConsensusMetrics {
+            checked: self.counters.checked.load(Ordering::Relaxed),
+            agreed: self.counters.agreed.load(Ordering::Relaxed),
+            disagreed: self.counters.disagreed.load(Ordering::Relaxed),
+            unverified: self.counters.unverified.load(Ordering::Relaxed),
+            quorum_failures: self.counters.quorum_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn consensus(policy: QuorumPolicy) -> ResultConsensus {
+        ResultConsensus::new(
+            ConsensusConfig::new(0.0)
+                .with_providers(3)
+                .with_policy(policy),
+        )
+        .unwrap()
+    }
+
+    fn answer(lamports: u64, slot: u64) -> QueryResult {
+        QueryResult::success(
+            "q".to_string(),
+            json!({ "context": { "slot": slot }, "lamports": lamports, "rentEpoch": slot }),
+        )
+    }
+
+    fn fault() -> QueryResult {
+        QueryResult::failure(
+            "q".to_string(),
+            "HTTP status client error (429 Too Many Requests)".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_rejects_invalid_configurations() {
+        assert!(ResultConsensus::new(ConsensusConfig::new(0.5).with_providers(1)).is_err());
+        assert!(ResultConsensus::new(ConsensusConfig::new(1.5)).is_err());
+        assert!(ResultConsensus::new(ConsensusConfig::new(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_normalize_drops_slot_dependent_fields() {
+        let account = json!({ "context": { "slot": 5 }, "rentEpoch": 1, "lamports": 2 });
+        assert_eq!(
+            normalize_result(RpcMethod::GetAccountInfo, &account),
+            json!({ "lamports": 2 })
+        );
+
+        let accounts = json!([{ "rentEpoch": 1, "lamports": 2 }, null]);
+        assert_eq!(
+            normalize_result(RpcMethod::GetMultipleAccounts, &accounts),
+            json!([{ "lamports": 2 }, null])
+        );
+
+        // A transaction's slot and nested fields are its content
+        let transaction = json!({ "slot": 5, "meta": { "rentEpoch": 1 } });
+        assert_eq!(
+            normalize_result(RpcMethod::GetTransaction, &transaction),
+            transaction
+        );
+    }
+
+    #[test]
+    fn test_transactions_at_different_slots_disagree() {
+        let transaction = |slot: u64| {
+            QueryResult::success("q".to_string(), json!({ "slot": slot, "blockTime": 1 }))
+        };
+        let consensus = consensus(QuorumPolicy::Primary);
+        let result = consensus.resolve(
+            "q".to_string(),
+            RpcMethod::GetTransaction,
+            vec![transaction(100), transaction(101)],
+        );
+        assert_eq!(result.consensus, Some(ConsensusStatus::Disagreed));
+    }
+
+    #[test]
+    fn test_selects_listed_methods_and_samples() {
+        let query = |method| Query::new("q".to_string(), method, "key".to_string());
+        let listed = ResultConsensus::new(
+            ConsensusConfig::new(0.0).with_methods(vec![RpcMethod::GetAccountInfo]),
+        )
+        .unwrap();
+        assert!(listed.selects(&query(RpcMethod::GetAccountInfo)));
+        assert!(!listed.selects(&query(RpcMethod::GetBalance)));
+        assert!(listed.always_selects(RpcMethod::GetAccountInfo));
+
+        let all = ResultConsensus::new(ConsensusConfig::new(1.0)).unwrap();
+        assert!(all.selects(&query(RpcMethod::GetBalance)));
+        assert!(!all.selects(&Query::with_params(
+            "q".to_string(),
+            RpcMethod::GetBlockHeight,
+            Value::Null
+        )));
+    }
+
+    #[test]
+    fn test_agreement_ignores_slots() {
+        let consensus = consensus(QuorumPolicy::Majority);
+        let result = consensus.resolve("q".to_string(), RpcMethod::GetAccountInfo, vec![answer(7, 100), answer(7, 101)]);
+        assert_eq!(result.consensus, Some(ConsensusStatus::Agreed));
+        assert!(result.success);
+        assert_eq!(consensus.metrics().agreed, 1);
+    }
+
+    #[test]
+    fn test_faults_leave_result_unverified() {
+        let consensus = consensus(QuorumPolicy::Majority);
+        let result = consensus.resolve("q".to_string(), RpcMethod::GetAccountInfo, vec![fault(), answer(7, 100), fault()]);
+        assert_eq!(result.consensus, Some(ConsensusStatus::Unverified));
+        assert!(result.success);
+
+        let result = consensus.resolve("q".to_string(), RpcMethod::GetAccountInfo, vec![fault()]);
+        assert!(!result.success);
+        assert_eq!(consensus.metrics().unverified, 2);
+    }
+
+    #[test]
+    fn test_quorum_policies_on_disagreement() {
+        let answers = || vec![answer(1, 100), answer(2, 100), answer(2, 101)];
+
+        let primary = consensus(QuorumPolicy::Primary).resolve("q".to_string(), RpcMethod::GetAccountInfo, answers());
+        assert_eq!(primary.consensus, Some(ConsensusStatus::Disagreed));
+        assert_eq!(primary.data.unwrap()["lamports"], 1);
+
+        let majority = consensus(QuorumPolicy::Majority).resolve("q".to_string(), RpcMethod::GetAccountInfo, answers());
+        assert_eq!(majority.consensus, Some(ConsensusStatus::Disagreed));
+        assert_eq!(majority.data.unwrap()["lamports"], 2);
+
+        let unanimous = consensus(QuorumPolicy::Unanimous);
+        let result = unanimous.resolve("q".to_string(), RpcMethod::GetAccountInfo, answers());
+        assert!(!result.success);
+        assert_eq!(result.consensus, Some(ConsensusStatus::Disagreed));
+        assert_eq!(unanimous.metrics().quorum_failures, 1);
+
+        // A tie has no majority
+        let majority = consensus(QuorumPolicy::Majority);
+        let result = majority.resolve("q".to_string(), RpcMethod::GetAccountInfo, vec![answer(1, 100), answer(2, 100)]);
+        assert!(!result.success);
+        assert_eq!(majority.metrics().disagreed, 1);
+    }
+}
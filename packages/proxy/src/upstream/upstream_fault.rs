@@ -0,0 +1,101 @@
+//! Classification of failed upstream calls
+
+use crate::enums::QueryErrorCode;
+use crate::types::QueryResult;
+
+/// Why a failed call counts against its upstream
+///
+/// Failures not classified as a fault (an invalid pubkey, a missing
+/// transaction) say nothing about the upstream and are returned as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamFault {
+    /// HTTP 429: the upstream is throttling the proxy
+    RateLimited,
+    /// The upstream could not be reached or failed to answer
+    Unavailable,
+}
+
+impl UpstreamFault {
+    /// Classify a failed result, by its error code when it has one
+    pub fn of(result: &QueryResult) -> Option<Self> {
+        match result.error_code {
+            Some(QueryErrorCode::RateLimited) => Some(UpstreamFault::RateLimited),
+            Some(QueryErrorCode::Unavailable) => Some(UpstreamFault::Unavailable),
+            Some(_) => None,
+            None => result.error.as_deref().and_then(Self::classify),
+        }
+    }
+
+    /// Error code reported for a query failed by this fault
+    pub fn code(self) -> QueryErrorCode {
+        match self {
+            UpstreamFault::RateLimited => QueryErrorCode::RateLimited,
+            UpstreamFault::Unavailable => QueryErrorCode::Unavailable,
+        }
+    }
+
+    /// Classify the error message of a failed call
+    pub fn classify(error: &str) -> Option<Self> {
+        const UNAVAILABLE: &[&str] = &[
+            "error sending request",
+            "connection",
+            "timed out",
+            "status server error",
+            "502 Bad Gateway",
+            "503 Service Unavailable",
+            "504 Gateway Timeout",
+        ];
+
+        if error.contains("429") || error.contains("Too Many Requests") {
+            Some(UpstreamFault::RateLimited)
+        } else if UNAVAILABLE.iter().any(|pattern| error.contains(pattern)) {
+            Some(UpstreamFault::Unavailable)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_errors() {
+        assert_eq!(
+            UpstreamFault::classify(
+                "HTTP status client error (429 Too Many Requests) for url (https://rpc/)"
+            ),
+            Some(UpstreamFault::RateLimited)
+        );
+        assert_eq!(
+            UpstreamFault::classify(
+                "RPC request error: cluster version query failed: error sending request for url \
+                 (http://127.0.0.1:1/): error trying to connect: tcp connect error: Connection \
+                 refused (os error 111)"
+            ),
+            Some(UpstreamFault::Unavailable)
+        );
+        assert_eq!(
+            UpstreamFault::classify("Invalid pubkey 'abc': Invalid Base58 string"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_error_code_takes_precedence() {
+        let failure = QueryResult::failure("a".to_string(), "HTTP 429".to_string());
+        assert_eq!(
+            UpstreamFault::of(&failure),
+            Some(UpstreamFault::RateLimited)
+        );
+        assert_eq!(
+            UpstreamFault::of(&failure.clone().with_error_code(QueryErrorCode::Invalid)),
+            None
+        );
+        assert_eq!(
+            UpstreamFault::of(&failure.with_error_code(QueryErrorCode::Unavailable)),
+            Some(UpstreamFault::Unavailable)
+        );
+    }
+}
@@ -0,0 +1,652 @@
+//! Pool of upstream RPC endpoints with per-query failover
+
+use super::{CallSlots, CircuitBreaker, HealthScore, UpstreamFault};
+use crate::enums::QueryErrorCode;
+use crate::error::{ProxyError, ProxyResult};
+use crate::executor::execute_single_query;
+use crate::scheduler::ShardRoom;
+use crate::types::{
+    FailoverConfig, Query, QueryResult, UpstreamMetrics, UpstreamStatus,
+    DEFAULT_MAX_IN_FLIGHT_CALLS,
+};
+use futures::future::join_all;
+use rand::Rng;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, SemaphorePermit};
+use tracing::{debug, warn};
+
+/// Sanitize an RPC URL for logs and `/health` (hide API keys)
+///
+/// Everything after the host is replaced, since providers embed keys in the
+/// path or the query string.
+pub fn sanitize_rpc_url(url: &str) -> String {
+    if let Some(idx) = url.find("://") {
+        let after_scheme = &url[idx + 3..];
+        if let Some(path_idx) = after_scheme.find(['/', '?']) {
+            let host = &after_scheme[..path_idx];
+            return format!("{}://{}/***", &url[..idx], host);
+        }
+    }
+    url.to_string()
+}
+
+#[derive(Debug)]
+struct UpstreamState {
+    breaker: CircuitBreaker,
+    score: HealthScore,
+}
+
+/// One endpoint of the pool
+struct Upstream {
+    url: String,
+    client: Arc<RpcClient>,
+    state: Mutex<UpstreamState>,
+}
+
+impl Upstream {
+    fn new(url: &str, config: &FailoverConfig, slots: &Arc<CallSlots>) -> Self {
+        Self {
+            url: sanitize_rpc_url(url),
+            client: Arc::new(slots.client(url)),
+            state: Mutex::new(UpstreamState {
+                breaker: CircuitBreaker::new(
+                    config.failure_threshold,
+                    Duration::from_millis(config.cooldown_ms),
+                    Duration::from_millis(config.max_cooldown_ms),
+                ),
+                score: HealthScore::default(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UpstreamState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Send a single call, recording its latency and outcome
+    async fn call(&self, query: Query) -> (QueryResult, Option<UpstreamFault>) {
+        let started = Instant::now();
+        let result = execute_single_query(Arc::clone(&self.client), query).await;
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        let fault = UpstreamFault::of(&result);
+        self.record(latency_ms, fault);
+        (result, fault)
+    }
+
+    fn record(&self, latency_ms: f64, fault: Option<UpstreamFault>) {
+        let mut state = self.lock();
+        state.score.record(latency_ms, fault.is_some());
+        match fault {
+            None => state.breaker.record_success(),
+            Some(fault) => {
+                let rate_limited = fault == UpstreamFault::RateLimited;
+                state.breaker.record_failure(Instant::now(), rate_limited);
+            }
+        }
+    }
+}
+
+/// Upstream endpoints queries fail over between
+///
+/// Every upstream keeps a rolling latency and error score and a circuit
+/// breaker. A query goes to its preferred upstream, or the best scored one,
+/// and on an upstream fault (transport error, HTTP 429 or 5xx) is retried
+/// with jittered exponential backoff on the next upstream whose circuit is
+/// not open, within the retry budget of its method. Calls of a sharded batch
+/// only fail over to upstreams the batch still has room on.
+///
+/// Every HTTP request to the upstreams takes one of a global number of call
+/// slots: past it requests queue until one in flight completes, however many
+/// batches are running.
+pub struct UpstreamPool {
+    upstreams: Vec<Upstream>,
+    config: FailoverConfig,
+    slots: Arc<CallSlots>,
+}
+
+impl UpstreamPool {
+    /// Create a pool over `urls`, in order of preference
+    pub fn new(urls: &[String], config: FailoverConfig) -> ProxyResult<Self> {
+        if urls.is_empty() {
+            return Err(ProxyError::Internal(
+                "The upstream pool needs at least one RPC endpoint".to_string(),
+            ));
+        }
+        let slots = Arc::new(CallSlots::new(DEFAULT_MAX_IN_FLIGHT_CALLS));
+        Ok(Self {
+            upstreams: urls
+                .iter()
+                .map(|url| Upstream::new(url, &config, &slots))
+                .collect(),
+            config,
+            slots,
+        })
+    }
+
+    /// Create a pool of a single upstream with the default failover settings
+    pub fn single(url: &str) -> Self {
+        let config = FailoverConfig::default();
+        let slots = Arc::new(CallSlots::new(DEFAULT_MAX_IN_FLIGHT_CALLS));
+        Self {
+            upstreams: vec![Upstream::new(url, &config, &slots)],
+            config,
+            slots,
+        }
+    }
+
+    /// Set the most upstream requests in flight at once (at least 1)
+    pub fn with_max_in_flight(self, limit: usize) -> Self {
+        self.with_call_slots(Arc::new(CallSlots::new(limit)))
+    }
+
+    /// Share `slots` with the other clients of the upstreams
+    pub fn with_call_slots(mut self, slots: Arc<CallSlots>) -> Self {
+        for upstream in &mut self.upstreams {
+            upstream.client = Arc::new(slots.client(&upstream.client.url()));
+        }
+        self.slots = slots;
+        self
+    }
+
+    /// Wait for one of the pool's call slots
+    ///
+    /// Requests made to an upstream without the pool's clients hold a slot
+    /// for as long as they are in flight.
+    pub async fn acquire_slot(&self) -> SemaphorePermit<'_> {
+        self.slots.acquire().await
+    }
+
+    /// Take one of the pool's call slots if one is free, without waiting
+    pub fn try_acquire_slot(&self) -> Option<OwnedSemaphorePermit> {
+        self.slots.try_acquire()
+    }
+
+    /// Report the pool's call slot usage
+    pub fn metrics(&self) -> UpstreamMetrics {
+        self.slots.metrics()
+    }
+
+    /// Number of upstreams
+    pub fn len(&self) -> usize {
+        self.upstreams.len()
+    }
+
+    /// Check whether the pool has no upstream (never true once built)
+    pub fn is_empty(&self) -> bool {
+        self.upstreams.is_empty()
+    }
+
+    /// Endpoint of upstream `index`, credentials included
+    pub fn endpoint(&self, index: usize) -> String {
+        self.upstreams[index].client.url()
+    }
+
+    /// Upstream a query without preference tries first
+    pub fn best(&self) -> usize {
+        self.order(None).first().copied().unwrap_or(0)
+    }
+
+    /// Check whether upstream `index` may receive a call now
+    ///
+    /// A successful check must be followed by [`record`](Self::record).
+    pub fn try_acquire(&self, index: usize) -> bool {
+        self.upstreams[index]
+            .lock()
+            .breaker
+            .try_acquire(Instant::now())
+    }
+
+    /// Record the outcome of a call made to upstream `index` directly
+    pub fn record(&self, index: usize, latency_ms: f64, fault: Option<UpstreamFault>) {
+        self.upstreams[index].record(latency_ms, fault);
+    }
+
+    /// Execute a query, failing over between upstreams
+    ///
+    /// Every request of an attempt waits for a call slot, so none is held
+    /// during the backoff. Failures that are not an upstream fault are returned at once; after
+    /// the last attempt the last failure is returned. The result records
+    /// how many calls were made.
+    ///
+    /// With `room`, the call belongs to a sharded batch: besides `preferred`
+    /// it may only go to upstreams it already reached or the batch still has
+    /// room on, so failover never breaks the shard cap.
+    pub async fn execute(
+        &self,
+        query: Query,
+        preferred: Option<usize>,
+        room: Option<&ShardRoom>,
+    ) -> QueryResult {
+        let order = self.order(preferred);
+        let mut reached: Vec<usize> = preferred.into_iter().collect();
+        let mut last_failure = None;
+        let mut calls = 0;
+
+        for attempt in 0..self.config.attempts_for(query.method).max(1) {
+            if attempt > 0 {
+                tokio::time::sleep(self.backoff(attempt)).await;
+            }
+
+            // Each attempt starts one upstream further down the order and
+            // takes the first whose circuit lets the call through
+            let now = Instant::now();
+            let Some(index) = order
+                .iter()
+                .cycle()
+                .skip(attempt as usize)
+                .take(order.len())
+                .copied()
+                .find(|index| self.admit(*index, room, &reached, now))
+            else {
+                break;
+            };
+            if !reached.contains(&index) {
+                reached.push(index);
+            }
+            let upstream = &self.upstreams[index];
+
+            let (result, fault) = upstream.call(query.clone()).await;
+            calls += 1;
+            match fault {
+                None => return result.with_attempts(calls),
+                Some(fault) => {
+                    debug!(
+                        upstream = %upstream.url,
+                        attempt = attempt,
+                        fault = ?fault,
+                        "Upstream call failed"
+                    );
+                    last_failure = Some(result);
+                }
+            }
+        }
+
+        last_failure
+            .unwrap_or_else(|| {
+                let reason = match room {
+                    Some(_) => "every circuit with room under the shard cap is open",
+                    None => "every circuit is open",
+                };
+                warn!(reason = reason, "No upstream available");
+                QueryResult::failure(query.id, format!("No upstream available: {}", reason))
+                    .with_error_code(QueryErrorCode::Unavailable)
+            })
+            .with_attempts(calls)
+    }
+
+    /// Check whether upstream `index` may take a call now
+    ///
+    /// Its circuit must let the call through and, for a call of a sharded
+    /// batch that has not reached it yet, the batch must have room on it.
+    fn admit(
+        &self,
+        index: usize,
+        room: Option<&ShardRoom>,
+        reached: &[usize],
+        now: Instant,
+    ) -> bool {
+        let room = room.filter(|_| !reached.contains(&index));
+        if room.is_some_and(|room| !room.take(index)) {
+            return false;
+        }
+        let admitted = self.upstreams[index].lock().breaker.try_acquire(now);
+        if let Some(room) = room.filter(|_| !admitted) {
+            room.release(index);
+        }
+        admitted
+    }
+
+    /// Execute a query in a single attempt, without retry or failover
+    ///
+    /// The call goes to the first upstream in the order [`execute`](Self::execute)
+    /// tries them whose circuit lets it through. A caller holding a call slot
+    /// runs it under [`CallSlots::with_slot`] so its requests use that slot.
+    pub async fn execute_once(&self, query: Query, preferred: Option<usize>) -> QueryResult {
+        let now = Instant::now();
+        let upstream = self
+            .order(preferred)
+            .into_iter()
+            .map(|index| &self.upstreams[index])
+            .find(|upstream| upstream.lock().breaker.try_acquire(now));
+
+        match upstream {
+            Some(upstream) => upstream.call(query).await.0.with_attempts(1),
+            None => QueryResult::failure(
+                query.id,
+                "No upstream available: every circuit is open".to_string(),
+            )
+            .with_error_code(QueryErrorCode::Unavailable)
+            .with_attempts(0),
+        }
+    }
+
+    /// Send a query to up to `count` distinct upstreams at once
+    ///
+    /// Upstreams are taken in the order [`execute`](Self::execute) tries
+    /// them, skipping those whose circuit is open. Each gets a single
+    /// attempt; results come back in that order, so the first one is from
+    /// the preferred upstream when it was available.
+    pub async fn execute_distinct(
+        &self,
+        query: Query,
+        preferred: Option<usize>,
+        count: usize,
+    ) -> Vec<QueryResult> {
+        let now = Instant::now();
+        let selected: Vec<&Upstream> = self
+            .order(preferred)
+            .into_iter()
+            .map(|index| &self.upstreams[index])
+            .filter(|upstream| upstream.lock().breaker.try_acquire(now))
+            .take(count)
+            .collect();
+
+        join_all(selected.into_iter().map(|upstream| {
+            let query = query.clone();
+            async move { upstream.call(query).await.0.with_attempts(1) }
+        }))
+        .await
+    }
+
+    /// Upstream indices in the order a query tries them
+    ///
+    /// The preferred upstream comes first, then the others by score; ties
+    /// keep the configured order.
+    fn order(&self, preferred: Option<usize>) -> Vec<usize> {
+        let costs: Vec<f64> = self
+            .upstreams
+            .iter()
+            .map(|upstream| upstream.lock().score.cost())
+            .collect();
+        let mut order: Vec<usize> = (0..self.upstreams.len()).collect();
+        order.sort_by(|a, b| costs[*a].total_cmp(&costs[*b]));
+
+        if let Some(preferred) = preferred.filter(|index| *index < order.len()) {
+            order.retain(|index| *index != preferred);
+            order.insert(0, preferred);
+        }
+        order
+    }
+
+    /// Delay before retry `attempt` (1 for the first retry)
+    ///
+    /// Doubles with every retry up to the cap, then picks a random point in
+    /// its upper half so concurrent retries spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay_ms = self
+            .config
+            .backoff_ms
+            .saturating_mul(1u64 << (attempt - 1).min(32))
+            .min(self.config.max_backoff_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(delay_ms / 2..=delay_ms))
+    }
+
+    /// Probe every upstream and report its state
+    ///
+    /// Probes take a call slot like any request, so they wait while the pool
+    /// is saturated.
+    pub async fn check_health(&self) -> Vec<UpstreamStatus> {
+        let probes = self
+            .upstreams
+            .iter()
+            .map(|upstream| upstream.client.get_health());
+        let probes = join_all(probes).await;
+
+        self.upstreams
+            .iter()
+            .zip(probes)
+            .map(|(upstream, probe)| {
+                let healthy = probe.is_ok();
+                let state = upstream.lock();
+                UpstreamStatus {
+                    url: upstream.url.clone(),
+                    healthy,
+                    circuit: state.breaker.state(Instant::now()),
+                    latency_ms: state.score.latency_ms(),
+                    error_rate: state.score.error_rate(),
+                    consecutive_failures: state.breaker.consecutive_failures(),
+                    calls: state.score.calls(),
+                    failures: state.score.failures(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{CircuitState, RpcMethod, ShardPolicy};
+    use crate::scheduler::UpstreamShards;
+    use crate::types::ShardConfig;
+    use crate::upstream::MockUpstream;
+
+    fn query() -> Query {
+        Query::new(
+            "query".to_string(),
+            RpcMethod::GetBalance,
+            "11111111111111111111111111111111".to_string(),
+        )
+    }
+
+    fn pool(urls: &[&str], config: FailoverConfig) -> UpstreamPool {
+        let urls: Vec<String> = urls.iter().map(|url| url.to_string()).collect();
+        UpstreamPool::new(&urls, config).unwrap()
+    }
+
+    #[test]
+    fn test_sanitize_rpc_url() {
+        assert_eq!(
+            sanitize_rpc_url("https://example.quiknode.pro/secret-token/"),
+            "https://example.quiknode.pro/***"
+        );
+        assert_eq!(
+            sanitize_rpc_url("https://rpc.example.com?api-key=secret"),
+            "https://rpc.example.com/***"
+        );
+        assert_eq!(
+            sanitize_rpc_url("http://127.0.0.1:8899"),
+            "http://127.0.0.1:8899"
+        );
+    }
+
+    #[test]
+    fn test_rejects_empty_pool() {
+        assert!(UpstreamPool::new(&[], FailoverConfig::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failover_tries_every_upstream() {
+        // Unreachable upstreams: every call fails fast
+        let config = FailoverConfig::default().with_backoff(1, 1);
+        let pool = pool(&["http://127.0.0.1:1", "http://127.0.0.1:2"], config);
+
+        let result = pool.execute(query(), Some(1), None).await;
+        assert!(!result.success);
+        assert_eq!(result.id, "query");
+        assert_eq!(result.attempts, Some(3));
+        assert_eq!(result.error_code, Some(QueryErrorCode::Unavailable));
+
+        let statuses = pool.check_health().await;
+        assert_eq!(statuses[1].calls, 2);
+        assert_eq!(statuses[0].calls, 1);
+        assert!(statuses.iter().all(|status| !status.healthy));
+        assert_eq!(statuses[1].consecutive_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_is_per_method() {
+        let config = FailoverConfig::default()
+            .with_backoff(1, 1)
+            .with_method_attempts(RpcMethod::GetBalance, 1);
+        let pool = pool(&["http://127.0.0.1:1", "http://127.0.0.1:2"], config);
+
+        let result = pool.execute(query(), Some(0), None).await;
+        assert_eq!(result.attempts, Some(1));
+        let statuses = pool.check_health().await;
+        assert_eq!((statuses[0].calls, statuses[1].calls), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_open_circuits_are_skipped() {
+        let config = FailoverConfig::default()
+            .with_max_attempts(1)
+            .with_circuit_breaker(1, 60_000);
+        let pool = pool(&["http://127.0.0.1:1", "http://127.0.0.1:2"], config);
+
+        pool.execute(query(), Some(0), None).await;
+        pool.execute(query(), Some(0), None).await;
+        let result = pool.execute(query(), Some(0), None).await;
+        assert_eq!(
+            result.error.as_deref(),
+            Some("No upstream available: every circuit is open")
+        );
+
+        let statuses = pool.check_health().await;
+        assert!(statuses
+            .iter()
+            .all(|status| status.circuit == CircuitState::Open && status.calls == 1));
+    }
+
+    #[tokio::test]
+    async fn test_sharded_failover_stays_under_the_cap() {
+        let mocks = [
+            MockUpstream::start().await,
+            MockUpstream::start().await,
+            MockUpstream::start().await,
+        ];
+        let urls: Vec<String> = mocks.iter().map(MockUpstream::url).collect();
+        let pool = UpstreamPool::new(&urls, FailoverConfig::default().with_backoff(1, 1)).unwrap();
+        let shards = UpstreamShards::new(
+            3,
+            &ShardConfig::default()
+                .with_policy(ShardPolicy::RoundRobin)
+                .with_max_fraction(0.5),
+        )
+        .unwrap();
+        // Four calls, at most two per provider: one more fits on 0 and 2
+        let room = shards.room(&[0, 1, 1, 2]);
+
+        // The breaker of provider 0 opens
+        pool.record(0, 1.0, Some(UpstreamFault::RateLimited));
+
+        let moved = pool.execute(query(), Some(0), Some(&room)).await;
+        assert!(moved.success);
+        assert_eq!(mocks[2].call_count(), 1);
+
+        // Provider 1 is full and 2 now is too: the call fails instead
+        let stuck = pool.execute(query(), Some(0), Some(&room)).await;
+        assert!(!stuck.success);
+        assert_eq!(stuck.error_code, Some(QueryErrorCode::Unavailable));
+        assert_eq!(stuck.attempts, Some(0));
+        let calls: Vec<usize> = mocks.iter().map(MockUpstream::call_count).collect();
+        assert_eq!(calls, [0, 0, 1]);
+
+        // Without a shard plan the same call fails over anywhere
+        assert!(pool.execute(query(), Some(0), None).await.success);
+    }
+
+    #[tokio::test]
+    async fn test_execute_distinct_skips_open_circuits() {
+        let config = FailoverConfig::default().with_circuit_breaker(1, 60_000);
+        let pool = pool(
+            &[
+                "http://127.0.0.1:1",
+                "http://127.0.0.1:2",
+                "http://127.0.0.1:3",
+            ],
+            config,
+        );
+
+        let results = pool.execute_distinct(query(), Some(2), 2).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.id == "query"));
+
+        // Both circuits opened, only the third upstream is left
+        assert_eq!(pool.execute_distinct(query(), Some(2), 2).await.len(), 1);
+        let statuses = pool.check_health().await;
+        assert!(statuses.iter().all(|status| status.calls == 1));
+    }
+
+    #[tokio::test]
+    async fn test_calls_queue_for_a_slot() {
+        let config = FailoverConfig::default().with_max_attempts(1);
+        let pool = Arc::new(pool(&["http://127.0.0.1:1"], config).with_max_in_flight(1));
+
+        let slot = pool.acquire_slot().await;
+        let call = tokio::spawn({
+            let pool = Arc::clone(&pool);
+            async move { pool.execute(query(), None, None).await }
+        });
+        while pool.metrics().queue_depth == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(pool.metrics().in_flight, 1);
+
+        drop(slot);
+        assert!(!call.await.unwrap().success);
+        let metrics = pool.metrics();
+        assert_eq!((metrics.in_flight, metrics.queue_depth), (0, 0));
+        assert_eq!(metrics.max_in_flight, 1);
+        assert!(metrics.queued_calls >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_every_chunk_of_a_call_takes_a_slot() {
+        let upstream = MockUpstream::start().await;
+        let pool = Arc::new(UpstreamPool::single(&upstream.url()).with_max_in_flight(2));
+        // The client asks for the node version once, before the upstream hangs
+        assert!(pool.execute(query(), None, None).await.success);
+        upstream.hang();
+
+        let accounts: Vec<String> = (0..250)
+            .map(|_| solana_sdk::pubkey::Pubkey::new_unique().to_string())
+            .collect();
+        let accounts = Query::with_params(
+            "accounts".to_string(),
+            RpcMethod::GetMultipleAccounts,
+            serde_json::json!(accounts),
+        );
+        let call = tokio::spawn({
+            let pool = Arc::clone(&pool);
+            async move { pool.execute(accounts, None, None).await }
+        });
+        // Three chunks: two in flight, the third waits for a slot
+        while pool.metrics().queue_depth == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(pool.metrics().in_flight, 2);
+        call.abort();
+    }
+
+    #[test]
+    fn test_order_prefers_low_cost() {
+        let pool = pool(
+            &["http://a", "http://b", "http://c"],
+            FailoverConfig::default(),
+        );
+        pool.upstreams[0].record(500.0, None);
+        pool.upstreams[1].record(20.0, None);
+        pool.upstreams[2].record(100.0, None);
+
+        assert_eq!(pool.order(None), [1, 2, 0]);
+        assert_eq!(pool.order(Some(0)), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let pool = pool(
+            &["http://a"],
+            FailoverConfig::default().with_backoff(100, 300),
+        );
+        let first = pool.backoff(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let second = pool.backoff(2);
+        assert!(second >= Duration::from_millis(100) && second <= Duration::from_millis(200));
+        assert!(pool.backoff(10) <= Duration::from_millis(300));
+    }
+}
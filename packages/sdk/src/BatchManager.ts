@@ -125,12 +125,13 @@ export class BatchManager {
             commitment: q.commitment,
         }));
 
-        const request: BatchRequest = {
-            queries: queryData,
-            batchHash: hashBatch(queryData),
-        };
-
         try {
+            // Hashing validates every query, so a bad key fails this batch only
+            const request: BatchRequest = {
+                queries: queryData,
+                batchHash: hashBatch(queryData),
+            };
+
             const response = await this.httpClient.post<BatchResponse>("/execute-batch", request);
 
             // Map results back to their queries
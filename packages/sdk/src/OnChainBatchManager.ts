@@ -14,9 +14,9 @@ import {
 } from "@solana/web3.js";
 import axios, { AxiosInstance } from "axios";
 import { CoordinatorClient } from "./coordinator";
-import { hashQuery, hashBatch } from "./utils";
+import { generateResultTicket, hashQueryCommitment, hashBatch } from "./utils";
 import { RpcMethod } from "./enums";
-import { BatchRequest, BatchResponse, PendingQuery, Query } from "./types";
+import { BatchRequest, BatchResponse, PendingQuery, Query, QueryResult } from "./types";
 
 /**
  * Configuration for on-chain batch management
@@ -43,6 +43,7 @@ export interface OnChainConfig {
 
 interface QueuedQuery extends PendingQuery {
     hash: Uint8Array;
+    ticket: string;
 }
 
 /**
@@ -53,10 +54,10 @@ interface QueuedQuery extends PendingQuery {
  * that no single party (including the proxy) can link queries to users.
  *
  * **Flow:**
- * 1. User adds query → hash submitted on-chain
+ * 1. User adds query → salted hash submitted on-chain
  * 2. When min queries reached (from any users) → batch finalized
- * 3. SDK sends actual queries to proxy for execution
- * 4. Results returned → batch marked complete on-chain
+ * 3. SDK sends actual queries and their salts to proxy for execution
+ * 4. Results returned (or fetched by ticket) → proxy marks batch complete on-chain
  *
  * @example
  * ```typescript
@@ -110,7 +111,7 @@ export class OnChainBatchManager {
      * Add a query to be batched with on-chain coordination
      *
      * This method:
-     * 1. Hashes the query (SHA-256)
+     * 1. Hashes the query with a salt derived from a fresh result ticket (SHA-256)
      * 2. Submits the hash on-chain to the coordinator
      * 3. Waits for batch to fill and finalize
      * 4. Returns the result when execution completes
@@ -134,20 +135,23 @@ export class OnChainBatchManager {
      * ```
      */
     async addQuery<T>(method: RpcMethod, pubkey: string, commitment?: string): Promise<T> {
+        const { ticket, salt } = generateResultTicket();
         const query: Query = {
             id: crypto.randomUUID(),
             method,
             pubkey,
             commitment: commitment as Query["commitment"],
+            salt,
         };
 
-        const queryHashHex = hashQuery(query);
+        const queryHashHex = hashQueryCommitment(query);
         const queryHash = Buffer.from(queryHashHex, "hex");
 
         return new Promise<T>(async (resolve, reject) => {
             const queuedQuery: QueuedQuery = {
                 ...query,
                 hash: queryHash,
+                ticket,
                 resolve: resolve as (value: unknown) => void,
                 reject,
             };
@@ -319,6 +323,7 @@ export class OnChainBatchManager {
             method: q.method,
             pubkey: q.pubkey,
             commitment: q.commitment,
+            salt: q.salt,
         }));
 
         const request: BatchRequest & { batchId: string } = {
@@ -330,10 +335,17 @@ export class OnChainBatchManager {
         try {
             const response = await this.httpClient.post<BatchResponse>("/execute-batch", request);
 
-            // Map results
-            const resultMap = new Map<string, BatchResponse["results"][0]>();
-            for (const result of response.data.results) {
-                resultMap.set(result.id, result);
+            // Map results, fetching each one by ticket if the proxy withholds them
+            const resultMap = new Map<string, QueryResult>();
+            if (response.data.resultDelivery === "ticket") {
+                for (const query of queries) {
+                    const result = await this.fetchResult(query.ticket);
+                    if (result) resultMap.set(query.id, result);
+                }
+            } else {
+                for (const result of response.data.results) {
+                    resultMap.set(result.id, result);
+                }
             }
 
             // Resolve queries
@@ -348,9 +360,8 @@ export class OnChainBatchManager {
                 }
             }
 
-            // Mark batch complete on-chain
-            const resultsHash = Buffer.from(response.data.batchHash, "hex");
-            await this.completeBatch(batchId, resultsHash);
+            // The proxy signs complete_batch as coordinator authority, so the
+            // batch is marked executed without a client transaction
         } catch (error) {
             for (const query of queries) {
                 query.reject(error instanceof Error ? error : new Error("Batch execution failed"));
@@ -359,16 +370,15 @@ export class OnChainBatchManager {
     }
 
     /**
-     * Mark batch complete on-chain
+     * Fetch a withheld result by its ticket (one-time retrieval)
      */
-    private async completeBatch(batchId: bigint, resultsHash: Uint8Array): Promise<void> {
-        const completeIx = this.coordinator.createCompleteBatchInstruction(
-            batchId,
-            resultsHash,
-            this.config.wallet.publicKey
-        );
-        const tx = new Transaction().add(completeIx);
-        await sendAndConfirmTransaction(this.config.connection, tx, [this.config.wallet]);
+    private async fetchResult(ticket: string): Promise<QueryResult | undefined> {
+        try {
+            const response = await this.httpClient.get<QueryResult>(`/results/${ticket}`);
+            return response.data;
+        } catch {
+            return undefined;
+        }
     }
 
     private sleep(ms: number): Promise<void> {
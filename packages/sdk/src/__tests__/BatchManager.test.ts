@@ -15,6 +15,11 @@ jest.mock("axios", () => ({
 
 import axios from "axios";
 
+// Valid base58 keys: batches are hashed with the canonical query encoding
+const PUBKEY_1 = "11111111111111111111111111111111";
+const PUBKEY_2 = "So11111111111111111111111111111111111111112";
+const PUBKEY_3 = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
 describe("BatchManager", () => {
     let manager: BatchManager;
     let mockPost: jest.Mock;
@@ -89,15 +94,15 @@ describe("BatchManager", () => {
             }));
 
             // Add queries (batch size is 3)
-            const promise1 = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
-            const promise2 = manager.addQuery(RpcMethod.GetBalance, "pubkey2");
+            const promise1 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
+            const promise2 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_2);
 
             // Should not have executed yet
             expect(mockPost).not.toHaveBeenCalled();
             expect(manager.pendingCount).toBe(2);
 
             // Add third query to trigger batch
-            const promise3 = manager.addQuery(RpcMethod.GetBalance, "pubkey3");
+            const promise3 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_3);
 
             // Wait for all promises
             const results = await Promise.all([promise1, promise2, promise3]);
@@ -109,15 +114,15 @@ describe("BatchManager", () => {
                     queries: expect.arrayContaining([
                         expect.objectContaining({
                             method: RpcMethod.GetBalance,
-                            pubkey: "pubkey1",
+                            pubkey: PUBKEY_1,
                         }),
                         expect.objectContaining({
                             method: RpcMethod.GetBalance,
-                            pubkey: "pubkey2",
+                            pubkey: PUBKEY_2,
                         }),
                         expect.objectContaining({
                             method: RpcMethod.GetBalance,
-                            pubkey: "pubkey3",
+                            pubkey: PUBKEY_3,
                         }),
                     ]),
                 })
@@ -139,7 +144,7 @@ describe("BatchManager", () => {
             }));
 
             // Add one query (below batch size)
-            const promise = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
 
             // Should not have executed yet
             expect(mockPost).not.toHaveBeenCalled();
@@ -159,9 +164,9 @@ describe("BatchManager", () => {
         it("should reject all queries on batch failure", async () => {
             mockPost.mockRejectedValue(new Error("Network error"));
 
-            const promise1 = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
-            const promise2 = manager.addQuery(RpcMethod.GetBalance, "pubkey2");
-            const promise3 = manager.addQuery(RpcMethod.GetBalance, "pubkey3");
+            const promise1 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
+            const promise2 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_2);
+            const promise3 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_3);
 
             await expect(promise1).rejects.toThrow("Network error");
             await expect(promise2).rejects.toThrow("Network error");
@@ -178,9 +183,9 @@ describe("BatchManager", () => {
                 return { success: true, data: { lamports: 100 } };
             });
 
-            const promise1 = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise1 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
             const promise2 = manager.addQuery(RpcMethod.GetBalance, "invalid");
-            const promise3 = manager.addQuery(RpcMethod.GetBalance, "pubkey3");
+            const promise3 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_3);
 
             const results = await Promise.allSettled([promise1, promise2, promise3]);
 
@@ -202,7 +207,7 @@ describe("BatchManager", () => {
                 data: { lamports: 100 },
             }));
 
-            const promise = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
 
             // Should not have executed yet
             expect(mockPost).not.toHaveBeenCalled();
@@ -229,7 +234,7 @@ describe("BatchManager", () => {
     describe("destroy", () => {
         it("should reject pending queries", async () => {
             const localManager = new BatchManager(defaultConfig);
-            const promise = localManager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise = localManager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
 
             localManager.destroy();
 
@@ -238,7 +243,7 @@ describe("BatchManager", () => {
 
         it("should clear pending count", async () => {
             const localManager = new BatchManager(defaultConfig);
-            const promise = localManager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise = localManager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
             expect(localManager.pendingCount).toBe(1);
 
             localManager.destroy();
@@ -258,14 +263,14 @@ describe("BatchManager", () => {
 
             expect(manager.pendingCount).toBe(0);
 
-            const promise1 = manager.addQuery(RpcMethod.GetBalance, "pubkey1");
+            const promise1 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_1);
             expect(manager.pendingCount).toBe(1);
 
-            const promise2 = manager.addQuery(RpcMethod.GetBalance, "pubkey2");
+            const promise2 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_2);
             expect(manager.pendingCount).toBe(2);
 
             // Trigger batch execution
-            const promise3 = manager.addQuery(RpcMethod.GetBalance, "pubkey3");
+            const promise3 = manager.addQuery(RpcMethod.GetBalance, PUBKEY_3);
 
             // Wait for all to complete
             await Promise.all([promise1, promise2, promise3]);
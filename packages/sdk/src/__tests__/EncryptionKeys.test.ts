@@ -0,0 +1,45 @@
+/**
+ * Tests for the proxy encryption key fingerprint
+ */
+
+import { fingerprintEncryptionKeys } from "../utils";
+import { ProxyEncryptionKey } from "../types";
+
+describe("fingerprintEncryptionKeys", () => {
+    const keys: ProxyEncryptionKey[] = [
+        {
+            kind: "longTerm",
+            publicKey: "11".repeat(32),
+            notBefore: 1_700_000_000,
+            notAfter: null,
+        },
+        {
+            kind: "rotating",
+            publicKey: "22".repeat(32),
+            notBefore: 1_700_003_600,
+            notAfter: 1_700_010_800,
+        },
+    ];
+
+    it("should match the proxy's fingerprint", () => {
+        // Same vector as the proxy's key_manager tests
+        expect(fingerprintEncryptionKeys(keys)).toBe(
+            "ba96c80379c8f6e6d760c72b8682854f633ce732d740d9e85592be65a97aafdd"
+        );
+    });
+
+    it("should depend on key order and validity windows", () => {
+        const fingerprint = fingerprintEncryptionKeys(keys);
+
+        expect(fingerprintEncryptionKeys([...keys].reverse())).not.toBe(fingerprint);
+        expect(
+            fingerprintEncryptionKeys([keys[0], { ...keys[1], notAfter: 1_800_000_000 }])
+        ).not.toBe(fingerprint);
+    });
+
+    it("should reject malformed public keys", () => {
+        expect(() => fingerprintEncryptionKeys([{ ...keys[0], publicKey: "zz" }])).toThrow(
+            "Invalid public key"
+        );
+    });
+});
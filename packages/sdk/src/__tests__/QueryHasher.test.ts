@@ -2,10 +2,45 @@
  * Tests for QueryHasher utilities
  */
 
-import { generateQueryId, hashQuery, hashBatch, verifyBatchHash } from "../utils";
+import { createHash } from "crypto";
+import * as fs from "fs";
+import * as path from "path";
+import {
+    generateQueryId,
+    generateSalt,
+    generateResultTicket,
+    hashQuery,
+    hashQueryCommitment,
+    encodeQuery,
+    hashBatch,
+    verifyBatchHash,
+    QUERY_HASH_VERSION,
+    SALT_LENGTH,
+} from "../utils";
 import { Query } from "../types";
 import { RpcMethod } from "../enums";
 
+interface QueryHashVector {
+    description: string;
+    query: Query;
+    encoding: string;
+    hash: string;
+}
+
+interface SaltedQueryHashVector {
+    description: string;
+    query: Query;
+    hash: string;
+}
+
+// Shared with the proxy's Rust tests so both implementations stay in lockstep
+const VECTOR_FILE = JSON.parse(
+    fs.readFileSync(
+        path.resolve(__dirname, "../../../../test-vectors/query-hash-v1.json"),
+        "utf8"
+    )
+) as { version: number; vectors: QueryHashVector[]; saltedVectors: SaltedQueryHashVector[] };
+
 describe("QueryHasher", () => {
     describe("generateQueryId", () => {
         it("should generate unique IDs", () => {
@@ -47,7 +82,7 @@ describe("QueryHasher", () => {
             const query2: Query = {
                 id: "test-2",
                 method: RpcMethod.GetBalance,
-                pubkey: "22222222222222222222222222222222",
+                pubkey: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
             };
 
             expect(hashQuery(query1)).not.toBe(hashQuery(query2));
@@ -99,6 +134,151 @@ describe("QueryHasher", () => {
 
             expect(hash).toMatch(/^[0-9a-f]{64}$/);
         });
+
+        it("should treat missing commitment as confirmed", () => {
+            const implicit: Query = {
+                id: "test-1",
+                method: RpcMethod.GetBalance,
+                pubkey: "11111111111111111111111111111111",
+            };
+
+            expect(hashQuery(implicit)).toBe(hashQuery({ ...implicit, commitment: "confirmed" }));
+        });
+
+        it("should include params in hash", () => {
+            const query1: Query = {
+                id: "test-1",
+                method: RpcMethod.GetMultipleAccounts,
+                params: ["11111111111111111111111111111111"],
+            };
+
+            const query2: Query = {
+                id: "test-1",
+                method: RpcMethod.GetMultipleAccounts,
+                params: ["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"],
+            };
+
+            expect(hashQuery(query1)).not.toBe(hashQuery(query2));
+        });
+
+        it("should separate methods with the same target", () => {
+            const balance: Query = {
+                id: "test-1",
+                method: RpcMethod.GetBalance,
+                pubkey: "11111111111111111111111111111111",
+            };
+
+            expect(hashQuery(balance)).not.toBe(
+                hashQuery({ ...balance, method: RpcMethod.GetAccountInfo })
+            );
+        });
+
+        it("should reject invalid pubkeys", () => {
+            const query: Query = {
+                id: "test-1",
+                method: RpcMethod.GetBalance,
+                pubkey: "pubkey1",
+            };
+
+            expect(() => hashQuery(query)).toThrow("Invalid base58 character");
+        });
+
+        it("should reject unsupported commitment levels", () => {
+            const query: Query = {
+                id: "test-1",
+                method: RpcMethod.GetBalance,
+                pubkey: "11111111111111111111111111111111",
+                commitment: "max",
+            };
+
+            expect(() => hashQuery(query)).toThrow("Unsupported commitment");
+        });
+    });
+
+    describe("hashQueryCommitment", () => {
+        const query: Query = {
+            id: "test-1",
+            method: RpcMethod.GetBalance,
+            pubkey: "11111111111111111111111111111111",
+        };
+
+        it("should require a salt", () => {
+            expect(() => hashQueryCommitment(query)).toThrow("Missing salt");
+        });
+
+        it("should depend on the salt", () => {
+            const saltA = hashQueryCommitment({ ...query, salt: "01".repeat(SALT_LENGTH) });
+            const saltB = hashQueryCommitment({ ...query, salt: "02".repeat(SALT_LENGTH) });
+
+            expect(saltA).not.toBe(hashQuery(query));
+            expect(saltA).not.toBe(saltB);
+        });
+
+        it("should not change the canonical hash", () => {
+            expect(hashQuery({ ...query, salt: generateSalt() })).toBe(hashQuery(query));
+        });
+
+        it("should commit to the encryption key", () => {
+            const salted = { ...query, salt: "01".repeat(SALT_LENGTH) };
+            const keyed = hashQueryCommitment({ ...salted, encryptionKey: "09".repeat(32) });
+
+            expect(keyed).not.toBe(hashQueryCommitment(salted));
+            expect(keyed).not.toBe(
+                hashQueryCommitment({ ...salted, encryptionKey: "0a".repeat(32) })
+            );
+            expect(() => hashQueryCommitment({ ...salted, encryptionKey: "zz" })).toThrow(
+                "Invalid encryption key"
+            );
+        });
+
+        it("should reject malformed salts", () => {
+            expect(() => hashQueryCommitment({ ...query, salt: "zz" })).toThrow("Invalid salt");
+            expect(() => hashQueryCommitment({ ...query, salt: "00".repeat(16) })).toThrow(
+                "Invalid salt"
+            );
+        });
+    });
+
+    describe("generateSalt", () => {
+        it("should generate unique 32-byte hex salts", () => {
+            const salt = generateSalt();
+
+            expect(salt).toMatch(/^[0-9a-f]{64}$/);
+            expect(salt).not.toBe(generateSalt());
+        });
+    });
+
+    describe("generateResultTicket", () => {
+        it("should derive the salt as the SHA-256 of the ticket", () => {
+            const { ticket, salt } = generateResultTicket();
+
+            expect(ticket).toMatch(/^[0-9a-f]{64}$/);
+            expect(salt).toBe(
+                createHash("sha256").update(Buffer.from(ticket, "hex")).digest("hex")
+            );
+            expect(generateResultTicket().ticket).not.toBe(ticket);
+        });
+    });
+
+    describe("published test vectors", () => {
+        it("should target the current encoding version", () => {
+            expect(VECTOR_FILE.version).toBe(QUERY_HASH_VERSION);
+        });
+
+        it.each(VECTOR_FILE.vectors.map((v) => [v.description, v] as const))(
+            "%s",
+            (_description, vector) => {
+                expect(encodeQuery(vector.query).toString("hex")).toBe(vector.encoding);
+                expect(hashQuery(vector.query)).toBe(vector.hash);
+            }
+        );
+
+        it.each(VECTOR_FILE.saltedVectors.map((v) => [v.description, v] as const))(
+            "%s",
+            (_description, vector) => {
+                expect(hashQueryCommitment(vector.query)).toBe(vector.hash);
+            }
+        );
     });
 
     describe("hashBatch", () => {
@@ -112,7 +292,7 @@ describe("QueryHasher", () => {
             const query2: Query = {
                 id: "2",
                 method: RpcMethod.GetAccountInfo,
-                pubkey: "22222222222222222222222222222222",
+                pubkey: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
             };
 
             const hash1 = hashBatch([query1, query2]);
@@ -134,7 +314,7 @@ describe("QueryHasher", () => {
                 {
                     id: "1",
                     method: RpcMethod.GetBalance,
-                    pubkey: "22222222222222222222222222222222",
+                    pubkey: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
                 },
             ];
 
@@ -158,7 +338,7 @@ describe("QueryHasher", () => {
                 {
                     id: "2",
                     method: RpcMethod.GetBalance,
-                    pubkey: "22222222222222222222222222222222",
+                    pubkey: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
                 },
             ];
 
@@ -194,7 +374,7 @@ describe("QueryHasher", () => {
                 {
                     id: "1",
                     method: RpcMethod.GetBalance,
-                    pubkey: "22222222222222222222222222222222",
+                    pubkey: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
                 },
             ];
 
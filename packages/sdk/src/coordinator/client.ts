@@ -14,6 +14,8 @@ import {
 } from "@solana/web3.js";
 import { createHash } from "crypto";
 import { PROGRAM_ID } from "./idl";
+import { ProxyKeysResponse } from "../types";
+import { fingerprintEncryptionKeys } from "../utils";
 import BN from "bn.js";
 
 /**
@@ -50,6 +52,8 @@ export interface CoordinatorState {
     maxBatchSize: number;
     /** Counter for next batch ID */
     batchCounter: bigint;
+    /** Hex-encoded fingerprint of the proxy's encryption keys, null if unpublished */
+    encryptionKeyFingerprint: string | null;
 }
 
 /**
@@ -128,8 +132,40 @@ export class CoordinatorClient {
         const minBatchSize = data[40];
         const maxBatchSize = data[41];
         const batchCounter = data.readBigUInt64LE(42);
+        // Accounts created before the fingerprint field end after the bump (byte 50)
+        const fingerprint = data.length >= 83 ? data.slice(51, 83) : null;
+        const encryptionKeyFingerprint =
+            fingerprint && fingerprint.some((byte) => byte !== 0)
+                ? fingerprint.toString("hex")
+                : null;
+
+        return { authority, minBatchSize, maxBatchSize, batchCounter, encryptionKeyFingerprint };
+    }
 
-        return { authority, minBatchSize, maxBatchSize, batchCounter };
+    /**
+     * Check the proxy's encryption keys against the fingerprint published on-chain
+     *
+     * @param response - Response of the proxy's `GET /keys` endpoint
+     * @returns Whether the served keys match both their claimed and the on-chain fingerprint
+     *
+     * @example
+     * ```typescript
+     * const { data } = await axios.get<ProxyKeysResponse>(`${proxyEndpoint}/keys`);
+     * if (!(await client.verifyProxyKeys(data))) {
+     *     throw new Error("Proxy encryption keys do not match the chain");
+     * }
+     * ```
+     */
+    async verifyProxyKeys(response: ProxyKeysResponse): Promise<boolean> {
+        const state = await this.getCoordinatorState();
+        if (!state?.encryptionKeyFingerprint) return false;
+
+        const fingerprint = fingerprintEncryptionKeys(response.keys);
+        return (
+            fingerprint === response.fingerprint &&
+            fingerprint === state.encryptionKeyFingerprint &&
+            response.keys.some((key) => key.publicKey === response.currentKey)
+        );
     }
 
     /**
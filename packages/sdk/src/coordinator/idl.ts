@@ -68,6 +68,15 @@ export const IDL = {
                 { name: "resultsHash", type: { array: ["u8", 32] } },
             ],
         },
+        {
+            name: "setEncryptionKey",
+            accounts: [
+                { name: "coordinatorState", writable: true },
+                { name: "authority", writable: true, signer: true },
+                { name: "systemProgram", address: "11111111111111111111111111111111" },
+            ],
+            args: [{ name: "fingerprint", type: { array: ["u8", 32] } }],
+        },
     ],
 } as const;
 
@@ -32,6 +32,8 @@ export type {
     QueryResult,
     BatchRequest,
     BatchResponse,
+    ProxyEncryptionKey,
+    ProxyKeysResponse,
     AccountInfoResult,
     BalanceResult,
 } from "./types";
@@ -49,7 +51,23 @@ export {
 } from "./types";
 
 // Utility exports
-export { generateQueryId, hashQuery, hashBatch, verifyBatchHash } from "./utils";
+export {
+    generateQueryId,
+    generateSalt,
+    generateResultTicket,
+    hashQuery,
+    hashQueryCommitment,
+    encodeQuery,
+    hashBatch,
+    verifyBatchHash,
+    fingerprintEncryptionKeys,
+    KEY_FINGERPRINT_DOMAIN,
+    KEY_FINGERPRINT_VERSION,
+    QUERY_HASH_DOMAIN,
+    QUERY_HASH_VERSION,
+    SALT_LENGTH,
+} from "./utils";
+export type { ResultTicket } from "./utils";
 
 // Coordinator exports (on-chain)
 export { CoordinatorClient, PROGRAM_ID as COORDINATOR_PROGRAM_ID } from "./coordinator";
@@ -18,5 +18,8 @@ export { Query, PendingQuery, createQuery } from "./query";
 // Response types
 export { QueryResult, BatchRequest, BatchResponse, createBatchRequest } from "./response";
 
+// Key types
+export { ProxyEncryptionKey, ProxyKeysResponse } from "./keys";
+
 // Result types
 export { AccountInfoResult, BalanceResult, isAccountInfoResult, isBalanceResult } from "./result";
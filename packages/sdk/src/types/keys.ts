@@ -0,0 +1,34 @@
+/**
+ * Proxy encryption key types
+ */
+
+/**
+ * A proxy encryption key as served at `GET /keys`
+ */
+export interface ProxyEncryptionKey {
+    /** Long-term key or rotating key */
+    kind: "longTerm" | "rotating";
+
+    /** Hex-encoded X25519 public key */
+    publicKey: string;
+
+    /** Unix timestamp (seconds) from which the key is valid */
+    notBefore: number;
+
+    /** Unix timestamp (seconds) at which the key expires, null if it never does */
+    notAfter: number | null;
+}
+
+/**
+ * Response of the proxy's `GET /keys` endpoint
+ */
+export interface ProxyKeysResponse {
+    /** Hex-encoded fingerprint of `keys`, as published on-chain */
+    fingerprint: string;
+
+    /** Hex-encoded public key to encrypt to */
+    currentKey: string;
+
+    /** Active keys: long-term first, then rotating keys oldest first */
+    keys: ProxyEncryptionKey[];
+}
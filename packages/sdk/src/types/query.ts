@@ -21,8 +21,14 @@ export interface Query {
     /** Generic params for methods that need different inputs */
     params?: string | string[];
 
-    /** Optional commitment level */
+    /** Optional commitment level (defaults to confirmed) */
     commitment?: Commitment;
+
+    /** Hex-encoded 32-byte commit-reveal salt (revealed only to the proxy) */
+    salt?: string;
+
+    /** Hex-encoded X25519 public key the proxy encrypts this query's result to */
+    encryptionKey?: string;
 }
 
 /**
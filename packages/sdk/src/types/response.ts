@@ -19,6 +19,16 @@ export interface QueryResult {
 
     /** Error message (if failed) */
     error?: string;
+
+    /**
+     * Machine-readable failure reason: the query missed its deadline, the
+     * upstreams throttled the proxy or were unavailable, or the upstream
+     * rejected the query
+     */
+    errorCode?: "timeout" | "rate-limited" | "unavailable" | "invalid";
+
+    /** Number of upstream calls made for the result, retries included */
+    attempts?: number;
 }
 
 /**
@@ -33,6 +43,15 @@ export interface BatchRequest {
 
     /** On-chain batch ID (for coordinated batches) */
     batchId?: string;
+
+    /** Return results in random order instead of query order */
+    shuffleResults?: boolean;
+
+    /** Per-query timeout in milliseconds, if shorter than the proxy's */
+    queryTimeoutMs?: number;
+
+    /** Deadline of the whole batch in milliseconds, if shorter than the proxy's */
+    batchTimeoutMs?: number;
 }
 
 /**
@@ -56,6 +75,21 @@ export interface BatchResponse {
 
     /** Hash of the results batch */
     batchHash: string;
+
+    /**
+     * How the proxy verified the batch before execution: against a finalized
+     * on-chain batch, not at all despite a batchId, or not coordinated
+     */
+    verificationMode: "onchain" | "unverified" | "uncoordinated";
+
+    /**
+     * How results are delivered: in `results`, or withheld and served per
+     * submitter through `GET /results/{ticket}`
+     */
+    resultDelivery: "inline" | "ticket";
+
+    /** Whether some queries missed their deadline and failed with a timeout */
+    partial: boolean;
 }
 
 /**
@@ -0,0 +1,42 @@
+/**
+ * Base58 decoding utility
+ *
+ * Decodes Bitcoin-alphabet base58 strings (as used by Solana pubkeys and
+ * signatures) into raw bytes.
+ */
+
+const ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/**
+ * Decode a base58 string into bytes
+ *
+ * Leading `1` characters decode to leading zero bytes, so the output length
+ * is exact and can be checked against the expected key or signature size.
+ *
+ * @param value - Base58 string to decode
+ * @returns Decoded bytes
+ * @throws Error if the string contains a non-base58 character
+ */
+export function decodeBase58(value: string): Uint8Array {
+    let num = 0n;
+    for (const char of value) {
+        const digit = ALPHABET.indexOf(char);
+        if (digit < 0) {
+            throw new Error(`Invalid base58 character '${char}'`);
+        }
+        num = num * 58n + BigInt(digit);
+    }
+
+    const bytes: number[] = [];
+    while (num > 0n) {
+        bytes.unshift(Number(num & 0xffn));
+        num >>= 8n;
+    }
+
+    let leadingZeros = 0;
+    while (leadingZeros < value.length && value[leadingZeros] === "1") {
+        leadingZeros++;
+    }
+
+    return Uint8Array.from([...new Array(leadingZeros).fill(0), ...bytes]);
+}
@@ -0,0 +1,58 @@
+/**
+ * Proxy encryption key fingerprint
+ *
+ * Mirrors the proxy's key set fingerprint, which is published in the
+ * coordinator state:
+ *
+ * ```text
+ * SHA-256("privacy-rpc/encryption-keys" || 0x01 || count (u32 LE)
+ *         || for each key: publicKey (32) || notBefore (i64 LE)
+ *                          || 0x00 | 0x01 || notAfter (i64 LE))
+ * ```
+ */
+
+import { createHash } from "crypto";
+import { ProxyEncryptionKey } from "../types/keys";
+
+/** Domain separator of the key set fingerprint */
+export const KEY_FINGERPRINT_DOMAIN = "privacy-rpc/encryption-keys";
+
+/** Version byte of the key set fingerprint encoding */
+export const KEY_FINGERPRINT_VERSION = 1;
+
+function encodeI64(value: number): Buffer {
+    const buffer = Buffer.alloc(8);
+    buffer.writeBigInt64LE(BigInt(value));
+    return buffer;
+}
+
+/**
+ * Compute the fingerprint of a key set, in the order served by the proxy
+ *
+ * @param keys - Keys from `GET /keys`
+ * @returns Hex-encoded SHA-256 fingerprint
+ * @throws If a public key is not 32 hex-encoded bytes
+ */
+export function fingerprintEncryptionKeys(keys: ProxyEncryptionKey[]): string {
+    const count = Buffer.alloc(4);
+    count.writeUInt32LE(keys.length);
+
+    const hash = createHash("sha256")
+        .update(Buffer.from(KEY_FINGERPRINT_DOMAIN, "utf8"))
+        .update(Buffer.from([KEY_FINGERPRINT_VERSION]))
+        .update(count);
+
+    for (const key of keys) {
+        if (!/^[0-9a-fA-F]{64}$/.test(key.publicKey)) {
+            throw new Error(`Invalid public key: ${key.publicKey}`);
+        }
+        hash.update(Buffer.from(key.publicKey, "hex")).update(encodeI64(key.notBefore));
+        if (key.notAfter === null) {
+            hash.update(Buffer.from([0]));
+        } else {
+            hash.update(Buffer.from([1])).update(encodeI64(key.notAfter));
+        }
+    }
+
+    return hash.digest("hex");
+}
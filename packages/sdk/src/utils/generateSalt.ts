@@ -0,0 +1,17 @@
+/**
+ * Commit-reveal salt generation utility
+ *
+ * Generates random salts for salted on-chain query commitments.
+ */
+
+import { randomBytes } from "crypto";
+import { SALT_LENGTH } from "./hashQuery";
+
+/**
+ * Generate a random commit-reveal salt
+ *
+ * @returns 32 random bytes as a hex string
+ */
+export function generateSalt(): string {
+    return randomBytes(SALT_LENGTH).toString("hex");
+}
@@ -1,27 +1,152 @@
 /**
  * Single query hashing utility
  *
- * Creates deterministic SHA-256 hashes of queries for identification.
+ * Implements the canonical, versioned query hash shared with the proxy and
+ * the on-chain coordinator. The encoding is specified in
+ * `test-vectors/README.md` at the repository root.
  */
 
 import { createHash } from "crypto";
 import { Query } from "../types";
+import { RpcMethod } from "../enums";
+import { decodeBase58 } from "./decodeBase58";
+
+/** Domain separation prefix for query hashes */
+export const QUERY_HASH_DOMAIN = "privacy-rpc/query-hash";
+
+/** Current version of the canonical encoding */
+export const QUERY_HASH_VERSION = 1;
+
+/** Length in bytes of a commit-reveal salt */
+export const SALT_LENGTH = 32;
+
+const METHOD_TAGS: Record<RpcMethod, number> = {
+    [RpcMethod.GetBalance]: 0x01,
+    [RpcMethod.GetAccountInfo]: 0x02,
+    [RpcMethod.GetTransaction]: 0x03,
+    [RpcMethod.GetTokenAccountBalance]: 0x04,
+    [RpcMethod.GetBlockHeight]: 0x05,
+    [RpcMethod.GetMultipleAccounts]: 0x06,
+};
+
+const COMMITMENT_TAGS: Record<string, number> = {
+    processed: 0x00,
+    confirmed: 0x01,
+    finalized: 0x02,
+};
+
+function decodeFixed(value: string | undefined, length: number, what: string): Uint8Array {
+    if (value === undefined) {
+        throw new Error(`Missing ${what}`);
+    }
+    const bytes = decodeBase58(value);
+    if (bytes.length !== length) {
+        throw new Error(`Invalid ${what} '${value}': expected ${length} bytes`);
+    }
+    return bytes;
+}
+
+function primaryParam(query: Query): string | undefined {
+    if (query.pubkey !== undefined) return query.pubkey;
+    return typeof query.params === "string" ? query.params : undefined;
+}
+
+/**
+ * Encode a query into its canonical byte representation
+ *
+ * The query `id` is excluded: it is a client-side correlation handle.
+ *
+ * @param query - The query to encode
+ * @returns Canonical encoding bytes
+ * @throws Error if the query cannot be encoded (bad key, unknown commitment)
+ */
+export function encodeQuery(query: Query): Buffer {
+    // The proxy executes every method at confirmed when none is given
+    const commitment = query.commitment ?? "confirmed";
+    const commitmentTag = COMMITMENT_TAGS[commitment];
+    if (commitmentTag === undefined) {
+        throw new Error(`Unsupported commitment '${commitment}'`);
+    }
+
+    const parts: Uint8Array[] = [
+        Buffer.from(QUERY_HASH_DOMAIN, "ascii"),
+        Uint8Array.of(QUERY_HASH_VERSION, METHOD_TAGS[query.method], commitmentTag),
+    ];
+
+    switch (query.method) {
+        case RpcMethod.GetBalance:
+        case RpcMethod.GetAccountInfo:
+        case RpcMethod.GetTokenAccountBalance:
+            parts.push(decodeFixed(primaryParam(query), 32, "pubkey"));
+            break;
+        case RpcMethod.GetTransaction:
+            parts.push(decodeFixed(primaryParam(query), 64, "signature"));
+            break;
+        case RpcMethod.GetBlockHeight:
+            break;
+        case RpcMethod.GetMultipleAccounts: {
+            const keys = typeof query.params === "string" ? [query.params] : query.params ?? [];
+            if (keys.length === 0 || keys.length > 0xffff) {
+                throw new Error(`Invalid pubkey count: ${keys.length}`);
+            }
+            const count = Buffer.alloc(2);
+            count.writeUInt16LE(keys.length);
+            parts.push(count);
+            for (const key of keys) {
+                parts.push(decodeFixed(key, 32, "pubkey"));
+            }
+            break;
+        }
+    }
+
+    return Buffer.concat(parts);
+}
 
 /**
  * Hash a single query for identification
  *
- * Creates a deterministic SHA-256 hash of the query parameters.
- * This allows verification without exposing the actual query details.
+ * Computes the canonical SHA-256 query hash, which is what gets committed
+ * on-chain through `submit_query` and what the proxy verifies against.
  *
  * @param query - The query to hash
  * @returns SHA-256 hash as hex string
  */
 export function hashQuery(query: Query): string {
-    const data = JSON.stringify({
-        method: query.method,
-        pubkey: query.pubkey,
-        commitment: query.commitment,
-    });
+    return createHash("sha256").update(encodeQuery(query)).digest("hex");
+}
+
+/**
+ * Hash a query for on-chain commitment
+ *
+ * Queries commit `SHA-256(salt || encoding)` so the on-chain hash cannot be
+ * reversed with a dictionary of popular accounts. The proxy rejects
+ * unsalted commitments, so a salt is required. A query with an
+ * `encryptionKey` appends the key, so it cannot be swapped after commitment.
+ *
+ * @param query - The query to hash
+ * @returns SHA-256 commitment as hex string
+ * @throws Error if the salt is missing or not 32 hex-encoded bytes, or the
+ *     encryption key is not 32 hex-encoded bytes
+ */
+export function hashQueryCommitment(query: Query): string {
+    if (query.salt === undefined) {
+        throw new Error("Missing salt: on-chain commitments must be salted");
+    }
+
+    if (!/^[0-9a-fA-F]*$/.test(query.salt) || query.salt.length !== SALT_LENGTH * 2) {
+        throw new Error(`Invalid salt: expected ${SALT_LENGTH} hex-encoded bytes`);
+    }
+
+    const hash = createHash("sha256")
+        .update(Buffer.from(query.salt, "hex"))
+        .update(encodeQuery(query));
+
+    if (query.encryptionKey !== undefined) {
+        if (!/^[0-9a-fA-F]{64}$/.test(query.encryptionKey)) {
+            throw new Error("Invalid encryption key: expected 32 hex-encoded bytes");
+        }
+        hash.update(Buffer.from(query.encryptionKey, "hex"));
+    }
 
-    return createHash("sha256").update(data).digest("hex");
+    return hash.digest("hex");
 }
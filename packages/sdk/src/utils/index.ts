@@ -5,6 +5,22 @@
  */
 
 export { generateQueryId } from "./generateQueryId";
-export { hashQuery } from "./hashQuery";
+export { generateSalt } from "./generateSalt";
+export { generateResultTicket } from "./resultTicket";
+export type { ResultTicket } from "./resultTicket";
+export {
+    hashQuery,
+    hashQueryCommitment,
+    encodeQuery,
+    QUERY_HASH_DOMAIN,
+    QUERY_HASH_VERSION,
+    SALT_LENGTH,
+} from "./hashQuery";
+export { decodeBase58 } from "./decodeBase58";
+export {
+    fingerprintEncryptionKeys,
+    KEY_FINGERPRINT_DOMAIN,
+    KEY_FINGERPRINT_VERSION,
+} from "./fingerprintEncryptionKeys";
 export { hashBatch } from "./hashBatch";
 export { verifyBatchHash } from "./verifyBatchHash";
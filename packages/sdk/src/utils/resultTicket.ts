@@ -0,0 +1,34 @@
+/**
+ * Result ticket utility
+ *
+ * Creates capabilities for ticketed result delivery. The ticket stays with
+ * the submitter; its SHA-256 is used as the query's commit-reveal salt, which
+ * binds the ticket to the on-chain commitment.
+ */
+
+import { createHash, randomBytes } from "crypto";
+import { SALT_LENGTH } from "./hashQuery";
+
+/**
+ * A result ticket and the salt it commits to
+ */
+export interface ResultTicket {
+    /** Hex-encoded ticket, presented to `GET /results/{ticket}` */
+    ticket: string;
+
+    /** Hex-encoded salt to set on the query */
+    salt: string;
+}
+
+/**
+ * Generate a random result ticket
+ *
+ * @returns The ticket and the salt derived from it
+ */
+export function generateResultTicket(): ResultTicket {
+    const ticket = randomBytes(SALT_LENGTH);
+    return {
+        ticket: ticket.toString("hex"),
+        salt: createHash("sha256").update(ticket).digest("hex"),
+    };
+}